@@ -0,0 +1,136 @@
+//! Converts the BMP assets of the game to the QOI format, which is decoded by
+//! `stm32f7_discovery::lcd::qoi`. The converted images are written to `OUT_DIR` and embedded
+//! with `include_bytes!`.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The images that are embedded in the firmware.
+const ASSETS: &[&str] = &[
+    "WaterBig3Small.bmp",
+    "StartScreen.bmp",
+];
+
+fn main() {
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    convert_assets(Path::new("."), &out);
+    println!("cargo:rerun-if-changed=build.rs");
+}
+
+/// Converts the `ASSETS` in the directory `dir` and writes them to `out`.
+///
+/// The build script of the simulator includes this file as module, so the assets are only
+/// listed once.
+pub fn convert_assets(dir: &Path, out: &Path) {
+    for asset in ASSETS {
+        let source = dir.join(asset);
+        let data = fs::read(&source).unwrap_or_else(|e| panic!("reading {} failed: {}", asset, e));
+        let (width, height, pixels) =
+            read_bmp(&data).unwrap_or_else(|e| panic!("{} is not supported: {}", asset, e));
+        let encoded = encode_qoi(width, height, &pixels);
+        fs::write(out.join(Path::new(asset).with_extension("qoi")), encoded).unwrap();
+        println!("cargo:rerun-if-changed={}", source.display());
+    }
+}
+
+/// Reads an uncompressed 24 or 32 bit BMP file and returns its RGB pixels row by row, starting
+/// with the top row.
+fn read_bmp(data: &[u8]) -> Result<(u32, u32, Vec<[u8; 3]>), String> {
+    let u16_at = |offset: usize| u16::from(data[offset]) | u16::from(data[offset + 1]) << 8;
+    let u32_at = |offset: usize| u32::from(u16_at(offset)) | u32::from(u16_at(offset + 2)) << 16;
+    if data.len() < 54 || &data[0..2] != b"BM" {
+        return Err("no BMP file".into());
+    }
+    let pixel_offset = u32_at(10) as usize;
+    let width = u32_at(18) as i32;
+    let height = u32_at(22) as i32;
+    let bits_per_pixel = u16_at(28);
+    let compression = u32_at(30);
+    if compression != 0 || (bits_per_pixel != 24 && bits_per_pixel != 32) {
+        return Err(format!(
+            "{} bits per pixel with compression {}",
+            bits_per_pixel, compression
+        ));
+    }
+    if width <= 0 || height == 0 {
+        return Err("invalid dimensions".into());
+    }
+    let (width, top_down, height) = (width as usize, height < 0, height.abs() as usize);
+    let bytes_per_pixel = usize::from(bits_per_pixel / 8);
+    let stride = (width * bytes_per_pixel + 3) / 4 * 4;
+    if data.len() < pixel_offset + stride * height {
+        return Err("truncated pixel data".into());
+    }
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let row = if top_down { y } else { height - 1 - y };
+        let row = &data[pixel_offset + row * stride..];
+        for x in 0..width {
+            let pixel = &row[x * bytes_per_pixel..];
+            pixels.push([pixel[2], pixel[1], pixel[0]]);
+        }
+    }
+    Ok((width as u32, height as u32, pixels))
+}
+
+/// Encodes opaque RGB pixels as QOI image with three channels.
+fn encode_qoi(width: u32, height: u32, pixels: &[[u8; 3]]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(b"qoif");
+    out.extend_from_slice(&width.to_be_bytes());
+    out.extend_from_slice(&height.to_be_bytes());
+    out.extend_from_slice(&[3, 0]);
+
+    let hash = |p: [u8; 3]| {
+        (usize::from(p[0]) * 3 + usize::from(p[1]) * 5 + usize::from(p[2]) * 7 + 255 * 11) % 64
+    };
+    let mut index = [[0u8; 3]; 64];
+    // the index starts with transparent black, which never matches an opaque pixel
+    let mut index_valid = [false; 64];
+    let mut previous = [0u8; 3];
+    let mut run = 0u8;
+    for (i, &pixel) in pixels.iter().enumerate() {
+        if pixel == previous {
+            run += 1;
+            if run == 62 || i == pixels.len() - 1 {
+                out.push(0xc0 | (run - 1));
+                run = 0;
+            }
+            continue;
+        }
+        if run > 0 {
+            out.push(0xc0 | (run - 1));
+            run = 0;
+        }
+
+        let slot = hash(pixel);
+        if index_valid[slot] && index[slot] == pixel {
+            out.push(slot as u8);
+        } else {
+            index[slot] = pixel;
+            index_valid[slot] = true;
+            let dr = pixel[0].wrapping_sub(previous[0]) as i8;
+            let dg = pixel[1].wrapping_sub(previous[1]) as i8;
+            let db = pixel[2].wrapping_sub(previous[2]) as i8;
+            let (dr_dg, db_dg) = (dr.wrapping_sub(dg), db.wrapping_sub(dg));
+            if within(dr, 2) && within(dg, 2) && within(db, 2) {
+                out.push(0x40 | ((dr + 2) as u8) << 4 | ((dg + 2) as u8) << 2 | (db + 2) as u8);
+            } else if within(dg, 32) && within(dr_dg, 8) && within(db_dg, 8) {
+                out.push(0x80 | (dg + 32) as u8);
+                out.push(((dr_dg + 8) as u8) << 4 | (db_dg + 8) as u8);
+            } else {
+                out.extend_from_slice(&[0xfe, pixel[0], pixel[1], pixel[2]]);
+            }
+        }
+        previous = pixel;
+    }
+    out.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+    out
+}
+
+/// Returns `true` if `-bias <= value < bias`, which is the range of a difference with a bias.
+fn within(value: i8, bias: i8) -> bool {
+    value >= -bias && value < bias
+}
@@ -0,0 +1,15 @@
+//! Converts the images of the game like its build script, so that the included `display.rs`
+//! finds them in `OUT_DIR`.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+#[allow(dead_code)]
+#[path = "../build.rs"]
+mod game;
+
+fn main() {
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    game::convert_assets(Path::new(".."), &out);
+    println!("cargo:rerun-if-changed=../build.rs");
+}
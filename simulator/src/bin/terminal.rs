@@ -0,0 +1,40 @@
+//! Plays the game in the terminal.
+//!
+//! Usage: `cargo run --bin terminal [--server] [<board ip>]`. Without an address the opponent is
+//! the computer, otherwise the board at the address. With `--server` we shoot first, so the
+//! board must be started as the client.
+
+use schiffe_versenken_simulator::game;
+use schiffe_versenken_simulator::opponent::{Computer, Udp, PORT};
+use schiffe_versenken_simulator::{Connection, TerminalUi};
+use std::env;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::process;
+
+fn play<C: Connection>(connection: C) -> io::Result<()> {
+    let ui = TerminalUi::new()?;
+    game::init_new_game(ui, connection).run_game()
+}
+
+fn main() -> io::Result<()> {
+    let mut is_server = false;
+    let mut board = None;
+    for arg in env::args().skip(1) {
+        if arg == "--server" {
+            is_server = true;
+            continue;
+        }
+        match arg.parse::<IpAddr>() {
+            Ok(ip) => board = Some(SocketAddr::new(ip, PORT)),
+            Err(_) => {
+                eprintln!("usage: terminal [--server] [<board ip>]");
+                process::exit(2);
+            }
+        }
+    }
+    match board {
+        Some(board) => play(Udp::connect(board, is_server)?),
+        None => play(Computer::new()),
+    }
+}
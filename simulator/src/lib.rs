@@ -0,0 +1,44 @@
+//! A host build of the game UI.
+//!
+//! The [`Simulator`] is a display backend that draws both layers into memory and replays a
+//! [`TouchScript`] instead of reading the touchscreen. The `display.rs` and `animations.rs` files
+//! of the game are compiled unchanged against it, so screens can be developed and tested
+//! without a board.
+//!
+//! The [`TerminalUi`] is a second front end, which plays the game in a terminal. The turns in
+//! `game.rs` and the rules in `gameboard.rs` are the ones of the firmware; the other player is
+//! reached through one of the [`Connection`]s in [`opponent`].
+
+#![feature(alloc)]
+
+extern crate alloc;
+
+#[path = "../../src/animations.rs"]
+mod animations;
+#[path = "../../src/display.rs"]
+pub mod display;
+#[path = "../../src/gameboard.rs"]
+pub mod gameboard;
+#[path = "../../src/game.rs"]
+pub mod game;
+pub mod opponent;
+pub mod scenes;
+pub mod script;
+#[path = "../../src/ships.rs"]
+pub mod ships;
+pub mod simulator;
+pub mod terminal;
+
+/// The network code of the firmware that does not need the ethernet interface.
+#[path = "../../src/network"]
+pub mod network {
+    mod connection;
+    pub mod packets;
+
+    pub use self::connection::Connection;
+}
+
+pub use crate::network::Connection;
+pub use crate::script::TouchScript;
+pub use crate::simulator::Simulator;
+pub use crate::terminal::TerminalUi;
@@ -0,0 +1,20 @@
+//! Renders all scenes of the game UI to PNG files.
+//!
+//! Usage: `cargo run [output directory]`, the images are written to `screens` by default.
+
+use schiffe_versenken_simulator::scenes::{self, SCENES};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+fn main() -> io::Result<()> {
+    let dir = PathBuf::from(env::args().nth(1).unwrap_or_else(|| "screens".into()));
+    fs::create_dir_all(&dir)?;
+    for &(name, scene) in SCENES {
+        let path = dir.join(name).with_extension("png");
+        scenes::render(scene).backend().save_png(&path)?;
+        println!("{}", path.display());
+    }
+    Ok(())
+}
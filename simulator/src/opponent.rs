@@ -0,0 +1,298 @@
+//! The opponents of the terminal front end.
+//!
+//! Both implement the [`Connection`] of the firmware, so the turns in `game.rs` run unchanged
+//! against them.
+
+use crate::gameboard::{Block, Board};
+use crate::network::packets::{FeedbackPacket, PingPacket, Serializable, ShootPacket};
+use crate::network::Connection;
+use crate::ships::Ship;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The UDP port of the game, which both boards use.
+pub const PORT: u16 = 1337;
+/// The sizes of the ships that every player places, in the order of `Board::initial_setup`.
+const FLEET: [u8; 5] = [5, 4, 3, 3, 2];
+/// How long a receive waits when no packet arrived, the game polls in a loop.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A board running the firmware, which is reached over UDP.
+///
+/// The packets are serialized like the firmware does, so the board sees a second board. With
+/// IPv4 the boards use fixed addresses, so this host must have the address of the other board:
+/// `192.168.42.2` if the board is the server and `192.168.42.1` otherwise.
+pub struct Udp {
+    socket: UdpSocket,
+    board: SocketAddr,
+    shoots_first: bool,
+}
+
+impl Udp {
+    /// Binds the game port and exchanges the packets with the board at the passed address.
+    ///
+    /// If we `shoot_first`, we take the role of the server board, so the board must be the
+    /// client.
+    pub fn connect(board: SocketAddr, shoots_first: bool) -> io::Result<Udp> {
+        let socket = UdpSocket::bind(("0.0.0.0", PORT))?;
+        socket.set_nonblocking(true)?;
+        Ok(Udp {
+            socket,
+            board,
+            shoots_first,
+        })
+    }
+
+    fn send(&mut self, data: &[u8]) {
+        // like on the board, a lost packet is not sent again
+        let _ = self.socket.send_to(data, self.board);
+    }
+
+    /// Returns the next game packet of the board. Pings are answered, like the firmware does
+    /// for its diagnostics screen.
+    fn recv(&mut self) -> Option<Vec<u8>> {
+        let mut buffer = [0; 64];
+        loop {
+            let (len, sender) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(_) => {
+                    thread::sleep(POLL_INTERVAL);
+                    return None;
+                }
+            };
+            if sender.ip() != self.board.ip() {
+                continue;
+            }
+            let data = &buffer[..len];
+            if len != PingPacket::len() {
+                return Some(data.to_vec());
+            }
+            let ping = PingPacket::deserialize(data);
+            if !ping.is_reply {
+                self.send(&ping.reply().serialize());
+            }
+        }
+    }
+}
+
+impl Connection for Udp {
+    fn shoots_first(&self) -> bool {
+        self.shoots_first
+    }
+
+    fn send_shoot(&mut self, shoot: ShootPacket) {
+        self.send(&shoot.serialize());
+    }
+
+    fn recv_shoot(&mut self) -> Option<ShootPacket> {
+        self.recv()
+            .filter(|data| data.len() == ShootPacket::len())
+            .map(|data| ShootPacket::deserialize(&data))
+    }
+
+    fn send_feedback(&mut self, feedback: FeedbackPacket) {
+        self.send(&feedback.serialize());
+    }
+
+    fn recv_feedback(&mut self) -> Option<FeedbackPacket> {
+        self.recv()
+            .filter(|data| data.len() == FeedbackPacket::len())
+            .map(|data| FeedbackPacket::deserialize(&data))
+    }
+}
+
+/// A computer player with a random fleet.
+///
+/// It shoots at random fields until it hits a ship and then at the fields next to the hits
+/// until the ship is sunk. Our shots are checked by a `Board` with the rules of the firmware.
+pub struct Computer {
+    board: Board,
+    random: XorShift,
+    shot: [[bool; 10]; 10],
+    targets: Vec<Block>,
+    last_shot: Option<Block>,
+    feedback: Option<FeedbackPacket>,
+}
+
+impl Computer {
+    /// Creates a computer player and places its ships.
+    pub fn new() -> Computer {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.subsec_nanos())
+            .unwrap_or(0);
+        Computer::with_seed(u64::from(seed))
+    }
+
+    /// Creates a computer player that always places and shoots the same way for the same seed.
+    pub fn with_seed(seed: u64) -> Computer {
+        let mut random = XorShift::new(seed);
+        let board = random_board(&mut random);
+        Computer {
+            board,
+            random,
+            shot: [[false; 10]; 10],
+            targets: Vec::new(),
+            last_shot: None,
+            feedback: None,
+        }
+    }
+
+    fn random_field(&mut self) -> Block {
+        let free = self.shot.iter().flatten().filter(|&&shot| !shot).count();
+        let index = self.random.below(free);
+        let (x, y) = (0..10)
+            .flat_map(|x| (0..10).map(move |y| (x, y)))
+            .filter(|&(x, y)| !self.shot[x][y])
+            .nth(index)
+            .unwrap();
+        Block {
+            x: x as u8 + 1,
+            y: y as u8 + 1,
+        }
+    }
+}
+
+impl Default for Computer {
+    fn default() -> Computer {
+        Computer::new()
+    }
+}
+
+impl Connection for Computer {
+    /// The computer answers at once, so we always shoot first.
+    fn shoots_first(&self) -> bool {
+        true
+    }
+
+    fn send_shoot(&mut self, shoot: ShootPacket) {
+        let block = Block {
+            x: shoot.column,
+            y: shoot.line,
+        };
+        let (hit, sunk) = self.board.shoot_at(block);
+        let win = self.board.check_win();
+        self.feedback = Some(FeedbackPacket::new(hit, sunk, win));
+    }
+
+    fn recv_shoot(&mut self) -> Option<ShootPacket> {
+        let block = loop {
+            match self.targets.pop() {
+                Some(block) if !self.shot[block.x as usize - 1][block.y as usize - 1] => {
+                    break block
+                }
+                Some(_) => {}
+                None => break self.random_field(),
+            }
+        };
+        self.shot[block.x as usize - 1][block.y as usize - 1] = true;
+        self.last_shot = Some(block);
+        Some(ShootPacket::new(block.y, block.x))
+    }
+
+    fn send_feedback(&mut self, feedback: FeedbackPacket) {
+        let last_shot = match self.last_shot.take() {
+            Some(block) => block,
+            None => return,
+        };
+        if feedback.sunk != 0 {
+            self.targets.clear();
+        } else if feedback.hit {
+            let (x, y) = (last_shot.x, last_shot.y);
+            let neighbours = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)];
+            for &(x, y) in neighbours.iter() {
+                if x >= 1 && x <= 10 && y >= 1 && y <= 10 {
+                    self.targets.push(Block { x, y });
+                }
+            }
+        }
+    }
+
+    fn recv_feedback(&mut self) -> Option<FeedbackPacket> {
+        self.feedback.take()
+    }
+}
+
+/// Places the ships of the fleet at random positions, without touching each other.
+fn random_board(random: &mut XorShift) -> Board {
+    let mut placed = [[false; 10]; 10];
+    let mut ships = Vec::new();
+    for &size in FLEET.iter() {
+        loop {
+            let vertical = random.below(2) == 0;
+            let (width, height) = if vertical { (1, size) } else { (size, 1) };
+            let x = random.below(11 - width as usize);
+            let y = random.below(11 - height as usize);
+            let fields =
+                || (0..size as usize).map(move |i| if vertical { (x, y + i) } else { (x + i, y) });
+            // the rules of the setup forbid ships in the eight neighbours of a field
+            let free = fields().all(|(x, y)| {
+                let columns = x.saturating_sub(1)..(x + 2).min(10);
+                columns
+                    .flat_map(|k| (y.saturating_sub(1)..(y + 2).min(10)).map(move |l| (k, l)))
+                    .all(|(k, l)| !placed[k][l])
+            });
+            if free {
+                for (x, y) in fields() {
+                    placed[x][y] = true;
+                }
+                ships.push(Ship::new(size, x as u8, y as u8, vertical));
+                break;
+            }
+        }
+    }
+    Board::new(ships, [[false; 10]; 10], [[false; 10]; 10], placed)
+}
+
+/// A small pseudo random number generator, the computer player needs no better randomness.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> XorShift {
+        // the state must never be zero
+        XorShift(seed | 1)
+    }
+
+    /// Returns a number from `0` to `bound - 1`.
+    fn below(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % bound as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computer_loses_after_all_ships_are_hit() {
+        let mut computer = Computer::with_seed(42);
+        let mut hits = 0;
+        for x in 1..=10 {
+            for y in 1..=10 {
+                computer.send_shoot(ShootPacket::new(y, x));
+                let feedback = computer.recv_feedback().unwrap();
+                hits += feedback.hit as usize;
+                assert_eq!(feedback.you_win, hits == 17);
+            }
+        }
+        assert_eq!(hits, 17);
+    }
+
+    #[test]
+    fn computer_never_shoots_a_field_twice() {
+        let mut computer = Computer::with_seed(7);
+        let mut shot = [[false; 10]; 10];
+        for _ in 0..100 {
+            let shoot = computer.recv_shoot().unwrap();
+            let (x, y) = (shoot.column as usize - 1, shoot.line as usize - 1);
+            assert!(!shot[x][y]);
+            shot[x][y] = true;
+            computer.send_feedback(FeedbackPacket::new(x % 3 == 0, 0, false));
+        }
+    }
+}
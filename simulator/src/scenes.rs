@@ -0,0 +1,80 @@
+//! The screens that the simulator renders and the golden image tests compare.
+
+use crate::display::{Display, Ui, WATER_BLUE};
+use crate::{Simulator, TouchScript};
+
+/// Draws a screen on a new display.
+pub type Scene = fn(&mut Display<Simulator>);
+
+/// All scenes with the names of their image files.
+pub const SCENES: &[(&str, Scene)] = &[
+    ("start_screen", start_screen),
+    ("board", board),
+    ("setup_ship", setup_ship),
+    ("status_panel", status_panel),
+    ("hit_animation", hit_animation),
+    ("settings_screen", settings_screen),
+    ("win_screen", win_screen),
+    ("lose_screen", lose_screen),
+];
+
+/// Creates a display on the simulator that replays the passed touches.
+pub fn display(script: TouchScript) -> Display<Simulator> {
+    Display::new(Simulator::new(WATER_BLUE, script))
+}
+
+/// Draws the scene on a new display and presents it.
+pub fn render(scene: Scene) -> Display<Simulator> {
+    let mut display = display(TouchScript::new());
+    scene(&mut display);
+    display.present();
+    display
+}
+
+fn start_screen(display: &mut Display<Simulator>) {
+    display.show_start_screen();
+}
+
+fn board(display: &mut Display<Simulator>) {
+    display.print_background();
+    display.print_ship(5, 0, 0, false);
+    display.print_ship(4, 2, 3, true);
+    display.print_ship(3, 6, 5, false);
+    display.print_ship(2, 9, 8, true);
+    display.print_hit(1, 1);
+    display.print_hit(3, 5);
+    display.print_miss(5, 5);
+    display.print_miss(10, 1);
+}
+
+fn setup_ship(display: &mut Display<Simulator>) {
+    display.print_background();
+    display.print_ship(5, 0, 0, false);
+    display.setup_ship(4);
+}
+
+fn status_panel(display: &mut Display<Simulator>) {
+    display.print_background();
+    display.update_status_text((1, 2, 1, 0), (0, 1, 2, 1));
+    display.print_text_on_display_layer2("enemy's turn".into());
+}
+
+fn hit_animation(display: &mut Display<Simulator>) {
+    display.print_background();
+    display.animate_hit(4, 6);
+    // the explosion is at its largest in the fourth frame
+    display.backend_mut().advance(3 * 70);
+    display.update_animations();
+}
+
+fn settings_screen(display: &mut Display<Simulator>) {
+    display.show_settings_screen();
+}
+
+fn win_screen(display: &mut Display<Simulator>) {
+    display.show_win_screen();
+}
+
+fn lose_screen(display: &mut Display<Simulator>) {
+    display.show_lose_screen();
+}
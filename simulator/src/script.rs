@@ -0,0 +1,129 @@
+//! Scripted touch input.
+
+use arrayvec::ArrayVec;
+use stm32f7_discovery::touch::Touch;
+
+/// The duration of a tap, which is well below the maximal tap duration of the touch tracker.
+pub const TAP_MS: usize = 100;
+/// The interval between two touch samples of a swipe.
+const SWIPE_STEP_MS: usize = 20;
+
+/// The touch points of a script from a point in time until the next change.
+#[derive(Debug, Clone)]
+struct Frame {
+    at_ms: usize,
+    touches: ArrayVec<[Touch; 5]>,
+}
+
+/// A list of touch events that are replayed on the clock of the [`Simulator`].
+///
+/// The events are added with the `with_*` methods and may be added in any order. Each event
+/// sets the touch points from its start time until the next event.
+///
+/// [`Simulator`]: crate::Simulator
+#[derive(Debug, Clone, Default)]
+pub struct TouchScript {
+    frames: Vec<Frame>,
+}
+
+impl TouchScript {
+    /// Creates a script without touches.
+    pub fn new() -> TouchScript {
+        TouchScript::default()
+    }
+
+    /// Puts fingers on the passed points at `at_ms`.
+    ///
+    /// Up to five points are used, like the touch controller of the board reports.
+    pub fn with_touches(mut self, at_ms: usize, points: &[(u16, u16)]) -> TouchScript {
+        let touches = points
+            .iter()
+            .take(5)
+            .map(|&(x, y)| Touch { x, y })
+            .collect();
+        // later events with the same start replace earlier ones
+        let index = self
+            .frames
+            .iter()
+            .take_while(|frame| frame.at_ms <= at_ms)
+            .count();
+        self.frames.insert(index, Frame { at_ms, touches });
+        self
+    }
+
+    /// Lifts all fingers at `at_ms`.
+    pub fn with_release(self, at_ms: usize) -> TouchScript {
+        self.with_touches(at_ms, &[])
+    }
+
+    /// Taps the point `(x, y)` at `at_ms`.
+    pub fn with_tap(self, at_ms: usize, x: u16, y: u16) -> TouchScript {
+        self.with_touches(at_ms, &[(x, y)])
+            .with_release(at_ms + TAP_MS)
+    }
+
+    /// Moves one finger on a straight line from `from` to `to`, starting at `at_ms` and lifting it
+    /// after `duration_ms`.
+    pub fn with_swipe(
+        mut self,
+        at_ms: usize,
+        from: (u16, u16),
+        to: (u16, u16),
+        duration_ms: usize,
+    ) -> TouchScript {
+        let steps = (duration_ms / SWIPE_STEP_MS).max(1);
+        for step in 0..=steps {
+            let x = interpolate(from.0, to.0, step, steps);
+            let y = interpolate(from.1, to.1, step, steps);
+            self = self.with_touches(at_ms + step * duration_ms / steps, &[(x, y)]);
+        }
+        self.with_release(at_ms + duration_ms + SWIPE_STEP_MS)
+    }
+
+    /// Returns the touch points at `now_ms`.
+    pub fn touches(&self, now_ms: usize) -> ArrayVec<[Touch; 5]> {
+        self.frames
+            .iter()
+            .take_while(|frame| frame.at_ms <= now_ms)
+            .last()
+            .map(|frame| frame.touches.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns the time of the last event, after which the touch points don't change anymore.
+    pub fn end_ms(&self) -> usize {
+        self.frames.last().map_or(0, |frame| frame.at_ms)
+    }
+}
+
+fn interpolate(from: u16, to: u16, step: usize, steps: usize) -> u16 {
+    let (from, to) = (from as isize, to as isize);
+    (from + (to - from) * step as isize / steps as isize) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_can_be_added_in_any_order() {
+        let script = TouchScript::new()
+            .with_tap(500, 10, 20)
+            .with_tap(100, 30, 40);
+        assert!(script.touches(50).is_empty());
+        assert_eq!(script.touches(150)[..], [Touch { x: 30, y: 40 }]);
+        assert!(script.touches(200).is_empty());
+        assert_eq!(script.touches(599)[..], [Touch { x: 10, y: 20 }]);
+        assert!(script.touches(600).is_empty());
+        assert_eq!(script.end_ms(), 600);
+    }
+
+    #[test]
+    fn swipes_move_the_finger() {
+        let script = TouchScript::new().with_swipe(0, (100, 50), (300, 50), 200);
+        assert_eq!(script.touches(0)[..], [Touch { x: 100, y: 50 }]);
+        assert_eq!(script.touches(100)[..], [Touch { x: 200, y: 50 }]);
+        assert_eq!(script.touches(200)[..], [Touch { x: 300, y: 50 }]);
+        assert!(script.touches(220).is_empty());
+    }
+}
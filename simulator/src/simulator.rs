@@ -0,0 +1,118 @@
+//! A display backend that renders into memory.
+
+use crate::display::Backend;
+use crate::script::TouchScript;
+use arrayvec::ArrayVec;
+use png::HasParameters;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use stm32f7_discovery::lcd::{Color, Layer, MemoryFramebuffer, HEIGHT, WIDTH};
+use stm32f7_discovery::touch::Touch;
+
+/// The time that passes between two touch samples.
+///
+/// The clock of the simulator only advances when the display samples the touchscreen or when
+/// [`Simulator::advance`] is called, so every run produces the same frames.
+pub const SAMPLE_PERIOD_MS: usize = 10;
+
+/// Renders the two layers of the display into memory and replays scripted touches.
+///
+/// Like on the board, the layers are composed above the background color and only become
+/// visible when the display presents them. Unlike on the board, layer 2 stores the exact colors
+/// instead of reducing them to the color lookup table, which makes no difference as long as only
+/// the colors of the palette are drawn.
+pub struct Simulator {
+    layer1: Layer<MemoryFramebuffer>,
+    layer2: Layer<MemoryFramebuffer>,
+    background: Color,
+    screen: Vec<Color>,
+    script: TouchScript,
+    now_ms: usize,
+}
+
+impl Simulator {
+    /// Creates a simulator with transparent layers above the passed background color.
+    pub fn new(background: Color, script: TouchScript) -> Simulator {
+        Simulator {
+            layer1: Layer::new(MemoryFramebuffer::new()),
+            layer2: Layer::new(MemoryFramebuffer::new()),
+            background,
+            screen: vec![background; WIDTH * HEIGHT],
+            script,
+            now_ms: 0,
+        }
+    }
+
+    /// Lets the passed number of milliseconds pass, e.g. to finish the running animations.
+    pub fn advance(&mut self, ms: usize) {
+        self.now_ms += ms;
+    }
+
+    /// Returns whether all events of the touch script were replayed.
+    pub fn script_finished(&self) -> bool {
+        self.now_ms > self.script.end_ms()
+    }
+
+    /// Returns the colors of the presented pixels, row by row.
+    pub fn pixels(&self) -> &[Color] {
+        &self.screen
+    }
+
+    /// Returns the presented pixels as rows of RGB bytes.
+    pub fn rgb(&self) -> Vec<u8> {
+        let mut rgb = Vec::with_capacity(3 * self.screen.len());
+        for color in &self.screen {
+            rgb.extend_from_slice(&[color.red, color.green, color.blue]);
+        }
+        rgb
+    }
+
+    /// Writes the presented pixels as PNG image.
+    pub fn write_png<W: Write>(&self, writer: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(writer, WIDTH as u32, HEIGHT as u32);
+        encoder.set(png::ColorType::RGB).set(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.rgb())
+    }
+
+    /// Saves the presented pixels as PNG file.
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        self.write_png(file)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+    }
+}
+
+impl Backend for Simulator {
+    type Layer1 = MemoryFramebuffer;
+    type Layer2 = MemoryFramebuffer;
+
+    fn layer1(&mut self) -> &mut Layer<MemoryFramebuffer> {
+        &mut self.layer1
+    }
+
+    fn layer2(&mut self) -> &mut Layer<MemoryFramebuffer> {
+        &mut self.layer2
+    }
+
+    /// Blends both layers over the background color, like the LCD controller does with the
+    /// default blending factors.
+    fn present(&mut self) {
+        let layer1 = self.layer1.framebuffer().pixels();
+        let layer2 = self.layer2.framebuffer().pixels();
+        for (i, pixel) in self.screen.iter_mut().enumerate() {
+            *pixel = layer2[i].blend_over(layer1[i].blend_over(self.background));
+        }
+        self.layer1.take_dirty_regions();
+        self.layer2.take_dirty_regions();
+    }
+
+    fn touches(&mut self) -> Option<ArrayVec<[Touch; 5]>> {
+        self.now_ms += SAMPLE_PERIOD_MS;
+        Some(self.script.touches(self.now_ms))
+    }
+
+    fn ms(&self) -> usize {
+        self.now_ms
+    }
+}
@@ -0,0 +1,490 @@
+//! A terminal front end for the game.
+//!
+//! [`TerminalUi`] implements the `Ui` trait of the game with a text rendering of the board and
+//! keyboard input. The keys are translated to the taps and gestures of the touchscreen, so the
+//! turns in `game.rs` and the rules in `gameboard.rs` run unchanged.
+
+use crate::display::{Input, Ui, CONFIRM_BUTTON};
+use std::collections::VecDeque;
+use std::io::{self, Stdout, Write};
+use std::process;
+use std::thread;
+use std::time::Duration;
+use stm32f7_discovery::lcd::Point;
+use stm32f7_discovery::touch::{Gesture, SwipeDirection, Touch};
+use termion::event::Key;
+use termion::input::{Keys, TermRead};
+use termion::raw::{IntoRawMode, RawTerminal};
+use termion::{clear, color, cursor, style, AsyncReader};
+
+/// The size of a field of the board on the display, in pixels.
+const BLOCK_SIZE: u16 = 25;
+/// A point on the start, win and lose screens, away from the corners with the diagnostics and
+/// settings.
+const SCREEN_CENTER: (u16, u16) = (240, 136);
+/// The labels of the columns and rows, like the display prints them.
+const COLUMNS: &str = "1234567890";
+const ROWS: &str = "abcdefghij";
+/// How long `input` waits when no key was pressed.
+const KEY_POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// The column of the terminal where the status panel starts.
+const PANEL_COLUMN: u16 = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Start,
+    Board,
+    Won,
+    Lost,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Marker {
+    Empty,
+    Selected,
+    Hit,
+    Miss,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Confirm {
+    Hidden,
+    Enabled,
+    Disabled,
+}
+
+/// What a key does.
+enum Action {
+    /// The cursor was moved.
+    Move,
+    Input(Input),
+    Quit,
+    Ignore,
+}
+
+/// The game UI in a terminal.
+///
+/// The board is drawn as a grid of letters, the status panel and the confirm button next to it.
+/// The arrow keys (or `hjkl`) move a cursor over the fields:
+///
+/// - `Enter` or `Space` taps the field under the cursor,
+/// - `c` taps the confirm button,
+/// - `u` or `Backspace` undoes the last selection like a two finger tap,
+/// - `Tab` switches between the enemy grid and our fleet like a swipe,
+/// - `q` or `Ctrl-C` quits.
+///
+/// On the start, win and lose screens any key continues. The terminal is in raw mode while the
+/// UI exists.
+pub struct TerminalUi {
+    stdout: RawTerminal<Stdout>,
+    keys: Keys<AsyncReader>,
+    mode: Mode,
+    ships: [[bool; 10]; 10],
+    markers: [[Marker; 10]; 10],
+    own_ships: (u8, u8, u8, u8),
+    enemy_ships: (u8, u8, u8, u8),
+    message: String,
+    confirm: Confirm,
+    /// The field under the cursor, counted from 1 like the fields of `gameboard.rs`.
+    cursor: (u16, u16),
+    pending_input: VecDeque<Input>,
+    changed: bool,
+}
+
+impl TerminalUi {
+    /// Switches the terminal to raw mode and reads the keys without blocking.
+    pub fn new() -> io::Result<TerminalUi> {
+        let mut stdout = io::stdout().into_raw_mode()?;
+        write!(stdout, "{}{}", cursor::Hide, clear::All)?;
+        Ok(TerminalUi {
+            stdout,
+            keys: termion::async_stdin().keys(),
+            mode: Mode::Start,
+            ships: [[false; 10]; 10],
+            markers: [[Marker::Empty; 10]; 10],
+            own_ships: (0, 0, 0, 0),
+            enemy_ships: (0, 0, 0, 0),
+            message: String::new(),
+            confirm: Confirm::Hidden,
+            cursor: (1, 1),
+            pending_input: VecDeque::new(),
+            changed: true,
+        })
+    }
+
+    /// Restores the terminal and exits the process.
+    fn quit(&mut self) -> ! {
+        let _ = write!(
+            self.stdout,
+            "{}{}{}{}",
+            style::Reset,
+            clear::All,
+            cursor::Goto(1, 1),
+            cursor::Show
+        );
+        let _ = self.stdout.flush();
+        let _ = self.stdout.suspend_raw_mode();
+        process::exit(0);
+    }
+
+    /// Reads the keys that were pressed since the last call.
+    fn poll_keys(&mut self) {
+        while let Some(Ok(key)) = self.keys.next() {
+            if self.mode != Mode::Board {
+                match key {
+                    Key::Char('q') | Key::Ctrl('c') => self.quit(),
+                    _ => {
+                        let (x, y) = SCREEN_CENTER;
+                        self.pending_input.push_back(Input::Tap(x, y));
+                    }
+                }
+                continue;
+            }
+            match map_key(key, &mut self.cursor) {
+                Action::Move => self.changed = true,
+                Action::Input(input) => self.pending_input.push_back(input),
+                Action::Quit => self.quit(),
+                Action::Ignore => {}
+            }
+        }
+    }
+
+    fn render(&mut self) -> io::Result<()> {
+        self.changed = false;
+        write!(self.stdout, "{}{}", clear::All, cursor::Goto(1, 1))?;
+        match self.mode {
+            Mode::Start => self.render_screen("SCHIFFE VERSENKEN", "press any key to start"),
+            Mode::Won => self.render_screen("YOU WIN!", "press any key for a new game"),
+            Mode::Lost => self.render_screen("YOU LOSE!", "press any key for a new game"),
+            Mode::Board => self.render_board(),
+        }?;
+        self.stdout.flush()
+    }
+
+    fn render_screen(&mut self, title: &str, hint: &str) -> io::Result<()> {
+        write!(
+            self.stdout,
+            "{}{}{}{}{}{}{}q quits",
+            cursor::Goto(4, 3),
+            style::Bold,
+            title,
+            style::Reset,
+            cursor::Goto(4, 5),
+            hint,
+            cursor::Goto(4, 6)
+        )
+    }
+
+    fn render_board(&mut self) -> io::Result<()> {
+        write!(self.stdout, "{}   ", cursor::Goto(1, 1))?;
+        for label in COLUMNS.chars() {
+            write!(self.stdout, " {} ", label)?;
+        }
+        for (y, label) in ROWS.chars().enumerate() {
+            write!(self.stdout, "{} {} ", cursor::Goto(1, y as u16 + 2), label)?;
+            for x in 0..10 {
+                let under_cursor = self.cursor == (x as u16 + 1, y as u16 + 1);
+                let (left, right) = if under_cursor { ("[", "]") } else { (" ", " ") };
+                write!(self.stdout, "{}", left)?;
+                self.render_field(x, y)?;
+                write!(self.stdout, "{}", right)?;
+            }
+        }
+        self.render_panel()?;
+        write!(
+            self.stdout,
+            "{}arrows/hjkl move, enter select, c confirm, u undo, tab switch view, q quit",
+            cursor::Goto(1, 13)
+        )
+    }
+
+    fn render_field(&mut self, x: usize, y: usize) -> io::Result<()> {
+        match self.markers[x][y] {
+            Marker::Hit => write!(self.stdout, "{}X", color::Fg(color::Red)),
+            Marker::Miss => write!(self.stdout, "{}O", color::Fg(color::Yellow)),
+            Marker::Selected => write!(self.stdout, "{}x", color::Fg(color::White)),
+            Marker::Empty if self.ships[x][y] => {
+                write!(self.stdout, "{}#", color::Fg(color::White))
+            }
+            Marker::Empty => write!(self.stdout, "{}~", color::Fg(color::Blue)),
+        }?;
+        write!(self.stdout, "{}", color::Fg(color::Reset))
+    }
+
+    fn render_panel(&mut self) -> io::Result<()> {
+        let rows = [
+            ("ships", (2, 3, 4, 5)),
+            ("own", self.own_ships),
+            ("enemy", self.enemy_ships),
+        ];
+        for (i, &(label, ships)) in rows.iter().enumerate() {
+            write!(
+                self.stdout,
+                "{}{:<6}{:>3}{:>3}{:>3}{:>3}",
+                cursor::Goto(PANEL_COLUMN, i as u16 + 2),
+                label,
+                ships.0,
+                ships.1,
+                ships.2,
+                ships.3
+            )?;
+        }
+        write!(
+            self.stdout,
+            "{}{}",
+            cursor::Goto(PANEL_COLUMN, 6),
+            self.message
+        )?;
+        let button = match self.confirm {
+            Confirm::Hidden => return Ok(()),
+            Confirm::Enabled => color::Fg(color::Green).to_string(),
+            Confirm::Disabled => color::Fg(color::LightBlack).to_string(),
+        };
+        write!(
+            self.stdout,
+            "{}{}[ CONFIRM ]{}",
+            cursor::Goto(PANEL_COLUMN, 9),
+            button,
+            color::Fg(color::Reset)
+        )
+    }
+
+    fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.changed = true;
+    }
+
+    fn set_marker(&mut self, x: usize, y: usize, marker: Marker) {
+        // the row and column 0 hold the indices, which the terminal prints itself
+        if x >= 1 && x <= 10 && y >= 1 && y <= 10 {
+            self.markers[x - 1][y - 1] = marker;
+            self.changed = true;
+        }
+    }
+
+    fn set_confirm(&mut self, confirm: Confirm) {
+        self.confirm = confirm;
+        self.changed = true;
+    }
+}
+
+impl Drop for TerminalUi {
+    fn drop(&mut self) {
+        let _ = write!(self.stdout, "{}{}", style::Reset, cursor::Show);
+        let _ = self.stdout.flush();
+    }
+}
+
+impl Ui for TerminalUi {
+    fn show_start_screen(&mut self) {
+        self.set_mode(Mode::Start);
+    }
+
+    fn show_win_screen(&mut self) {
+        self.clear_board();
+        self.set_mode(Mode::Won);
+    }
+
+    fn show_lose_screen(&mut self) {
+        self.clear_board();
+        self.set_mode(Mode::Lost);
+    }
+
+    fn print_background(&mut self) {
+        self.set_mode(Mode::Board);
+    }
+
+    fn update_status_text(&mut self, own_ships: (u8, u8, u8, u8), enemy_ships: (u8, u8, u8, u8)) {
+        self.own_ships = own_ships;
+        self.enemy_ships = enemy_ships;
+        self.changed = true;
+    }
+
+    fn print_text_on_display_layer2(&mut self, text: String) {
+        self.message = text;
+        self.changed = true;
+    }
+
+    fn clear_text_on_display(&mut self) {
+        self.print_text_on_display_layer2(String::new());
+    }
+
+    fn setup_ship(&mut self, ship_len: u8) {
+        self.print_text_on_display_layer2(format!("Set up your {} ship", ship_len));
+        self.set_confirm(Confirm::Enabled);
+    }
+
+    fn print_confirm_button_enabled(&mut self) {
+        self.set_confirm(Confirm::Enabled);
+    }
+
+    fn print_confirm_button_disabled(&mut self) {
+        self.set_confirm(Confirm::Disabled);
+    }
+
+    fn check_confirm_button_touched(&mut self, x: u16, y: u16) -> bool {
+        let point = Point::new(x as usize, y as usize);
+        self.confirm != Confirm::Hidden && CONFIRM_BUTTON.contains(point)
+    }
+
+    fn write_in_field(&mut self, x: usize, y: usize, letter: &str) {
+        let marker = if letter.trim().is_empty() {
+            Marker::Empty
+        } else {
+            Marker::Selected
+        };
+        self.set_marker(x, y, marker);
+    }
+
+    fn print_hit(&mut self, x: usize, y: usize) {
+        self.set_marker(x, y, Marker::Hit);
+    }
+
+    fn print_miss(&mut self, x: usize, y: usize) {
+        self.set_marker(x, y, Marker::Miss);
+    }
+
+    fn print_ship(
+        &mut self,
+        ship_size: usize,
+        ship_start_xblock: usize,
+        ship_start_yblock: usize,
+        vertical: bool,
+    ) {
+        for i in 0..ship_size {
+            let (x, y) = if vertical {
+                (ship_start_xblock, ship_start_yblock + i)
+            } else {
+                (ship_start_xblock + i, ship_start_yblock)
+            };
+            if x < 10 && y < 10 {
+                self.ships[x][y] = true;
+            }
+        }
+        self.changed = true;
+    }
+
+    fn clear_board(&mut self) {
+        self.layer_2_clear();
+    }
+
+    fn layer_2_clear(&mut self) {
+        self.ships = [[false; 10]; 10];
+        self.markers = [[Marker::Empty; 10]; 10];
+        self.changed = true;
+    }
+
+    // the terminal shows the markers at once, there is nothing to animate
+    fn animate_hit(&mut self, _x: usize, _y: usize) {}
+
+    fn animate_miss(&mut self, _x: usize, _y: usize) {}
+
+    fn animate_sinking(
+        &mut self,
+        ship_size: usize,
+        ship_start_xblock: usize,
+        ship_start_yblock: usize,
+        vertical: bool,
+    ) {
+        self.print_ship(ship_size, ship_start_xblock, ship_start_yblock, vertical);
+    }
+
+    fn update_animations(&mut self) {
+        self.poll_keys();
+        if self.changed {
+            // the game has no way to report an error of the UI, like the display it keeps going
+            let _ = self.render();
+        }
+    }
+
+    fn input(&mut self) -> Option<Input> {
+        self.update_animations();
+        let input = self.pending_input.pop_front();
+        if input.is_none() {
+            // the rules poll for input in a loop, which should not keep a core busy
+            thread::sleep(KEY_POLL_INTERVAL);
+        }
+        input
+    }
+}
+
+/// Translates a key on the board to a tap or gesture, or moves the cursor.
+fn map_key(key: Key, cursor: &mut (u16, u16)) -> Action {
+    let center = |(x, y): (u16, u16)| {
+        (
+            x * BLOCK_SIZE + BLOCK_SIZE / 2,
+            y * BLOCK_SIZE + BLOCK_SIZE / 2,
+        )
+    };
+    let (x, y) = *cursor;
+    *cursor = match key {
+        Key::Left | Key::Char('h') => (x.max(2) - 1, y),
+        Key::Right | Key::Char('l') => (x.min(9) + 1, y),
+        Key::Up | Key::Char('k') => (x, y.max(2) - 1),
+        Key::Down | Key::Char('j') => (x, y.min(9) + 1),
+        Key::Char('\n') | Key::Char(' ') => {
+            let (x, y) = center(*cursor);
+            return Action::Input(Input::Tap(x, y));
+        }
+        Key::Char('c') => {
+            let x = CONFIRM_BUTTON.x + CONFIRM_BUTTON.width / 2;
+            let y = CONFIRM_BUTTON.y + CONFIRM_BUTTON.height / 2;
+            return Action::Input(Input::Tap(x as u16, y as u16));
+        }
+        Key::Char('u') | Key::Backspace => {
+            let (x, y) = center(*cursor);
+            let gesture = Gesture::TwoFingerTap {
+                center: Touch { x, y },
+            };
+            return Action::Input(Input::Gesture(gesture));
+        }
+        Key::Char('\t') => {
+            return Action::Input(Input::Gesture(Gesture::Swipe(SwipeDirection::Left)))
+        }
+        Key::Char('q') | Key::Ctrl('c') => return Action::Quit,
+        _ => return Action::Ignore,
+    };
+    Action::Move
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboard;
+
+    #[test]
+    fn keys_tap_the_field_under_the_cursor() {
+        let mut board = gameboard::gameboard_init();
+        let mut cursor = (1, 1);
+        for &key in [Key::Right, Key::Right, Key::Char('j'), Key::Left].iter() {
+            assert!(match map_key(key, &mut cursor) {
+                Action::Move => true,
+                _ => false,
+            });
+        }
+        assert_eq!(cursor, (2, 2));
+        let (x, y) = match map_key(Key::Char('\n'), &mut cursor) {
+            Action::Input(Input::Tap(x, y)) => (x, y),
+            _ => panic!("enter must tap"),
+        };
+        let block = board.calculate_touch_block(x, y).unwrap();
+        assert_eq!((block.x, block.y), (2, 2));
+    }
+
+    #[test]
+    fn cursor_stays_on_the_board() {
+        let mut cursor = (1, 10);
+        map_key(Key::Left, &mut cursor);
+        map_key(Key::Down, &mut cursor);
+        assert_eq!(cursor, (1, 10));
+    }
+
+    #[test]
+    fn confirm_key_taps_the_confirm_button() {
+        let (x, y) = match map_key(Key::Char('c'), &mut (1, 1)) {
+            Action::Input(Input::Tap(x, y)) => (x, y),
+            _ => panic!("c must tap"),
+        };
+        assert!(CONFIRM_BUTTON.contains(Point::new(x as usize, y as usize)));
+    }
+}
@@ -0,0 +1,63 @@
+//! Compares the scenes with the reviewed images in `tests/golden`.
+//!
+//! A missing image fails the test, `UPDATE_GOLDEN=1 cargo test` records all images again. Look
+//! at recorded images before committing them. If a scene differs from its image, the rendered
+//! scene is saved next to it as `<scene>.actual.png`.
+
+use schiffe_versenken_simulator::scenes::{self, SCENES};
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+}
+
+/// Returns the RGB pixels of a golden image.
+fn read_rgb(path: &Path) -> Vec<u8> {
+    let decoder = png::Decoder::new(File::open(path).unwrap());
+    let (info, mut reader) = decoder.read_info().unwrap();
+    assert_eq!(info.color_type, png::ColorType::RGB, "{}", path.display());
+    let mut pixels = vec![0; info.buffer_size()];
+    reader.next_frame(&mut pixels).unwrap();
+    pixels
+}
+
+#[test]
+fn scenes_match_golden_images() {
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+    fs::create_dir_all(golden_dir()).unwrap();
+    let mut failed = Vec::new();
+    for &(name, scene) in SCENES {
+        let display = scenes::render(scene);
+        let simulator = display.backend();
+        let path = golden_dir().join(name).with_extension("png");
+        if update {
+            simulator.save_png(&path).unwrap();
+            eprintln!("recorded {}", path.display());
+            continue;
+        }
+        if !path.exists() {
+            failed.push(format!(
+                "{}: missing golden image, record it with UPDATE_GOLDEN=1",
+                name
+            ));
+            continue;
+        }
+        let expected = read_rgb(&path);
+        let actual = simulator.rgb();
+        let different = expected
+            .chunks(3)
+            .zip(actual.chunks(3))
+            .filter(|(expected, actual)| expected != actual)
+            .count();
+        if expected.len() != actual.len() || different > 0 {
+            let actual_path = golden_dir().join(format!("{}.actual.png", name));
+            simulator.save_png(&actual_path).unwrap();
+            failed.push(format!("{}: {} pixels differ", name, different));
+        }
+    }
+    assert!(failed.is_empty(), "{}", failed.join("\n"));
+}
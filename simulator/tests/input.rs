@@ -0,0 +1,56 @@
+//! Drives the display with scripted touches.
+
+use schiffe_versenken_simulator::display::{Display, Input, Ui};
+use schiffe_versenken_simulator::scenes;
+use schiffe_versenken_simulator::{Simulator, TouchScript};
+use stm32f7_discovery::touch::{Gesture, SwipeDirection};
+
+/// Returns the inputs until all scripted touches are replayed.
+fn inputs(display: &mut Display<Simulator>) -> Vec<Input> {
+    let mut inputs = Vec::new();
+    while !display.backend().script_finished() {
+        inputs.extend(display.input());
+    }
+    // the touch tracker reports a release only after a few samples without touch
+    for _ in 0..10 {
+        inputs.extend(display.input());
+    }
+    inputs
+}
+
+/// Returns the only input of the script.
+fn single_input(display: &mut Display<Simulator>) -> Input {
+    let mut inputs = inputs(display);
+    assert_eq!(inputs.len(), 1, "expected a single input");
+    inputs.remove(0)
+}
+
+#[test]
+fn tap_presses_the_confirm_button() {
+    let mut display = scenes::display(TouchScript::new().with_tap(100, 378, 225));
+    display.setup_ship(3);
+    match single_input(&mut display) {
+        Input::Tap(x, y) => assert!(display.check_confirm_button_touched(x, y)),
+        Input::Gesture(_) => panic!("expected a tap"),
+    }
+}
+
+#[test]
+fn tap_outside_of_the_confirm_button_is_ignored() {
+    let mut display = scenes::display(TouchScript::new().with_tap(100, 100, 100));
+    display.setup_ship(3);
+    match single_input(&mut display) {
+        Input::Tap(x, y) => assert!(!display.check_confirm_button_touched(x, y)),
+        Input::Gesture(_) => panic!("expected a tap"),
+    }
+}
+
+#[test]
+fn swipe_is_a_gesture() {
+    let script = TouchScript::new().with_swipe(100, (100, 136), (350, 136), 200);
+    let mut display = scenes::display(script);
+    match single_input(&mut display) {
+        Input::Gesture(gesture) => assert_eq!(gesture, Gesture::Swipe(SwipeDirection::Right)),
+        Input::Tap(..) => panic!("expected a swipe"),
+    }
+}
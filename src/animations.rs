@@ -0,0 +1,136 @@
+use stm32f7_discovery::lcd::sprite::{Animation, Generated};
+use stm32f7_discovery::lcd::Color;
+
+/**
+ * the animations fill the inside of a field, without the grid lines
+ */
+pub const SPRITE_SIZE: usize = 21;
+const CENTER: isize = (SPRITE_SIZE / 2) as isize;
+
+const SPRAY: Color = Color {
+    red: 210,
+    green: 235,
+    blue: 255,
+    alpha: 255,
+};
+const WATER: Color = Color {
+    red: 30,
+    green: 120,
+    blue: 170,
+    alpha: 230,
+};
+const HULL: Color = Color {
+    red: 110,
+    green: 110,
+    blue: 110,
+    alpha: 255,
+};
+const FIRE_YELLOW: Color = Color {
+    red: 255,
+    green: 230,
+    blue: 80,
+    alpha: 255,
+};
+const FIRE_ORANGE: Color = Color {
+    red: 255,
+    green: 140,
+    blue: 0,
+    alpha: 255,
+};
+const FIRE_RED: Color = Color {
+    red: 220,
+    green: 30,
+    blue: 30,
+    alpha: 255,
+};
+const TRANSPARENT: Color = Color {
+    red: 0,
+    green: 0,
+    blue: 0,
+    alpha: 0,
+};
+
+/**
+ * the water splash of a miss, a drop falls into the water and a ring of spray spreads out
+ */
+pub fn splash() -> impl Animation {
+    Generated::new(SPRITE_SIZE, SPRITE_SIZE, 6, 80, splash_pixel)
+}
+
+/**
+ * the explosion of a hit, a fireball that grows, flickers and fades out
+ */
+pub fn explosion() -> impl Animation {
+    Generated::new(SPRITE_SIZE, SPRITE_SIZE, 7, 70, explosion_pixel)
+}
+
+/**
+ * one field of a sinking ship, the hull goes down while the water rises over it
+ */
+pub fn sinking() -> impl Animation {
+    Generated::new(SPRITE_SIZE, SPRITE_SIZE, 8, 90, sinking_pixel)
+}
+
+/**
+ * the squared distance from the center of the sprite
+ */
+fn distance_squared(x: usize, y: usize) -> isize {
+    let (dx, dy) = (x as isize - CENTER, y as isize - CENTER);
+    dx * dx + dy * dy
+}
+
+fn with_alpha(color: Color, alpha: usize) -> Color {
+    Color {
+        alpha: alpha.min(255) as u8,
+        ..color
+    }
+}
+
+fn splash_pixel(frame: usize, x: usize, y: usize) -> Color {
+    let distance = distance_squared(x, y);
+    let drop = 3 - frame.min(3) as isize;
+    if distance <= drop * drop {
+        return SPRAY;
+    }
+    let radius = 3 + 2 * frame as isize;
+    if (distance - radius * radius).abs() <= 2 * radius {
+        with_alpha(SPRAY, 255 - 40 * frame)
+    } else {
+        TRANSPARENT
+    }
+}
+
+fn explosion_pixel(frame: usize, x: usize, y: usize) -> Color {
+    const RADIUS: [isize; 7] = [3, 6, 9, 10, 10, 9, 7];
+    let radius = RADIUS[frame];
+    let distance = distance_squared(x, y);
+    //leave out some pixels, which changes in every frame and looks like flickering flames
+    if distance > radius * radius || (x * 7 + y * 13 + frame * 5) % 9 == 0 {
+        return TRANSPARENT;
+    }
+    let color = match distance * 255 / (radius * radius) {
+        0..=79 => FIRE_YELLOW,
+        80..=169 => FIRE_ORANGE,
+        _ => FIRE_RED,
+    };
+    with_alpha(color, 255 - 60 * frame.saturating_sub(3))
+}
+
+fn sinking_pixel(frame: usize, x: usize, y: usize) -> Color {
+    let water_level = SPRITE_SIZE - SPRITE_SIZE * frame / 7;
+    if y == water_level {
+        return SPRAY;
+    }
+    if y > water_level {
+        return WATER;
+    }
+    //the hull is a trapezoid that sinks two pixels per frame
+    let hull_top = 7 + 2 * frame;
+    if y >= hull_top && y < hull_top + 7 {
+        let inset = 1 + (y - hull_top) / 2;
+        if x >= inset && x < SPRITE_SIZE - inset {
+            return HULL;
+        }
+    }
+    TRANSPARENT
+}
@@ -1,211 +1,901 @@
 use alloc::string::String;
+use alloc::vec::Vec;
 use crate::alloc::string::ToString;
+use arrayvec::ArrayVec;
+use core::cmp;
 use core::fmt::Write;
 use stm32f7_discovery::{
-    lcd::Color, lcd::FramebufferAl88, lcd::FramebufferArgb8888, lcd::Layer, lcd::Lcd,
+    lcd::font::{Font, Font8x8},
+    lcd::qoi::Qoi,
+    lcd::sprite::{AnimationId, Animator},
+    lcd::text::TextBox,
+    lcd::Color, lcd::DoubleBuffer, lcd::FramebufferAl88Clut, lcd::FramebufferArgb8888, lcd::Lcd,
+    lcd::{Framebuffer, Layer, Point, Rect, WIDTH},
+    lcd::ui::{Button, ButtonState, Grid, Label, Panel, Screen, WidgetId},
     i2c::I2C,
-    touch,
-    system_clock::{self},
+    system_clock,
+    touch::{self, Calibration, Gesture, GestureRecognizer, Touch, TouchEvent, TouchTracker},
+    log_error, log_info, log_warning,
 };
-use stm32f7::stm32f7x6::I2C3;
-static BACKGROUND: &'static [u8] = include_bytes!("../WaterBig3Small.bmp");
-static STARTSCREEN: &'static [u8] = include_bytes!("../StartScreen.bmp");
-static WIN_FONT: &'static [u8] = include_bytes!("../win_font_small_without_alpha.bmp");
-static LOSE_FONT: &'static [u8] = include_bytes!("../loser_font_small.bmp");
+use stm32f7::stm32f7x6::{FLASH, I2C3};
+use crate::animations::{self, SPRITE_SIZE};
+// the bitmaps are converted to QOI images by the build script
+static BACKGROUND: &'static [u8] = include_bytes!(concat!(env!("OUT_DIR"), "/WaterBig3Small.qoi"));
+static STARTSCREEN: &'static [u8] = include_bytes!(concat!(env!("OUT_DIR"), "/StartScreen.qoi"));
 
 
 
-static BLACK: Color = Color {
+const BLACK: Color = Color {
     red: 0,
     green: 0,
     blue: 0,
     alpha: 255,
 };
-static GREY: Color = Color {
-    red: 127,
-    green: 127,
-    blue: 127,
-    alpha: 100,
-};
-static WHITE: Color = Color {
+const WHITE: Color = Color {
     red: 255,
     green: 255,
     blue: 255,
     alpha: 255,
 };
-static WATER_BLUE: Color = Color {
+/**
+ * the background color of the LCD, which shows through the transparent parts of both layers
+ */
+pub const WATER_BLUE: Color = Color {
     red: 49,
     green: 190,
     blue: 190,
     alpha: 255,
 };
+const SHIP_GREY: Color = Color {
+    red: 160,
+    green: 160,
+    blue: 160,
+    alpha: 255,
+};
+const HIT_RED: Color = Color {
+    red: 220,
+    green: 30,
+    blue: 30,
+    alpha: 255,
+};
+const MISS_YELLOW: Color = Color {
+    red: 255,
+    green: 220,
+    blue: 0,
+    alpha: 255,
+};
+/**
+ * the colors of layer 2, which is drawn through the color lookup table of the LCD controller
+ */
+static LAYER_2_PALETTE: [Color; 5] = [BLACK, WHITE, SHIP_GREY, HIT_RED, MISS_YELLOW];
+
+const BLOCK_SIZE: usize = 25;
+//number of fields in a row and a column of the zoomed board, they are drawn at double size
+const ZOOM_FIELDS: usize = 5;
+const STATUS_Y: usize = 55;
+const STATUS_LINE_HEIGHT: usize = 15;
+const X_YOU: usize = 290;
+const X_ENEMY: usize = X_YOU + 101;
+const COUNT_OFFSET: usize = 60;
+const HEADING_SCALE: usize = 2;
+const TITLE_SCALE: usize = 6;
+/**
+ * the area of the confirm button, other front ends report a tap in it when the user confirms
+ */
+pub const CONFIRM_BUTTON: Rect = Rect::new(299, 199, 158, 52);
+
+/**
+ * widgets on layer 2: the grid lines, the ship status table and the message line
+ */
+struct Overlay<T> {
+    screen: Screen<T>,
+    grid: WidgetId,
+    zoomed_grid: [WidgetId; 3],
+    message: WidgetId,
+    own_ships: [WidgetId; 4],
+    enemy_ships: [WidgetId; 4],
+}
 
-pub struct Display {
-    layer1: Layer<FramebufferArgb8888>,
-    layer2: Layer<FramebufferAl88>,
+/**
+ * widgets on layer 1: the confirm button
+ */
+struct Controls<T> {
+    screen: Screen<T>,
+    confirm: WidgetId,
+}
+
+fn board_grid() -> Grid {
+    // the first row and column hold the indices, the other 10x10 cells the board
+    Grid::new(Point::new(0, 0), BLOCK_SIZE, 11, 11).with_lines(BLACK, 2)
+}
+
+fn zoomed_grid<T: Framebuffer + 'static>(screen: &mut Screen<T>) -> [WidgetId; 3] {
+    // the indices keep their size, the fields of the zoomed board are twice as large
+    let fields = Grid::new(
+        Point::new(BLOCK_SIZE, BLOCK_SIZE),
+        2 * BLOCK_SIZE,
+        ZOOM_FIELDS,
+        ZOOM_FIELDS,
+    )
+    .with_lines(BLACK, 2);
+    let board_size = BLOCK_SIZE * (2 * ZOOM_FIELDS + 1) + 1;
+    let widgets = [
+        screen.add(fields),
+        screen.add(Panel::new(Rect::new(BLOCK_SIZE - 1, 0, 2, board_size), BLACK)),
+        screen.add(Panel::new(Rect::new(0, BLOCK_SIZE - 1, board_size, 2), BLACK)),
+    ];
+    for &widget in widgets.iter() {
+        screen.set_visible(widget, false);
+    }
+    widgets
+}
+
+/**
+ * the position and size of a column or row of fields, if it is shown. `first` is the first field
+ * of the zoomed board, the index column and row keep their size
+ */
+fn field_span(field: usize, first: Option<usize>) -> Option<(usize, usize)> {
+    match first {
+        None => Some((field * BLOCK_SIZE, BLOCK_SIZE)),
+        Some(_) if field == 0 => Some((0, BLOCK_SIZE)),
+        Some(first) if field >= first && field < first + ZOOM_FIELDS => {
+            Some((BLOCK_SIZE + (field - first) * 2 * BLOCK_SIZE, 2 * BLOCK_SIZE))
+        }
+        Some(_) => None,
+    }
+}
+
+fn status_label(x: usize, y: usize, text: &str) -> Label {
+    Label::new(Rect::new(x, y, text.len() * 8, 8), text)
+}
+
+fn status_table<T: Framebuffer + 'static>(screen: &mut Screen<T>, x: usize, title: &str) -> [WidgetId; 4] {
+    screen.add(status_label(x + 20, 10, title));
+    screen.add(status_label(x + 20, 20, "Ships"));
+    let mut counts = [None; 4];
+    for (i, count) in counts.iter_mut().enumerate() {
+        let y = STATUS_Y + STATUS_LINE_HEIGHT * i;
+        screen.add(status_label(x, y, &format!("size {}:", i + 2)));
+        *count = Some(screen.add(status_label(x + COUNT_OFFSET, y, "   ")));
+    }
+    [counts[0].unwrap(), counts[1].unwrap(), counts[2].unwrap(), counts[3].unwrap()]
+}
+
+impl<T: Framebuffer + 'static> Overlay<T> {
+    fn new() -> Overlay<T> {
+        let mut screen = Screen::new();
+        let grid = screen.add(board_grid());
+        let zoomed_grid = zoomed_grid(&mut screen);
+        screen.add(Panel::new(Rect::new(378, 0, 1, 136), BLACK));
+        screen.add(Panel::new(Rect::new(275, 136, 205, 1), BLACK));
+        let own_ships = status_table(&mut screen, X_YOU, "Your");
+        let enemy_ships = status_table(&mut screen, X_ENEMY, "Enemy");
+        let message = screen.add(Label::new(Rect::new(300, 156, 180, 16), ""));
+        Overlay {
+            screen,
+            grid,
+            zoomed_grid,
+            message,
+            own_ships,
+            enemy_ships,
+        }
+    }
+}
+
+/**
+ * the actions of the settings screen
+ */
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SettingsAction {
+    Calibrate,
+    ResetCalibration,
+    Back,
+}
+
+/**
+ * widgets of the settings screen on layer 1
+ */
+struct Settings<T> {
+    screen: Screen<T>,
+    buttons: Vec<(WidgetId, SettingsAction)>,
+}
+
+impl<T: Framebuffer + 'static> Settings<T> {
+    fn new() -> Settings<T> {
+        let mut screen = Screen::new();
+        let actions = [
+            ("calibrate touchscreen", SettingsAction::Calibrate),
+            ("reset calibration", SettingsAction::ResetCalibration),
+            ("back", SettingsAction::Back),
+        ];
+        let area = Rect::new(140, 50, 200, 180);
+        let buttons = area
+            .rows(actions.len(), 20)
+            .zip(actions.iter())
+            .map(|(bounds, (caption, action))| (screen.add(Button::new(bounds, caption)), *action))
+            .collect();
+        Settings { screen, buttons }
+    }
+}
+
+impl<T: Framebuffer + 'static> Controls<T> {
+    fn new() -> Controls<T> {
+        let mut screen = Screen::new();
+        let confirm = screen.add(Button::new(CONFIRM_BUTTON, "CONFIRM"));
+        screen.set_visible(confirm, false);
+        Controls { screen, confirm }
+    }
+}
+
+/**
+ * the animations of shots, which are played on layer 1 below the grid
+ */
+struct Effects {
+    animator: Animator,
+    splash: AnimationId,
+    explosion: AnimationId,
+    sinking: AnimationId,
+    //sunk ships are drawn when their animation is over, otherwise they would hide it
+    sunk_ships: Vec<(usize, usize, usize, bool)>,
+}
+
+impl Effects {
+    fn new() -> Effects {
+        let mut animator = Animator::new();
+        let splash = animator.add(animations::splash());
+        let explosion = animator.add(animations::explosion());
+        let sinking = animator.add(animations::sinking());
+        Effects {
+            animator,
+            splash,
+            explosion,
+            sinking,
+            sunk_ships: Vec::new(),
+        }
+    }
+}
+
+/**
+ * a tap on the display or a gesture
+ */
+pub enum Input {
+    Tap(u16, u16),
+    Gesture(Gesture),
+}
+
+/**
+ * what the display draws on and where the touches come from. The board shows double buffered
+ * layers and reads the touchscreen, the simulator draws into memory and replays scripted touches
+ */
+pub trait Backend {
+    /**
+     * the framebuffer of the background images, the animations and the buttons
+     */
+    type Layer1: Framebuffer + 'static;
+    /**
+     * the framebuffer of the grid, the ships, the markers and the texts above layer 1
+     */
+    type Layer2: Framebuffer + 'static;
+
+    fn layer1(&mut self) -> &mut Layer<Self::Layer1>;
+    fn layer2(&mut self) -> &mut Layer<Self::Layer2>;
+
+    /**
+     * show everything that was drawn on the layers since the last call
+     */
+    fn present(&mut self);
+
+    /**
+     * the current touch points in display coordinates, or None if they can't be read
+     */
+    fn touches(&mut self) -> Option<ArrayVec<[Touch; 5]>>;
+
+    /**
+     * the milliseconds since the start, which time the animations and the touch events
+     */
+    fn ms(&self) -> usize;
+
+    /**
+     * keep the touch calibration over a restart. Backends without a flash forget it
+     */
+    fn store_calibration(&mut self, _calibration: Calibration) {}
+}
+
+/**
+ * the LCD and the touchscreen of the discovery board, and the flash that keeps the touch calibration
+ */
+pub struct Board {
+    layer1: DoubleBuffer<FramebufferArgb8888>,
+    layer2: DoubleBuffer<FramebufferAl88Clut>,
     touchscreen: I2C<I2C3>,
-    last_touch: usize,
+    flash: FLASH,
 }
 
-impl Display {
-    pub fn new(layer1: Layer<FramebufferArgb8888>, layer2: Layer<FramebufferAl88>, touchscreen: I2C<I2C3>) -> Display {
+impl Backend for Board {
+    type Layer1 = FramebufferArgb8888;
+    type Layer2 = FramebufferAl88Clut;
+
+    fn layer1(&mut self) -> &mut Layer<FramebufferArgb8888> {
+        &mut self.layer1
+    }
+
+    fn layer2(&mut self) -> &mut Layer<FramebufferAl88Clut> {
+        &mut self.layer2
+    }
+
+    fn present(&mut self) {
+        self.layer1.present();
+        self.layer2.present();
+    }
+
+    fn touches(&mut self) -> Option<ArrayVec<[Touch; 5]>> {
+        match touch::touches(&mut self.touchscreen) {
+            Ok(touches) => Some(touches),
+            Err(err) => {
+                log_warning!("reading touches failed: {:?}", err);
+                None
+            }
+        }
+    }
+
+    fn ms(&self) -> usize {
+        system_clock::ms()
+    }
+
+    fn store_calibration(&mut self, calibration: Calibration) {
+        match calibration.store(&mut self.flash) {
+            Ok(()) => log_info!("stored touch calibration"),
+            Err(err) => log_error!("failed to store touch calibration: {:?}", err),
+        }
+    }
+}
+
+pub struct Display<B: Backend = Board> {
+    backend: B,
+    touch_tracker: TouchTracker,
+    gestures: GestureRecognizer,
+    multi_touch: bool,
+    pending_input: Vec<Input>,
+    overlay: Overlay<B::Layer2>,
+    controls: Controls<B::Layer1>,
+    settings: Settings<B::Layer1>,
+    effects: Effects,
+    zoom: Option<(usize, usize)>, //the first field of the zoomed board, None shows the whole board
+}
+
+impl<B: Backend> Display<B> {
+    pub fn new(backend: B) -> Display<B> {
         Display {
-            layer1,
-            layer2,
-            touchscreen,
-            last_touch: system_clock::ticks(),
+            backend,
+            touch_tracker: TouchTracker::default(),
+            gestures: GestureRecognizer::default(),
+            multi_touch: false,
+            pending_input: Vec::new(),
+            overlay: Overlay::new(),
+            controls: Controls::new(),
+            settings: Settings::new(),
+            effects: Effects::new(),
+            zoom: None,
         }
     }
 }
 
-pub fn init_display(lcd: &mut Lcd, touchscreen: I2C<I2C3>) -> Display {
-    let layer_1 = lcd.layer_1().unwrap();
-    let layer_2 = lcd.layer_2().unwrap();
-    let mut display = Display::new(layer_1, layer_2, touchscreen);
-    display.layer1.clear();
-    display.layer2.clear();
+pub fn init_display(lcd: &mut Lcd, touchscreen: I2C<I2C3>, flash: FLASH) -> Display {
+    let layer_1 = lcd.layer_1_double_buffered().unwrap();
+    let layer_2 = lcd.layer_2_clut_double_buffered(&LAYER_2_PALETTE).unwrap();
+    let mut display = Display::new(Board {
+        layer1: layer_1,
+        layer2: layer_2,
+        touchscreen,
+        flash,
+    });
+    display.backend.layer1().clear();
+    display.backend.layer2().clear();
+    display.present();
     lcd.set_background_color(WATER_BLUE);
     display
 }
-impl Display {
-    
-    pub fn print_background(&mut self) {
-        
-        self.print_bmp_at_location(BACKGROUND, 0, 0);
-        self.print_bmp_at_location(BACKGROUND, 240, 0);
-        self.print_bmp_at_location(BACKGROUND, 0, 136);
-        self.print_bmp_at_location(BACKGROUND, 240, 136);
-        let xarr = [
-            24, 25, 49, 50, 74, 75, 99, 100, 124, 125, 149, 150, 174, 175, 199, 200, 224, 225, 249,
-            250, 274, 275,
-        ];
-        let yarr = [
-            24, 25, 49, 50, 74, 75, 99, 100, 124, 125, 149, 150, 174, 175, 199, 200, 224, 225, 249,
-            250,
-        ];
-        for c in xarr.iter() {
-            for i in 0..272 {
-                self.layer2.print_point_color_at(*c, i, BLACK);
-            }
-        }
-        for c in yarr.iter() {
-            for i in 0..275 {
-                self.layer2.print_point_color_at(i, *c, BLACK);
-            }
+
+/**
+ * the screens and the input of a game. The rules in gameboard.rs and the turns of a game only use this
+ * interface, so the game can also be played with another front end than the display of the board
+ */
+pub trait Ui {
+    fn show_start_screen(&mut self);
+
+    fn show_win_screen(&mut self);
+
+    fn show_lose_screen(&mut self);
+
+    fn print_background(&mut self);
+
+    /**
+     * show the number of remaining ships of the sizes 2, 3, 4 and 5
+     */
+    fn update_status_text(&mut self, own_ships: (u8, u8, u8, u8), enemy_ships: (u8, u8, u8, u8));
+
+    fn print_text_on_display_layer2(&mut self, text: String);
+
+    fn clear_text_on_display(&mut self);
+
+    /**
+     * ask for the fields of the next ship and show the confirm button
+     */
+    fn setup_ship(&mut self, ship_len: u8);
+
+    fn print_confirm_button_enabled(&mut self);
+
+    fn print_confirm_button_disabled(&mut self);
+
+    /**
+     * return whether the position x, y is on the confirm button, which then shows that it was pressed
+     */
+    fn check_confirm_button_touched(&mut self, x: u16, y: u16) -> bool;
+
+    /**
+     * write a letter in a field. The fields of the board are counted from 1, the row and column 0 hold the indices
+     */
+    fn write_in_field(&mut self, x: usize, y: usize, letter: &str);
+
+    /**
+     * mark a field that was shot and hit a ship
+     */
+    fn print_hit(&mut self, x: usize, y: usize);
+
+    /**
+     * mark a field that was shot and missed
+     */
+    fn print_miss(&mut self, x: usize, y: usize);
+
+    /**
+     * draw ship on x, y coordination. The direction is vertical for true and horizontal for false.
+     */
+    fn print_ship(
+        &mut self,
+        ship_size: usize,
+        ship_start_xblock: usize,
+        ship_start_yblock: usize,
+        vertical: bool,
+    );
+
+    /**
+     * remove all ships and markers from the board, but keep the grid and the status information
+     */
+    fn clear_board(&mut self);
+
+    fn layer_2_clear(&mut self);
+
+    /**
+     * play the explosion of a hit in the field x, y. The fields are counted like in print_hit
+     */
+    fn animate_hit(&mut self, x: usize, y: usize);
+
+    /**
+     * play the water splash of a miss in the field x, y
+     */
+    fn animate_miss(&mut self, x: usize, y: usize);
+
+    /**
+     * let a ship sink field by field and draw it with print_ship when the animation is over.
+     * The arguments are the same as for print_ship
+     */
+    fn animate_sinking(
+        &mut self,
+        ship_size: usize,
+        ship_start_xblock: usize,
+        ship_start_yblock: usize,
+        vertical: bool,
+    );
+
+    /**
+     * draw the next frames of the running animations and show them. This never waits for an
+     * animation, so it is called in all loops that wait for input or the network
+     */
+    fn update_animations(&mut self);
+
+    /**
+     * return the next tap or gesture, if any. The animations are updated and everything drawn
+     * before is presented first
+     */
+    fn input(&mut self) -> Option<Input>;
+
+    /**
+     * show the fields around the position x, y at double size and return whether the board was zoomed.
+     * The board has to be drawn again afterwards. Front ends that always show the whole board do not zoom
+     */
+    fn zoom_in(&mut self, _x: u16, _y: u16) -> bool {
+        false
+    }
+
+    /**
+     * show the whole board again. The board has to be drawn again afterwards
+     */
+    fn zoom_out(&mut self) {}
+
+    /**
+     * map a position on the zoomed board to the center of its field on the whole board, so it can be
+     * passed to calculate_touch_block. Other positions are returned unchanged
+     */
+    fn unzoom(&self, x: u16, y: u16) -> (u16, u16) {
+        (x, y)
+    }
+
+    /**
+     * clear the display and print the frame of the network diagnostics screen. Front ends without
+     * diagnostics show nothing
+     */
+    fn show_diagnostics_screen(&mut self) {}
+
+    /**
+     * print the diagnostic lines of the connection, replacing the previous ones
+     */
+    fn update_diagnostics(&mut self, _lines: &[String]) {}
+
+    /**
+     * clear the display and show the buttons of the settings screen
+     */
+    fn show_settings_screen(&mut self) {}
+
+    /**
+     * return the action of the settings button at the touched position. Front ends without
+     * settings go back to the start screen
+     */
+    fn settings_action(&mut self, _x: u16, _y: u16) -> Option<SettingsAction> {
+        Some(SettingsAction::Back)
+    }
+
+    /**
+     * clear the display and draw a crosshair that the user should touch during the calibration
+     */
+    fn show_calibration_target(&mut self, _target: Point) {}
+
+    /**
+     * keep the touch calibration over a restart
+     */
+    fn store_calibration(&mut self, _calibration: Calibration) {}
+
+    /**
+     * return the position of a tap, i.e. a short touch without moving. Gestures are ignored
+     */
+    fn touch(&mut self) -> Option<(u16, u16)> {
+        match self.input() {
+            Some(Input::Tap(x, y)) => Some((x, y)),
+            _ => None,
         }
+    }
+}
+
+impl<B: Backend> Ui for Display<B> {
+    fn show_start_screen(&mut self) {
+        self.stop_animations();
+        self.print_image_at_location(STARTSCREEN, 0, 0);
+    }
+
+    fn show_win_screen(&mut self) {
+        self.stop_animations();
+        self.backend.layer1().clear();
+        self.backend.layer2().clear();
+        self.show_start_screen();
+        self.print_title("YOU WIN!", 45);
+    }
+
+    fn show_lose_screen(&mut self) {
+        self.stop_animations();
+        self.backend.layer1().clear();
+        self.backend.layer2().clear();
+        self.show_start_screen();
+        self.print_title("YOU LOSE!", 45);
+    }
+
+    fn print_background(&mut self) {
+        self.stop_animations();
+        self.print_image_at_location(BACKGROUND, 0, 0);
+        self.print_image_at_location(BACKGROUND, 240, 0);
+        self.print_image_at_location(BACKGROUND, 0, 136);
+        self.print_image_at_location(BACKGROUND, 240, 136);
+        self.overlay.screen.invalidate_all();
+        self.controls.screen.invalidate_all();
+        self.redraw_widgets();
         self.print_indicies();
-        self.print_status_information();
     }
 
-    pub fn update_status_text(&mut self, own_ships: (u8, u8, u8, u8), enemy_ships: (u8, u8, u8, u8)) {
-        let x_you = 350;
-        let x_enemy = 451;
-        let y = 55;
-        self.write_text_on_location(x_you, y, format!("{}", own_ships.0).to_string());
-        self.write_text_on_location(x_you, y+15, format!("{}", own_ships.1).to_string());
-        self.write_text_on_location(x_you, y+30, format!("{}", own_ships.2).to_string());
-        self.write_text_on_location(x_you, y+45, format!("{}", own_ships.3).to_string());
-        
-        self.write_text_on_location(x_enemy, y, format!("{}", enemy_ships.0).to_string());
-        self.write_text_on_location(x_enemy, y+15, format!("{}", enemy_ships.1).to_string());
-        self.write_text_on_location(x_enemy, y+30, format!("{}", enemy_ships.2).to_string());
-        self.write_text_on_location(x_enemy, y+45, format!("{}", enemy_ships.3).to_string());
+    fn update_status_text(&mut self, own_ships: (u8, u8, u8, u8), enemy_ships: (u8, u8, u8, u8)) {
+        let own = [own_ships.0, own_ships.1, own_ships.2, own_ships.3];
+        let enemy = [enemy_ships.0, enemy_ships.1, enemy_ships.2, enemy_ships.3];
+        for i in 0..4 {
+            let own_id = self.overlay.own_ships[i];
+            let enemy_id = self.overlay.enemy_ships[i];
+            self.overlay.screen.update(own_id, |label: &mut Label| label.set_text(&format!("{}", own[i])));
+            self.overlay.screen.update(enemy_id, |label: &mut Label| label.set_text(&format!("{}", enemy[i])));
+        }
+        self.redraw_widgets();
     }
 
-    fn write_text_on_location(&mut self, x: usize, y: usize, text: String) {
-        let mut text_writer = self.layer2.text_writer_at(x, y);
-        let result = text_writer.write_str(&text);
-        match result {
-            Ok(result) => result,
-            Err(error) => panic!("error while writing text on display: {}", error),
-        };
+    fn print_text_on_display_layer2(&mut self, text: String) {
+        let message = self.overlay.message;
+        self.overlay.screen.update(message, |label: &mut Label| label.set_text(&text));
+        self.redraw_widgets();
+    }
+
+    fn clear_text_on_display(&mut self) {
+        self.print_text_on_display_layer2(String::new());
     }
-    
-    fn print_status_information(&mut self) {
-        for i in 0..136 {
-            self.layer2.print_point_color_at(378, i, BLACK);
+
+    //TODO refactor method -> not neccesary 
+    fn setup_ship(&mut self, ship_len: u8) {
+        self.print_text_on_display_layer2(format_args!("Set up your {} ship", ship_len).to_string());
+        self.print_confirm_button(ButtonState::Enabled);
+    }
+
+    fn print_confirm_button_enabled(&mut self) {
+        self.print_confirm_button(ButtonState::Enabled);
+    }
+
+    fn print_confirm_button_disabled(&mut self) {
+        self.print_confirm_button(ButtonState::Disabled);
+    }
+
+    fn check_confirm_button_touched(&mut self, x: u16, y: u16) -> bool {
+        let point = Point::new(x as usize, y as usize);
+        if self.controls.screen.hit_test(point) == Some(self.controls.confirm) {
+            self.print_confirm_button(ButtonState::Pressed);
+            self.print_confirm_button(ButtonState::Enabled);
+            true
+        } else {
+            false
         }
-        for i in 275..480 {
-            self.layer2.print_point_color_at(i, 136, BLACK);
+    }
+
+    //pub fn write_in_field(x: usize, y: usize, mut text_writer: &mut TextWriter<FramebufferArgb8888>, letter: &str) {
+    fn write_in_field(&mut self, x: usize, y: usize, letter: &str) {
+        let field = match self.field_area(x, y) {
+            Some(field) => field,
+            None => return,
+        };
+        if self.zoom.is_some() {
+            //the text writer only has the small font
+            self.write_in_field_colored(x, y, letter, WHITE);
+            return;
         }
-        let y = 55;
-        let x_you = 290;
-        self.write_text_on_location(x_you+20, 10, "Your".to_string());
-        self.write_text_on_location(x_you+15, 20, "Ships".to_string());
-        self.write_text_on_location(x_you, y, "size 2:".to_string());
-        self.write_text_on_location(x_you, y+15, "size 3:".to_string());
-        self.write_text_on_location(x_you, y+30, "size 4:".to_string());
-        self.write_text_on_location(x_you, y+45, "size 5:".to_string());
-
-        let x_enemy = x_you + 101;
-        self.write_text_on_location(x_enemy+20, 10, "Enemy".to_string());
-        self.write_text_on_location(x_enemy+20, 20, "Ships".to_string());
-        self.write_text_on_location(x_enemy, y, "size 2:".to_string());
-        self.write_text_on_location(x_enemy, y+15, "size 3:".to_string());
-        self.write_text_on_location(x_enemy, y+30, "size 4:".to_string());
-        self.write_text_on_location(x_enemy, y+45, "size 5:".to_string());
-    }
-
-    /**
-     * print a confirm button on the right side of the display
-     */
-    fn print_confirm_button(&mut self, color: Color) {
-        for i in 299..301 {
-            for j in 199..250 {
-                //todo change this to lookup color since layer 2 is lookup only
-                self.layer1.print_point_color_at(i, j, color);
-            }
+        let (x_pos, y_pos) = (field.x + 9, field.y + 9);
+        let mut text_writer = self.backend.layer2().text_writer_at(x_pos, y_pos);
+        if let Ok(value) = text_writer.write_str(letter) {
+            value
         }
-        for i in 455..457 {
-            for j in 199..250 {
-                self.layer1.print_point_color_at(i, j, color);
+    }
+
+    fn print_hit(&mut self, x: usize, y: usize) {
+        self.write_in_field_colored(x, y, "X", HIT_RED);
+    }
+
+    fn print_miss(&mut self, x: usize, y: usize) {
+        self.write_in_field_colored(x, y, "O", MISS_YELLOW);
+    }
+
+    fn print_ship(
+        &mut self,
+        ship_size: usize,
+        ship_start_xblock: usize,
+        ship_start_yblock: usize,
+        vertical: bool,
+    ) {
+        let (width, height) = if vertical {
+            (1, ship_size)
+        } else {
+            (ship_size, 1)
+        };
+        //only the part of the ship on the zoomed board is drawn
+        let mut fields = Vec::new();
+        for x in ship_start_xblock + 1..ship_start_xblock + 1 + width {
+            for y in ship_start_yblock + 1..ship_start_yblock + 1 + height {
+                fields.extend(self.field_area(x, y));
             }
         }
-        for i in 299..457 {
-            for j in 199..201 {
-                self.layer1.print_point_color_at(i, j, color);
-            }
+        let (first, last) = match (fields.first(), fields.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return,
+        };
+        //leave a gap of two pixels to the grid lines, the layer clips ships at the bottom of the display
+        let ship = Rect::new(
+            first.x + 2,
+            first.y + 2,
+            last.right() - first.x - 4,
+            last.bottom() - first.y - 4,
+        );
+        self.backend.layer2().fill_rect(ship, SHIP_GREY);
+    }
+
+    fn clear_board(&mut self) {
+        self.stop_animations();
+        self.backend.layer2().clear();
+        self.overlay.screen.invalidate_all();
+        self.redraw_widgets();
+        self.print_indicies();
+    }
+
+    fn layer_2_clear(&mut self) {
+        self.backend.layer2().clear();
+    }
+
+    fn animate_hit(&mut self, x: usize, y: usize) {
+        let explosion = self.effects.explosion;
+        self.play_in_field(explosion, x, y, self.backend.ms());
+    }
+
+    fn animate_miss(&mut self, x: usize, y: usize) {
+        let splash = self.effects.splash;
+        self.play_in_field(splash, x, y, self.backend.ms());
+    }
+
+    fn animate_sinking(
+        &mut self,
+        ship_size: usize,
+        ship_start_xblock: usize,
+        ship_start_yblock: usize,
+        vertical: bool,
+    ) {
+        let sinking = self.effects.sinking;
+        //every field starts when the one before is half sunk
+        let delay = self.effects.animator.animation(sinking).duration() / 2;
+        let start = self.backend.ms();
+        for i in 0..ship_size {
+            let (x, y) = if vertical {
+                (ship_start_xblock, ship_start_yblock + i)
+            } else {
+                (ship_start_xblock + i, ship_start_yblock)
+            };
+            self.play_in_field(sinking, x + 1, y + 1, start + i * delay);
         }
-        for i in 299..457 {
-            for j in 249..251 {
-                self.layer1.print_point_color_at(i, j, color);
+        self.effects.sunk_ships.push((ship_size, ship_start_xblock, ship_start_yblock, vertical));
+    }
+
+    fn update_animations(&mut self) {
+        let now = self.backend.ms();
+        let running = self.effects.animator.update(self.backend.layer1(), now);
+        if !running && !self.effects.sunk_ships.is_empty() {
+            let sunk_ships = core::mem::replace(&mut self.effects.sunk_ships, Vec::new());
+            for (size, x, y, vertical) in sunk_ships {
+                self.print_ship(size, x, y, vertical);
             }
         }
-        for i in 299..457 {
-            for j in 199..251 {
-                self.layer1.print_point_color_at(i, j, color);
-            }
+        self.present();
+    }
+
+    fn input(&mut self) -> Option<Input> {
+        self.update_animations();
+        if self.pending_input.is_empty() {
+            self.sample_touchscreen();
+        }
+        if self.pending_input.is_empty() {
+            None
+        } else {
+            Some(self.pending_input.remove(0))
         }
-        let mut text_writer = self.layer2.text_writer_at(350, 220);
-        let result = text_writer.write_str("CONFIRM");
-        match result {
-            Ok(result) => result,
-            Err(error) => panic!("error while writing text on display: {}", error),
-        };
     }
 
-    
+    /**
+     * clear the display and print the frame of the network diagnostics screen
+     */
+    fn show_diagnostics_screen(&mut self) {
+        self.stop_animations();
+        self.backend.layer1().clear();
+        self.backend.layer2().clear();
+        self.print_heading("Network diagnostics");
+        self.write_text_on_location(10, 255, "touch to return".to_string());
+    }
 
-    pub fn print_text_on_display_layer2(&mut self, text: String) {
+    /**
+     * print the diagnostic lines below the title. The box of every line is cleared, so it replaces the previous value
+     */
+    fn update_diagnostics(&mut self, lines: &[String]) {
+        for (i, line) in lines.iter().enumerate() {
+            let line_box = TextBox::new(Rect::new(10, 35 + 15 * i, 460, 8), Font8x8::new(1));
+            self.backend.layer2().draw_text_box(&line_box, line);
+        }
+    }
 
-        assert!(text.len() < 50); //TODO check max string length for the gui
-        // let split = text.split_whitespace();
-        let y = 160;
-        // for word in split {
-            let mut text_writer = self.layer2.text_writer_at(300, y);
-            let result = text_writer.write_str(&text.to_string());
-            match result {
-                Ok(result) => result,
-                Err(error) => panic!("error while writing text on display: {}", error),
-            };
-            // y += 20;
-        // }
+    /**
+     * clear the display and show the buttons of the settings screen
+     */
+    fn show_settings_screen(&mut self) {
+        self.stop_animations();
+        self.backend.layer1().clear();
+        self.backend.layer2().clear();
+        self.print_heading("Settings");
+        self.settings.screen.invalidate_all();
+        self.settings.screen.draw(self.backend.layer1());
     }
 
-    //TODO refactor method -> not neccesary 
-    pub fn setup_ship(&mut self, ship_len: u8) {
-        self.print_text_on_display_layer2(format_args!("Set up your {} ship", ship_len).to_string());
-        self.print_confirm_button(BLACK);
+    /**
+     * return the action of the settings button at the touched position
+     */
+    fn settings_action(&mut self, x: u16, y: u16) -> Option<SettingsAction> {
+        let id = self.settings.screen.hit_test(Point::new(x as usize, y as usize))?;
+        self.settings
+            .buttons
+            .iter()
+            .find(|(button, _)| *button == id)
+            .map(|(_, action)| *action)
+    }
+
+    /**
+     * clear the display and draw a crosshair that the user should touch during the calibration
+     */
+    fn show_calibration_target(&mut self, target: Point) {
+        self.stop_animations();
+        self.backend.layer1().clear();
+        self.backend.layer2().clear();
+        self.write_text_on_location(150, 10, "touch the center of the cross".to_string());
+        let size = 10;
+        self.backend.layer2().hline(target.x - size, target.y, 2 * size + 1, WHITE);
+        self.backend.layer2().vline(target.x, target.y - size, 2 * size + 1, WHITE);
+    }
+
+    fn store_calibration(&mut self, calibration: Calibration) {
+        self.backend.store_calibration(calibration);
+    }
+
+    fn zoom_in(&mut self, x: u16, y: u16) -> bool {
+        let (x, y) = (x as usize, y as usize);
+        let board_end = BLOCK_SIZE * 11;
+        if x < BLOCK_SIZE || x >= board_end || y < BLOCK_SIZE || y >= board_end {
+            return false;
+        }
+        //center the zoomed board on the field, but keep it inside the board
+        let first = |field: usize| cmp::min(cmp::max(field, 3) - 2, 11 - ZOOM_FIELDS);
+        self.zoom = Some((first(x / BLOCK_SIZE), first(y / BLOCK_SIZE)));
+        self.show_zoomed_grid(true);
+        true
+    }
+
+    fn zoom_out(&mut self) {
+        self.zoom = None;
+        self.show_zoomed_grid(false);
+    }
+
+    fn unzoom(&self, x: u16, y: u16) -> (u16, u16) {
+        let (first_x, first_y) = match self.zoom {
+            Some(first) => first,
+            None => return (x, y),
+        };
+        let (x, y) = (x as usize, y as usize);
+        let board_end = BLOCK_SIZE * (2 * ZOOM_FIELDS + 1);
+        if x < BLOCK_SIZE || x >= board_end || y < BLOCK_SIZE || y >= board_end {
+            return (x as u16, y as u16);
+        }
+        let field_x = first_x + (x - BLOCK_SIZE) / (2 * BLOCK_SIZE);
+        let field_y = first_y + (y - BLOCK_SIZE) / (2 * BLOCK_SIZE);
+        let center = |field: usize| (field * BLOCK_SIZE + BLOCK_SIZE / 2) as u16;
+        (center(field_x), center(field_y))
+    }
+}
+
+impl<B: Backend> Display<B> {
+    /**
+     * draw all widgets that changed since the last call
+     */
+    fn redraw_widgets(&mut self) {
+        self.overlay.screen.draw(self.backend.layer2());
+        self.controls.screen.draw(self.backend.layer1());
+    }
+
+    fn write_text_on_location(&mut self, x: usize, y: usize, text: String) {
+        let mut text_writer = self.backend.layer2().text_writer_at(x, y);
+        let result = text_writer.write_str(&text);
+        match result {
+            Ok(result) => result,
+            Err(error) => panic!("error while writing text on display: {}", error),
+        };
+    }
+
+    /**
+     * show the confirm button on the right side of the display in the given state
+     */
+    fn print_confirm_button(&mut self, state: ButtonState) {
+        let confirm = self.controls.confirm;
+        self.controls.screen.set_visible(confirm, true);
+        self.controls.screen.update(confirm, |button: &mut Button| button.set_state(state));
+        self.redraw_widgets();
     }
 
     //fn print_indicies(mut text_writer: &mut TextWriter<FramebufferArgb8888>) {
@@ -233,181 +923,127 @@ impl Display {
         self.write_in_field(0, 10, "j");
     }
 
-    //pub fn write_in_field(x: usize, y: usize, mut text_writer: &mut TextWriter<FramebufferArgb8888>, letter: &str) {
-    pub fn write_in_field(&mut self, x: usize, y: usize, letter: &str) {
-        let mut x_pos = 9 + 25 * x;
-        let mut y_pos = 9 + 25 * y;
-        if x == 0 {
-            x_pos = 9;
-        }
-        if y == 0 {
-            y_pos = 9;
-        }
-        let mut text_writer = self.layer2.text_writer_at(x_pos, y_pos);
-        if let Ok(value) = text_writer.write_str(letter) {
-            value
-        }
+    fn write_in_field_colored(&mut self, x: usize, y: usize, letter: &str, color: Color) {
+        let field = match self.field_area(x, y) {
+            Some(field) => field,
+            None => return,
+        };
+        //the letters grow with the fields of the zoomed board
+        let scale = cmp::min(field.width, field.height) / BLOCK_SIZE;
+        let offset = |size: usize| size / 2 - 4 * scale + 1;
+        let position = Point::new(field.x + offset(field.width), field.y + offset(field.height));
+        let font = Font8x8::new(scale);
+        let cell = Rect::new(position.x, position.y, font.text_width(letter), font.height());
+        self.backend.layer2().fill_rect(cell, Color::rgba(0, 0, 0, 0));
+        self.backend.layer2().draw_text(position, letter, &font, color);
     }
 
-    pub fn layer_2_clear(&mut self) {
-        self.layer2.clear();
+    fn play_in_field(&mut self, animation: AnimationId, x: usize, y: usize, start: usize) {
+        let field = match self.field_area(x, y) {
+            Some(field) => field,
+            None => return,
+        };
+        //the sprite is centered in the field, between the grid lines
+        let inset = (field.width - SPRITE_SIZE) / 2;
+        let position = Point::new(field.x + inset, field.y + inset);
+        self.effects.animator.play(animation, position, start);
     }
 
-    pub fn clear_text_on_display(&mut self) {
-        let mut y = 160;
-        for _ in 0..3 {
-            let mut text_writer = self.layer2.text_writer_at(350, y);
-            let result = text_writer.write_str("                ");
-            match result {
-                Ok(result) => result,
-                Err(error) => panic!("error while writing text on display: {}", error),
-            };
-            y += 20;
-        }    
+    /**
+     * the area of the field x, y from the grid line at its left and top on, or None if the field is not
+     * on the zoomed board. The fields are counted like in write_in_field
+     */
+    fn field_area(&self, x: usize, y: usize) -> Option<Rect> {
+        let (x, width) = field_span(x, self.zoom.map(|(first_x, _)| first_x))?;
+        let (y, height) = field_span(y, self.zoom.map(|(_, first_y)| first_y))?;
+        Some(Rect::new(x, y, width, height))
     }
 
     /**
-     * draw ship on x, y coordination. The direction is vertical for true and horizontal for false.
+     * switch the grid lines between the whole and the zoomed board
      */
-    pub fn print_ship(
-        &mut self,
-        ship_size: usize,
-        ship_start_xblock: usize,
-        ship_start_yblock: usize,
-        vertical: bool,
-    ) {
-        let block_size = 25;
-        let x_start_pixel = (ship_start_xblock+1) * block_size + 2;
-        let y_start_pixel = (ship_start_yblock+1) * block_size + 2;
-        if vertical {
-            let x_end_pixel = x_start_pixel + block_size - 4;
-            let mut y_end_pixel = y_start_pixel + (block_size * ship_size) - 4;
-            if y_end_pixel >= 272 {y_end_pixel = 271;}
-            //vertical
-            for c in x_start_pixel..x_end_pixel {
-                for i in y_start_pixel..y_end_pixel {
-                    self.layer2.print_point_color_at(c, i, WHITE);
-                }
-            }
-        } else {
-            let x_end_pixel = x_start_pixel + (block_size * ship_size) - 4;
-            let mut y_end_pixel = y_start_pixel + block_size - 4;
-            if y_end_pixel >= 272 {y_end_pixel = 271;}
-            //horizontal
-            for c in x_start_pixel..x_end_pixel {
-                for i in y_start_pixel..y_end_pixel {
-                    self.layer2.print_point_color_at(c, i, WHITE);
-                }
-            }
+    fn show_zoomed_grid(&mut self, zoomed: bool) {
+        let grid = self.overlay.grid;
+        self.overlay.screen.set_visible(grid, !zoomed);
+        for &widget in self.overlay.zoomed_grid.iter() {
+            self.overlay.screen.set_visible(widget, zoomed);
         }
     }
 
-    pub fn print_confirm_button_enabled(&mut self) {
-        self.print_confirm_button(BLACK);
-    }
-
-    pub fn print_confirm_button_disabled(&mut self) {
-        self.print_confirm_button(GREY);
+    /**
+     * stop all animations and restore the background below them. Ships that were not drawn yet
+     * are dropped, the board draws them again when it is shown
+     */
+    fn stop_animations(&mut self) {
+        self.effects.animator.stop_all(self.backend.layer1());
+        self.effects.sunk_ships.clear();
     }
 
-    pub fn check_confirm_button_touched(&mut self, x: u16, y: u16) -> bool {
-        if (x,y).0 < 457 && (x,y).0 >= 299 && (x,y).1 < 251 && (x,y).1 >= 199 {
-            self.print_confirm_button(WHITE);
-            self.print_confirm_button(BLACK);
-            true
-        } else {
-            false
+    /**
+     * read the touchscreen and queue the recognized taps and gestures
+     */
+    fn sample_touchscreen(&mut self) {
+        let touches = match self.backend.touches() {
+            Some(touches) => touches,
+            None => return,
+        };
+        let now = self.backend.ms();
+        for event in self.touch_tracker.update(touches.first().cloned(), now) {
+            match event {
+                TouchEvent::Down(_) => self.multi_touch = false,
+                // the first finger of a two finger gesture is no tap
+                TouchEvent::Tap(touch) if !self.multi_touch => {
+                    self.pending_input.push(Input::Tap(touch.x, touch.y))
+                }
+                _ => {}
+            }
+        }
+        if let Some(gesture) = self.gestures.update(&touches, now) {
+            self.pending_input.push(Input::Gesture(gesture));
         }
+        self.multi_touch |= self.gestures.is_multi_touch();
     }
 
+    /**
+     * show everything that was drawn since the last call. Drawing goes to the back buffers, so
+     * a screen never becomes visible half drawn
+     */
+    pub fn present(&mut self) {
+        self.backend.present();
+    }
 
-    pub fn touch(&mut self) -> (u16, u16) {
-        let mut touch_x = 0;
-        let mut touch_y = 0;
-        let curr_ticks = system_clock::ticks();
-        if curr_ticks - self.last_touch >= 8 {
-            for touch in &touch::touches(&mut self.touchscreen).unwrap() {
-                touch_x = touch.x;
-                touch_y = touch.y;
-                self.last_touch = curr_ticks;
-            }
-            (touch_x, touch_y)
-        }
-        else {
-            (0,0)
-        }
+    /**
+     * the backend below the display, e.g. to render the layers of the simulator
+     */
+    pub fn backend(&self) -> &B {
+        &self.backend
     }
 
-    pub fn show_start_screen(&mut self) {
-        self.print_bmp_at_location(STARTSCREEN, 0, 0);
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
     }
 
-    pub fn show_lose_screen(&mut self) {
-        self.layer1.clear();
-        self.layer2.clear();
-        self.show_start_screen();
-        self.print_bmp_at_location_black_white(LOSE_FONT, 0, 45); 
+    /**
+     * print a heading in the top left corner of layer 2
+     */
+    fn print_heading(&mut self, text: &str) {
+        let font = Font8x8::new(HEADING_SCALE);
+        self.backend.layer2().draw_text(Point::new(10, 10), text, &font, WHITE);
     }
 
-    pub fn show_win_screen(&mut self) {
-        self.layer1.clear();
-        self.layer2.clear();
-        self.show_start_screen();
-        self.print_bmp_at_location_black_white(WIN_FONT, 0, 45); 
-    }
-
-    fn print_bmp_at_location_black_white(&mut self, pic: &[u8], x: u32, y: u32) {
-        let width = u32::from(pic[18]) + (u32::from(pic[19]) * 256_u32);
-        let height = u32::from(pic[22]) + (u32::from(pic[23]) * 256_u32);
-        let pixel_rest = width % 4;
-        let loc_x = x;
-        let loc_y = y;
-        let pixel_end: u32 = pic.len() as u32 - 1;
-
-        for i in 0..height {
-            let mut bytenr = pixel_end + 1 - (pixel_rest + width * 3) * (i + 1);
-            for j in 0..width {
-                if pic[(bytenr + 2) as usize] == 0 && pic[(bytenr + 1) as usize] == 0  && pic[(bytenr) as usize] == 0  {
-                    self.layer1.print_point_color_at(
-                        (loc_x + j) as usize,
-                        (loc_y + i) as usize,
-                        Color::rgba(
-                            0,
-                            0,
-                            0,
-                            255,
-                        ),
-                    );
-                }
-                bytenr += 3;
-            }
-        }
+    /**
+     * print a large black title, horizontally centered at the line y
+     */
+    fn print_title(&mut self, text: &str, y: usize) {
+        let font = Font8x8::new(TITLE_SCALE);
+        let x = WIDTH.saturating_sub(font.text_width(text)) / 2;
+        self.backend.layer1().draw_text(Point::new(x, y), text, &font, BLACK);
     }
 
-    fn print_bmp_at_location(&mut self, pic: &[u8], x: u32, y: u32) {
-        let width = u32::from(pic[18]) + (u32::from(pic[19]) * 256_u32);
-        let height = u32::from(pic[22]) + (u32::from(pic[23]) * 256_u32);
-        let pixel_rest = width % 4;
-        let loc_x = x;
-        let loc_y = y;
-        let pixel_end: u32 = pic.len() as u32 - 1;
-
-        for i in 0..height {
-            let mut bytenr = pixel_end + 1 - (pixel_rest + width * 3) * (i + 1);
-            for j in 0..width {
-                    self.layer1.print_point_color_at(
-                        (loc_x + j) as usize,
-                        (loc_y + i) as usize,
-                        Color::rgba(
-                            pic[(bytenr + 2) as usize],
-                            pic[(bytenr + 1) as usize],
-                            pic[(bytenr) as usize],
-                            255,
-                        ),
-                    );
-                // }
-                bytenr += 3;
-            }
+    fn print_image_at_location(&mut self, pic: &[u8], x: u32, y: u32) {
+        match Qoi::parse(pic) {
+            Ok(image) => self.backend.layer1().draw_qoi(Point::new(x as usize, y as usize), &image),
+            Err(err) => log_warning!("invalid image: {:?}", err),
         }
     }
 }
\ No newline at end of file
@@ -9,19 +9,30 @@ use crate::gameboard::{
 use crate::network::{
     Connection,
     packets,
-    Network,
-    EthClient,
 };
 use crate::display::{
-    Display
+    Input,
+    SettingsAction,
+    Ui,
 };
+use stm32f7_discovery::system_clock;
+use stm32f7_discovery::touch::{self, Calibration, Gesture, SwipeDirection, Touch, CALIBRATION_TARGETS};
+use stm32f7_discovery::log_warning;
 
-pub struct Game {
+//the scale of a pinch in percent, from which on the enemy grid is zoomed in or out
+const ZOOM_IN_PERCENT: u16 = 150;
+const ZOOM_OUT_PERCENT: u16 = 66;
+
+/**
+ * the turns of a game between the player at the display and the other player behind the connection.
+ * The board plays with its display and network, the simulator with a terminal and a host socket
+ */
+pub struct Game<U: Ui, C: Connection> {
     game_state: Gamestate,
-    display: Display,
+    display: U,
     board: Board,
-    network: Network,
-    ethernet_c: EthClient,
+    connection: C,
+    last_diagnostics_update: usize,
 }
 
 enum Gamestate {
@@ -31,28 +42,31 @@ enum Gamestate {
     Lose,
     GameStart,
     SetupShips,
+    Diagnostics,
+    Settings,
 }
 
 //start game, init field and wait for other player
-pub fn init_new_game(display: Display,net: network::Network, is_server: bool) -> Game {
-    Game::new(display, net, is_server)    
+pub fn init_new_game<U: Ui, C: Connection>(display: U, connection: C) -> Game<U, C> {
+    Game::new(display, connection)
 }
 
-impl Game {
-    fn new(display: Display, net: network::Network, is_server: bool) -> Game {
+impl<U: Ui, C: Connection> Game<U, C> {
+    fn new(display: U, connection: C) -> Game<U, C> {
         Game {
             game_state: Gamestate::GameStart,
             display,
             board: gameboard::gameboard_init(),
-            network: net,
-            ethernet_c: EthClient::new(is_server),
+            connection,
+            last_diagnostics_update: 0,
         }
     }
 
-    pub fn run_game(&mut self) {
+    pub fn run_game(&mut self) -> ! {
 
         self.display.show_start_screen();
         loop {
+            self.connection.poll();
             match self.game_state {
                 Gamestate::YourTurn => self.select_shoot_location(),
                 Gamestate::WaitForEnemy => self.wait_and_check_enemy_shot(),
@@ -62,6 +76,8 @@ impl Game {
                 Gamestate::GameStart => {
                     self.wait_for_start_screen_interaction();
                 },
+                Gamestate::Diagnostics => self.show_diagnostics(),
+                Gamestate::Settings => self.show_settings(),
             } 
         }
     }
@@ -91,14 +107,105 @@ impl Game {
             Gamestate::Lose => {
                 self.game_state = Gamestate::Lose;
             }
+            Gamestate::Diagnostics => {
+                // assert!(self.game_state == Gamestate::GameStart);
+                self.game_state = Gamestate::Diagnostics;
+            }
+            Gamestate::Settings => {
+                self.game_state = Gamestate::Settings;
+            }
         }
     }
 
     fn wait_for_start_screen_interaction(&mut self) {
-        let (x,y) = self.display.touch();
-        if (x,y) != (0,0) {
-            self.set_game_state(Gamestate::SetupShips);
-            self.display.print_background();
+        match self.display.touch() {
+            Some((x, y)) if x >= 420 && y < 50 => {
+                //top right corner opens the network diagnostics
+                self.set_game_state(Gamestate::Diagnostics);
+                self.display.show_diagnostics_screen();
+                self.last_diagnostics_update = 0;
+            }
+            Some((x, y)) if x < 60 && y < 50 => {
+                //top left corner opens the settings
+                self.set_game_state(Gamestate::Settings);
+                self.display.show_settings_screen();
+            }
+            Some(_) => {
+                self.set_game_state(Gamestate::SetupShips);
+                self.display.print_background();
+            }
+            None => {}
+        }
+    }
+
+    fn show_diagnostics(&mut self) {
+        //answers pings of the other board and receives our own ping replies
+        self.connection.receive_pings();
+
+        let ticks = system_clock::ticks();
+        // refresh every second (20 ticks per second)
+        if self.last_diagnostics_update == 0 || ticks - self.last_diagnostics_update >= 20 {
+            self.connection.send_ping();
+            let lines = self.connection.diagnostics();
+            self.display.update_diagnostics(&lines);
+            self.last_diagnostics_update = ticks;
+        }
+
+        if self.display.touch().is_some() {
+            self.set_game_state(Gamestate::GameStart);
+            self.display.layer_2_clear();
+            self.display.show_start_screen();
+        }
+    }
+
+    fn show_settings(&mut self) {
+        let (x, y) = match self.display.touch() {
+            Some(position) => position,
+            None => return,
+        };
+        match self.display.settings_action(x, y) {
+            Some(SettingsAction::Calibrate) => {
+                self.calibrate_touchscreen();
+                self.display.show_settings_screen();
+            }
+            Some(SettingsAction::ResetCalibration) => {
+                touch::set_calibration(Calibration::IDENTITY);
+                self.display.store_calibration(Calibration::IDENTITY);
+            }
+            Some(SettingsAction::Back) => {
+                self.set_game_state(Gamestate::GameStart);
+                self.display.layer_2_clear();
+                self.display.show_start_screen();
+            }
+            None => {}
+        }
+    }
+
+    /**
+     * show the calibration targets one after another and compute the touch transform from the touched positions
+     */
+    fn calibrate_touchscreen(&mut self) {
+        let previous = touch::calibration();
+        //the transform is computed from the raw coordinates
+        touch::set_calibration(Calibration::IDENTITY);
+        let mut touched = [Touch { x: 0, y: 0 }; 3];
+        for (target, touched) in CALIBRATION_TARGETS.iter().zip(touched.iter_mut()) {
+            self.display.show_calibration_target(*target);
+            *touched = loop {
+                if let Some((x, y)) = self.display.touch() {
+                    break Touch { x, y };
+                }
+            };
+        }
+        match Calibration::from_points(CALIBRATION_TARGETS, touched) {
+            Some(calibration) => {
+                touch::set_calibration(calibration);
+                self.display.store_calibration(calibration);
+            }
+            None => {
+                log_warning!("touch calibration failed, the touched points lie on a line");
+                touch::set_calibration(previous);
+            }
         }
     }
 
@@ -117,7 +224,10 @@ impl Game {
 
     fn wait_for_shoot(&mut self) -> network::packets::ShootPacket {
         loop {
-            if let Some(shoot) = self.ethernet_c.recv_shoot(&mut self.network) {
+            //the animations of our last shot keep running while we wait
+            self.display.update_animations();
+            self.connection.poll();
+            if let Some(shoot) = self.connection.recv_shoot() {
                 return shoot;
             }
         }
@@ -135,8 +245,7 @@ impl Game {
         //create feedback packet
         let win = self.board.check_win();
         let feedback = packets::FeedbackPacket::new(hit, ship_sunk_size, win);
-        self.ethernet_c.send_feedback(&mut self.network, feedback);
-        self.network.poll_all();
+        self.connection.send_feedback(feedback);
         if win {
             self.set_game_state(Gamestate::Lose);
        } else {
@@ -149,16 +258,16 @@ impl Game {
 
         let shoot_packet = packets::ShootPacket::new(block.y, block.x); //TODO set x,y public
         //use network file and send package
-        self.ethernet_c.send_shoot(&mut self.network, shoot_packet);
+        self.connection.send_shoot(shoot_packet);
 
         //wait for answer
         let feedback_packet = self.wait_for_feedback();
-        // let feedback_packet = self.ethernet_c.recv_feedback(self.network);
         if feedback_packet.you_win {
             self.set_game_state(Gamestate::Won);
             return;
         } else if feedback_packet.hit {
-            self.display.write_in_field(block.x as usize, block.y as usize, "X");
+            self.display.animate_hit(block.x as usize, block.y as usize);
+            self.display.print_hit(block.x as usize, block.y as usize);
             self.board.enemy_ships_hit[(block.x - 1) as usize][(block.y-1) as usize] = true;
             let sunk_size = feedback_packet.sunk;
             if feedback_packet.sunk != 0 {
@@ -166,13 +275,15 @@ impl Game {
                 self.display.print_text_on_display_layer2(format!("sunk ship of length {}", sunk_size).to_string());
                 let (x, y, dir, size) = self.board.get_enemy_ship_start_dir_len(block.x-1, block.y-1);
                 assert!(size != 0);
-                self.display.print_ship(size as usize, x as usize, y as usize, dir);
+                self.display.animate_sinking(size as usize, x as usize, y as usize, dir);
+                self.board.add_sunk_enemy_ship(x, y, dir, size);
             } else {
                 self.display.clear_text_on_display();
                 self.display.print_text_on_display_layer2("You hit the enemy".to_string().to_string());
             }
         } else {
-            self.display.write_in_field(block.x as usize, block.y as usize, "O");
+            self.display.animate_miss(block.x as usize, block.y as usize);
+            self.display.print_miss(block.x as usize, block.y as usize);
         }
 
         self.set_game_state(Gamestate::WaitForEnemy);
@@ -182,7 +293,9 @@ impl Game {
 
     fn wait_for_feedback(&mut self) ->  network::packets::FeedbackPacket {
         loop {
-            if let Some(feedback) = self.ethernet_c.recv_feedback(&mut self.network) {
+            self.display.update_animations();
+            self.connection.poll();
+            if let Some(feedback) = self.connection.recv_feedback() {
         return feedback;
 }
         }
@@ -195,7 +308,7 @@ impl Game {
         self.display.layer_2_clear();
         self.display.print_background();
         self.display.print_confirm_button_enabled();
-        if self.ethernet_c.is_server {
+        if self.connection.shoots_first() {
             self.set_game_state(Gamestate::YourTurn);
         } else {
             self.set_game_state(Gamestate::WaitForEnemy);
@@ -209,13 +322,73 @@ impl Game {
         let mut confirmed = false;
         let mut block_set = false;
         let mut block = Block{x: 1, y: 1};
+        let mut showing_fleet = false;
+        let mut zoomed = false;
         //create methods in display to handle touch
         while !confirmed {
-            let (x,y) = self.display.touch();
+            self.connection.poll();
+            let (x,y) = match self.display.input() {
+                Some(Input::Tap(x, y)) => self.display.unzoom(x, y),
+                Some(Input::Gesture(Gesture::Swipe(SwipeDirection::Left)))
+                | Some(Input::Gesture(Gesture::Swipe(SwipeDirection::Right))) => {
+                    //swipe to switch between our fleet and the enemy grid
+                    showing_fleet = !showing_fleet;
+                    if zoomed {
+                        self.display.zoom_out();
+                        zoomed = false;
+                    }
+                    if showing_fleet {
+                        self.board.show_fleet(&mut self.display);
+                        self.display.print_confirm_button_disabled();
+                        self.display.print_text_on_display_layer2("your fleet".to_string());
+                    } else {
+                        self.show_enemy_fleet(block, block_set);
+                        self.display.print_confirm_button_enabled();
+                        self.display.print_text_on_display_layer2("select a fire location".to_string());
+                    }
+                    continue;
+                }
+                Some(Input::Gesture(Gesture::TwoFingerTap { .. })) => {
+                    //undo the selected fire location
+                    if block_set && !showing_fleet {
+                        self.display.write_in_field(block.x as usize, block.y as usize, " ");
+                        block_set = false;
+                    }
+                    continue;
+                }
+                Some(Input::Gesture(Gesture::Pinch { center, scale_percent })) => {
+                    //spread two fingers to zoom into the enemy grid, pinch them to zoom out again
+                    if showing_fleet {
+                        continue;
+                    }
+                    let zoom_changed = if !zoomed && scale_percent >= ZOOM_IN_PERCENT {
+                        self.display.zoom_in(center.x, center.y)
+                    } else if zoomed && scale_percent <= ZOOM_OUT_PERCENT {
+                        self.display.zoom_out();
+                        true
+                    } else {
+                        false
+                    };
+                    if zoom_changed {
+                        zoomed = !zoomed;
+                        self.show_enemy_fleet(block, block_set);
+                    }
+                    continue;
+                }
+                Some(Input::Gesture(_)) | None => continue,
+            };
+            if showing_fleet {
+                continue;
+            }
             match self.board.calculate_touch_block(x, y) {
                 None => {
                     if block_set && self.display.check_confirm_button_touched(x,y) {
                       //shot location set   
+                      if zoomed {
+                          //the shot is animated on the whole board
+                          self.display.zoom_out();
+                          self.show_enemy_fleet(block, block_set);
+                      }
                       self.board.enemy_fields_shot[(block.x - 1) as usize][(block.y -1 ) as usize] = true;
                       self.fire(block); //TODO: in fire -> update gameboard information
                       confirmed = true;
@@ -237,5 +410,14 @@ impl Game {
             }
         }
     }
-}
 
+    /**
+     * draw the enemy grid again, with the selected fire location if there is one
+     */
+    fn show_enemy_fleet(&mut self, block: Block, block_set: bool) {
+        self.board.show_enemy_fleet(&mut self.display);
+        if block_set {
+            self.display.write_in_field(block.x as usize, block.y as usize, "x");
+        }
+    }
+}
@@ -1,6 +1,7 @@
-use crate::display::Display;
+use crate::display::{Input, Ui};
 use crate::ships::Ship;
 use alloc::vec::Vec;
+use stm32f7_discovery::touch::Gesture;
 
 pub struct Board {
     ships: Vec<Ship>,
@@ -10,6 +11,8 @@ pub struct Board {
     pub enemy_ships_hit: [[bool; 10]; 10],
     remaining_enemy_ships: [u8; 4],
     pub enemy_fields_shot: [[bool; 10]; 10],
+    sunk_enemy_ships: Vec<Ship>,
+    enemy_shots: [[bool; 10]; 10], //shots of the enemy at our fleet, only used to draw them
 }
 
 #[derive(Copy, Clone)]
@@ -33,6 +36,8 @@ impl Board {
             enemy_ships_hit: [[false; 10]; 10],
             remaining_enemy_ships: [1,2,1,1],
             enemy_fields_shot: [[false; 10]; 10],
+            sunk_enemy_ships: Vec::new(),
+            enemy_shots: [[false; 10]; 10],
         }
     }
 
@@ -54,14 +59,38 @@ impl Board {
         }
     }
 
+    /**
+     * select or deselect a block during the ship setup
+     */
+    fn toggle_setup_field<D: Ui>(&mut self, block: Block, display: &mut D) {
+        let (x, y) = ((block.x - 1) as usize, (block.y - 1) as usize);
+        self.setup_field[x][y] = !self.setup_field[x][y];
+        if self.setup_field[x][y] {
+            display.write_in_field(block.x as usize, block.y as usize, "x");
+        } else {
+            display.write_in_field(block.x as usize, block.y as usize, " ");
+        }
+    }
+
     /**
      * get the user input for setting up the ships, i.e. the x'es where the ship is supposed to be set up
      */
-    pub fn setup_ship(&mut self, length: u8, display: &mut Display) {
+    pub fn setup_ship<D: Ui>(&mut self, length: u8, display: &mut D) {
         display.setup_ship(length); //This is basically double - maybe remove in the initBoard function
         let mut confirmed = false;
+        let mut toggled_blocks = Vec::new();
         while !confirmed {
-            let (x, y) = display.touch();
+            let (x, y) = match display.input() {
+                Some(Input::Tap(x, y)) => (x, y),
+                Some(Input::Gesture(Gesture::TwoFingerTap { .. })) => {
+                    //undo the last change
+                    if let Some(block) = toggled_blocks.pop() {
+                        self.toggle_setup_field(block, display);
+                    }
+                    continue;
+                }
+                Some(Input::Gesture(_)) | None => continue,
+            };
             match self.calculate_touch_block(x, y) {
                 None => {
                     if display.check_confirm_button_touched(x, y) {
@@ -69,14 +98,8 @@ impl Board {
                     }
                 }
                 Some(block) => {
-                    let (x, y) = (block.x - 1, block.y - 1);
-                    if !self.setup_field[x as usize][y as usize] {
-                        self.setup_field[x as usize][y as usize] = true;
-                        display.write_in_field(block.x as usize, block.y as usize, "x");
-                    } else {
-                        self.setup_field[x as usize][y as usize] = false;
-                        display.write_in_field(block.x as usize, block.y as usize, " ");
-                    }
+                    self.toggle_setup_field(block, display);
+                    toggled_blocks.push(block);
                 }
             }
         }
@@ -100,7 +123,7 @@ impl Board {
     /**
      * check whether the given user input is a valid ship
      */
-    fn get_valid_ship(&mut self, len: u8, display: &mut Display) -> bool {
+    fn get_valid_ship<D: Ui>(&mut self, len: u8, display: &mut D) -> bool {
 
         if !self.ship_length_correct(len) {
             return false;
@@ -225,7 +248,66 @@ impl Board {
         true
     }
 
-    pub fn clear_x_es(&mut self, display: &mut Display) {
+    /**
+     * draw our ships and the shots of the enemy on the board
+     */
+    pub fn show_fleet<D: Ui>(&self, display: &mut D) {
+        display.clear_board();
+        for ship in self.ships.iter() {
+            display.print_ship(
+                ship.size as usize,
+                ship.x_start_location as usize,
+                ship.y_start_location as usize,
+                ship.vertical,
+            );
+        }
+        for x in 0..10 {
+            for y in 0..10 {
+                if self.enemy_shots[x][y] {
+                    if self.placed_ships[x][y] {
+                        display.print_hit(x + 1, y + 1);
+                    } else {
+                        display.print_miss(x + 1, y + 1);
+                    }
+                }
+            }
+        }
+    }
+
+    /**
+     * draw our shots and the sunk enemy ships on the board
+     */
+    pub fn show_enemy_fleet<D: Ui>(&self, display: &mut D) {
+        display.clear_board();
+        for x in 0..10 {
+            for y in 0..10 {
+                if self.enemy_fields_shot[x][y] {
+                    if self.enemy_ships_hit[x][y] {
+                        display.print_hit(x + 1, y + 1);
+                    } else {
+                        display.print_miss(x + 1, y + 1);
+                    }
+                }
+            }
+        }
+        for ship in self.sunk_enemy_ships.iter() {
+            display.print_ship(
+                ship.size as usize,
+                ship.x_start_location as usize,
+                ship.y_start_location as usize,
+                ship.vertical,
+            );
+        }
+    }
+
+    /**
+     * remember a sunk enemy ship, so it can be drawn again after switching the view
+     */
+    pub fn add_sunk_enemy_ship(&mut self, x: u8, y: u8, vertical: bool, size: u8) {
+        self.sunk_enemy_ships.push(Ship::new(size, x, y, vertical));
+    }
+
+    pub fn clear_x_es<D: Ui>(&mut self, display: &mut D) {
         for i in 1..11 {
             for j in 1..11 {
                 display.write_in_field(i, j, " ");
@@ -246,6 +328,7 @@ impl Board {
      * shoot at a location, return if hit.  If sunk returns the sunken ship's length. If not sunk, return 0 instead.
      */
     pub fn shoot_at(&mut self, block: Block) -> (bool, u8) {
+        self.enemy_shots[block.x as usize - 1][block.y as usize - 1] = true;
         if !self.fields_shot[block.x as usize - 1][block.y as usize - 1]
             && self.placed_ships[block.x as usize - 1][block.y as usize - 1]
         {
@@ -337,7 +420,7 @@ impl Board {
             for k in 2..6 {
                 if !self.get_enemy_helper(x as i8 - k, y as i8) {
                     before = k as u8;
-                    x_start = x - k as u8 + 1;
+                    x_start = x + 1 - k as u8;
                     break;
                 }
             }
@@ -357,7 +440,7 @@ impl Board {
             for k in 2..6 {
                 if !self.get_enemy_helper(x as i8, y as i8 - k) {
                     before = k as u8;
-                    y_start = y - k as u8 + 1;
+                    y_start = y + 1 - k as u8;
                     break;
                 }
             }
@@ -418,7 +501,7 @@ impl Board {
     /**
      * Get the 5 ships from the user
      */
-    pub fn initial_setup(&mut self, display: &mut Display) {
+    pub fn initial_setup<D: Ui>(&mut self, display: &mut D) {
         display.setup_ship(5);
         self.setup_ship(5, display);
         display.setup_ship(4);
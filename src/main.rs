@@ -10,14 +10,16 @@ use alloc_cortex_m::CortexMHeap;
 use core::alloc::Layout as AllocLayout;
 use core::panic::PanicInfo;
 use cortex_m_rt::{entry, exception};
-use cortex_m_semihosting::{hprintln};
 use stm32f7::stm32f7x6::{CorePeripherals, Peripherals};
 use stm32f7_discovery::{
     gpio::{GpioPort, OutputPin},
-    init,
+    init, lcd,
+    log_error, log_info,
+    logging::{self, SemihostingSink, SyslogSink},
     system_clock::{self, Hz},
     touch,
 };
+mod animations;
 mod display;
 mod ships;
 mod game;
@@ -25,6 +27,15 @@ mod gameboard;
 mod network;
 
 const IS_SERVER: bool = false;
+//both boards must use the same address family
+const ADDRESS_FAMILY: network::AddressFamily = network::AddressFamily::Ipv4;
+//semihosting halts the board if no debugger is attached
+const LOG_TO_SEMIHOSTING: bool = false;
+//record all ethernet frames for debugging, download them with `nc <board ip> 5555 > game.pcap`
+const CAPTURE_PACKETS: bool = false;
+//serve screenshots of the display, download them with `nc <board ip> 5556 > screen.bmp`.
+//The user button captures the display for the next download
+const SERVE_SCREENSHOTS: bool = false;
 
 #[entry]
 fn main() -> ! {
@@ -41,7 +52,7 @@ fn main() -> ! {
     let mut fmc = peripherals.FMC;
     let mut ltdc = peripherals.LTDC;
     let mut syscfg = peripherals.SYSCFG;
-    let mut ethernet_mac = peripherals.ETHERNET_MAC;
+    let ethernet_mac = peripherals.ETHERNET_MAC;
     let ethernet_dma = peripherals.ETHERNET_DMA;
 
     let gpio_a = GpioPort::new(peripherals.GPIOA);
@@ -66,6 +77,7 @@ fn main() -> ! {
 
     init::init_sdram(&mut rcc, &mut fmc);
     let mut lcd = init::init_lcd(&mut ltdc, &mut rcc);
+    lcd::dma2d::enable(peripherals.DMA2D);
 
     pins.display_enable.set(true);
     pins.backlight.set(true);
@@ -78,24 +90,47 @@ fn main() -> ! {
     while system_clock::ticks() - ticks <= 10 {}
     touch::check_family_id(&mut touchscreen).unwrap();
 
-    let display = display::init_display(&mut lcd, touchscreen);
+    let display = display::init_display(&mut lcd, touchscreen, flash);
 
     // Initialize the allocator BEFORE you use it
     unsafe { ALLOCATOR.init(cortex_m_rt::heap_start() as usize, 50_000) }
 
+    if LOG_TO_SEMIHOSTING {
+        logging::add_sink(SemihostingSink);
+    }
+    let hostname = if IS_SERVER {"schiffe-server"} else {"schiffe-client"};
+    let (syslog_sink, syslog_queue) = SyslogSink::new(hostname, "schiffe_versenken", 16);
+    logging::add_sink(syslog_sink);
+
+    if let Some(calibration) = touch::Calibration::load() {
+        touch::set_calibration(calibration);
+        log_info!("loaded touch calibration");
+    }
+
     // turn led on
     pins.led.set(true);
 
-    let net = network::init(&mut rcc, &mut syscfg, &mut ethernet_mac, ethernet_dma, IS_SERVER);
+    let net = network::init(&mut rcc, &mut syscfg, ethernet_mac, ethernet_dma, IS_SERVER, ADDRESS_FAMILY);
     
     match net {
         Ok(value) => {
-            let nw: network::Network = value;
-            let mut game = game::init_new_game(display , nw, IS_SERVER);
+            let mut nw: network::Network = value;
+            nw.enable_remote_logging(syslog_queue);
+            if CAPTURE_PACKETS {
+                nw.enable_capture();
+            }
+            if SERVE_SCREENSHOTS {
+                if let Some(screenshot) = lcd.screenshot() {
+                    nw.enable_screenshots(screenshot);
+                }
+            }
+            log_info!("network initialized");
+            //the button captures a screenshot, see Network::capture_screenshot
+            let connection = network::EthClient::new(nw, IS_SERVER, pins.button);
+            let mut game = game::init_new_game(display, connection);
             game.run_game();
-            match hprintln!("connected") {_ => {}}
         }
-        Err(_e) => {match hprintln!("failed to init network") {_ => {}}}
+        Err(e) => log_error!("failed to init network: {:?}", e),
     }
 
 
@@ -0,0 +1,42 @@
+use super::packets::{FeedbackPacket, ShootPacket};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/**
+ * the other player as seen by the turns of a game. The board reaches it over its ethernet interface,
+ * the simulator over a host socket or as a computer player. The recv methods never block and return
+ * None until the packet arrived
+ */
+pub trait Connection {
+    /**
+     * whether we shoot first, which is the role of the server board
+     */
+    fn shoots_first(&self) -> bool;
+
+    fn send_shoot(&mut self, shoot: ShootPacket);
+    fn recv_shoot(&mut self) -> Option<ShootPacket>;
+    fn send_feedback(&mut self, feedback: FeedbackPacket);
+    fn recv_feedback(&mut self) -> Option<FeedbackPacket>;
+
+    /**
+     * called in every loop that waits for input or the other player, e.g. to check the screenshot button
+     */
+    fn poll(&mut self) {}
+
+    /**
+     * answer the pings of the other player and receive the replies to ours, while the diagnostics are shown
+     */
+    fn receive_pings(&mut self) {}
+
+    /**
+     * send a ping to the other player, the round trip time is part of the diagnostics once the reply arrives
+     */
+    fn send_ping(&mut self) {}
+
+    /**
+     * the diagnostics of the connection as lines of text, empty if there are none
+     */
+    fn diagnostics(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+}
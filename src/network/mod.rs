@@ -2,115 +2,388 @@
 
 use smoltcp;
 use smoltcp::iface::EthernetInterface;
-use smoltcp::socket::{Socket, SocketSet, UdpPacketMetadata, UdpSocket, UdpSocketBuffer};
+use smoltcp::socket::{Socket, SocketHandle, SocketSet, UdpPacketMetadata, UdpSocket, UdpSocketBuffer};
 use smoltcp::time::Instant;
-use smoltcp::wire::{EthernetAddress, IpAddress, IpEndpoint, Ipv4Address};
+use smoltcp::wire::{IpAddress, IpEndpoint, Ipv4Address, Ipv6Address};
 
+mod connection;
 pub mod packets;
+pub use self::connection::Connection;
 use self::packets::ShootPacket;
 use self::packets::FeedbackPacket;
 use self::packets::WhoamiPacket;
+use self::packets::PingPacket;
 use self::packets::Serializable;
 
+use alloc::string::String;
 use alloc::vec::Vec;
 use stm32f7::stm32f7x6::{RCC, SYSCFG, ETHERNET_MAC, ETHERNET_DMA};
 use stm32f7_discovery::{ethernet, system_clock};
-use cortex_m_semihosting::hprintln;
+use stm32f7_discovery::gpio::InputPin;
+use stm32f7_discovery::ethernet::capture::{Capture, PcapServer, PCAP_PORT};
+use stm32f7_discovery::lcd::Screenshot;
+use stm32f7_discovery::lcd::screenshot::{ScreenshotServer, SCREENSHOT_PORT};
+use stm32f7_discovery::logging::syslog::{SyslogQueue, SYSLOG_PORT};
+use stm32f7_discovery::{log_debug, log_error, log_info, log_warning};
 
 const PORT: u16 = 1337;
-const CLIENT_ETH_ADDR: EthernetAddress = EthernetAddress([0x00, 0x11, 0x22, 0x33, 0x44, 0x01]);
 const CLIENT_IP_ADDR: Ipv4Address = Ipv4Address([192, 168, 42, 2]);
-const SERVER_ETH_ADDR: EthernetAddress = EthernetAddress([0x00, 0x11, 0x22, 0x33, 0x44, 0x02]);
 const SERVER_IP_ADDR: Ipv4Address = Ipv4Address([192, 168, 42, 1]);
+//link-local multicast group of all boards that are looking for an opponent
+const DISCOVERY_GROUP: Ipv6Address = Ipv6Address([0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x13, 0x37]);
+//syslog messages are broadcast, so any machine in the network can collect them
+const SYSLOG_COLLECTOR_IP_ADDR: Ipv4Address = Ipv4Address::BROADCAST;
+const SYSLOG_COLLECTOR_IPV6_ADDR: Ipv6Address = Ipv6Address::LINK_LOCAL_ALL_NODES;
+const SYSLOG_LOCAL_PORT: u16 = 1514;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AddressFamily {
+    //fixed addresses for server and client
+    Ipv4,
+    //link-local addresses, the opponent is discovered through a multicast group
+    Ipv6,
+}
 
 pub struct Network {
     ethernet_interface: EthernetInterface<'static, 'static, 'static, ethernet::EthernetDevice>,
     sockets: SocketSet<'static, 'static, 'static>,
-    partner_ip_addr: Ipv4Address,
+    game_socket: SocketHandle,
+    syslog_socket: SocketHandle,
+    syslog_queue: Option<SyslogQueue>,
+    pcap_server: Option<PcapServer>,
+    screenshot_server: Option<ScreenshotServer>,
+    ethernet_mac: ETHERNET_MAC,
+    ip_addr: IpAddress,
+    partner: Partner,
+    stats: Statistics,
+}
+
+/**
+ * the other board, either known or still to be discovered through a multicast group
+ */
+#[derive(Debug, Copy, Clone)]
+pub enum Partner {
+    Known(IpEndpoint),
+    Discovering(IpEndpoint),
+}
+
+impl Partner {
+    pub fn endpoint(&self) -> IpEndpoint {
+        match self {
+            Partner::Known(endpoint) | Partner::Discovering(endpoint) => *endpoint,
+        }
+    }
+}
+
+/**
+ * packet counters of the game connection
+ */
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Statistics {
+    pub sent: usize,
+    pub received: usize,
+    pub dropped: usize,
+    pub errors: usize,
+    pub last_error: Option<smoltcp::Error>,
+    pub rtt_ms: Option<usize>,
+}
+
+/**
+ * snapshot of everything shown on the diagnostics screen
+ */
+pub struct Diagnostics {
+    pub link_up: bool,
+    pub link: ethernet::AutoNegotiationResult,
+    pub ip_addr: IpAddress,
+    pub partner: Partner,
+    pub stats: Statistics,
+    pub device: ethernet::Statistics,
+}
+
+impl Diagnostics {
+    /**
+     * format the diagnostics as lines of text for the display
+     */
+    pub fn lines(&self) -> Vec<String> {
+        let speed = match self.link.speed {
+            ethernet::Speed::Speed10M => "10M",
+            ethernet::Speed::Speed100M => "100M",
+        };
+        let duplex = if self.link.duplex {"full"} else {"half"};
+        let rtt = match self.stats.rtt_ms {
+            Some(rtt) => format!("{} ms", rtt),
+            None => "-".into(),
+        };
+        let last_error = match self.stats.last_error {
+            Some(e) => format!("{:?}", e),
+            None => "-".into(),
+        };
+        let peer = match self.partner {
+            Partner::Known(endpoint) => format!("{}", endpoint.addr),
+            Partner::Discovering(_) => "discovering...".into(),
+        };
+        let rx_errors = self.device.rx_errors;
+        vec![
+            format!("link:     {}", if self.link_up {"up"} else {"down"}),
+            format!("speed:    {} {} duplex", speed, duplex),
+            format!("own ip:   {}", self.ip_addr),
+            format!("peer ip:  {}", peer),
+            format!("rtt:      {}", rtt),
+            format!("packets:  sent {} recv {} drop {}", self.stats.sent, self.stats.received, self.stats.dropped),
+            format!("frames:   tx {} rx {}", self.device.tx_frames, self.device.rx_frames),
+            format!("rx err:   crc {} rx {} wd {} lc {}", rx_errors.crc, rx_errors.receive, rx_errors.watchdog_timeout, rx_errors.late_collision),
+            format!("          giant {} ovf {} desc {}", rx_errors.giant_frame, rx_errors.overflow, rx_errors.descriptor),
+            format!("net err:  {} last {}", self.stats.errors, last_error),
+        ]
+    }
 }
 
 impl Network {
     pub fn get_udp_packet(&mut self) -> Result<Option<Vec<u8>>, smoltcp::Error> {
+        let result = self.receive_udp_packet();
+        match result {
+            Ok(Some((data, remote_endpoint))) => {
+                self.stats.received += 1;
+                if let Partner::Discovering(_) = self.partner {
+                    log_info!("found opponent at {}", remote_endpoint);
+                    self.partner = Partner::Known(remote_endpoint);
+                }
+                if data.len() == PingPacket::len() {
+                    self.handle_ping(PingPacket::deserialize(&data));
+                    Ok(None)
+                } else {
+                    Ok(Some(data))
+                }
+            },
+            Err(e) => {
+                self.record_error(e);
+                Err(e)
+            },
+            Ok(None) => Ok(None),
+        }
+    }
+
+    fn receive_udp_packet(&mut self) -> Result<Option<(Vec<u8>, IpEndpoint)>, smoltcp::Error> {
         match self.ethernet_interface.poll(
             &mut self.sockets,
             Instant::from_millis(system_clock::ms() as i64),
         ) {
             Err(smoltcp::Error::Exhausted) => {
-                let mut socket = &mut self.sockets.iter_mut().nth(0).unwrap();
-                Network::poll_udp_packet(&mut socket)
+                self.service_sockets();
+                let mut socket = self.sockets.get::<UdpSocket>(self.game_socket);
+                Network::recv_udp_packet(&mut socket)
             },
             Err(e) => Err(e),
             Ok(socket_changed) => if socket_changed {
-                let mut socket = &mut self.sockets.iter_mut().nth(0).unwrap();
-                Network::poll_udp_packet(&mut socket)
+                self.service_sockets();
+                let mut socket = self.sockets.get::<UdpSocket>(self.game_socket);
+                Network::recv_udp_packet(&mut socket)
             } else {
+                self.service_sockets();
                 Ok(None)
             },
         }
     }
 
+    /**
+     * work for the helper sockets that has to be done after each poll of the interface
+     */
+    fn service_sockets(&mut self) {
+        self.flush_log();
+        if let Some(ref mut server) = self.pcap_server {
+            if let Some(capture) = self.ethernet_interface.device_mut().capture() {
+                server.poll(&mut self.sockets, capture);
+            }
+        }
+        if let Some(ref mut server) = self.screenshot_server {
+            server.poll(&mut self.sockets);
+        }
+    }
+
+    /**
+     * record all frames and serve them as pcap file on the PCAP_PORT, e.g. `nc <board ip> 5555 > game.pcap`
+     */
+    pub fn enable_capture(&mut self) {
+        //the heap is small, so only keep the start of each frame. Game packets are far shorter anyway
+        self.ethernet_interface.device_mut().start_capture(Capture::new(8 * 1024, 128));
+        self.pcap_server = Some(PcapServer::new(&mut self.sockets, PCAP_PORT));
+        log_info!("packet capture enabled on port {}", PCAP_PORT);
+    }
+
+    /**
+     * serve screenshots of the display as BMP file on the SCREENSHOT_PORT, e.g. `nc <board ip> 5556 > screen.bmp`.
+     * Connecting captures the display, unless a screenshot was taken with capture_screenshot before
+     */
+    pub fn enable_screenshots(&mut self, screenshot: Screenshot<'static>) {
+        self.screenshot_server = Some(ScreenshotServer::new(&mut self.sockets, SCREENSHOT_PORT, screenshot));
+        log_info!("screenshots enabled on port {}", SCREENSHOT_PORT);
+    }
+
+    /**
+     * capture the display now, the next download on the SCREENSHOT_PORT returns this screenshot
+     */
+    pub fn capture_screenshot(&mut self) {
+        match self.screenshot_server {
+            Some(ref mut server) => {
+                server.capture();
+                log_info!("captured screenshot, download it from port {}", SCREENSHOT_PORT);
+            }
+            None => log_warning!("screenshots are not enabled"),
+        }
+    }
+
+    /**
+     * send the queued syslog messages, if remote logging is enabled
+     */
+    fn flush_log(&mut self) {
+        if let Some(ref queue) = self.syslog_queue {
+            let mut socket = self.sockets.get::<UdpSocket>(self.syslog_socket);
+            let collector_addr = match self.ip_addr {
+                IpAddress::Ipv6(_) => IpAddress::Ipv6(SYSLOG_COLLECTOR_IPV6_ADDR),
+                _ => IpAddress::Ipv4(SYSLOG_COLLECTOR_IP_ADDR),
+            };
+            let collector = IpEndpoint::new(collector_addr, SYSLOG_PORT);
+            // errors are not logged here, since that would queue yet another message
+            match queue.flush(&mut socket, collector) {_ => {}}
+        }
+    }
+
+    /**
+     * send log messages of the passed syslog sink through this interface
+     */
+    pub fn enable_remote_logging(&mut self, queue: SyslogQueue) {
+        self.syslog_queue = Some(queue);
+    }
+
     fn poll_udp_packet(socket: &mut Socket) -> Result<Option<Vec<u8>>, smoltcp::Error> {
         match socket {
-            Socket::Udp(ref mut socket) => { 
-
-                if socket.can_recv() {
-                    match socket.recv() {
-                        Ok((data, _remote_endpoint)) => Ok(Some(Vec::from(data))),
-                        Err(err) => Err(err),
-                    }
-                } else {
-                    Ok(None)
-                }
-            },
+            Socket::Udp(ref mut socket) => Network::recv_udp_packet(socket).map(|packet| packet.map(|(data, _)| data)),
             _ => Ok(None),
         }
     }
 
+    fn recv_udp_packet(socket: &mut UdpSocket) -> Result<Option<(Vec<u8>, IpEndpoint)>, smoltcp::Error> {
+        if socket.can_recv() {
+            match socket.recv() {
+                Ok((data, remote_endpoint)) => Ok(Some((Vec::from(data), remote_endpoint))),
+                Err(err) => Err(err),
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
     pub fn poll_all(&mut self) {
         match self.ethernet_interface.poll(&mut self.sockets, Instant::from_millis(system_clock::ms() as i64)) {_ => {}}
         for mut socket in self.sockets.iter_mut() {
-            match Network::poll_udp_packet(&mut socket) {_ => {}}
+            if let Ok(Some(_)) = Network::poll_udp_packet(&mut socket) {
+                self.stats.dropped += 1;
+            }
         }
     }
 
     pub fn send_udp_packet(&mut self, data: &[u8]) {
-        let endpoint = IpEndpoint::new(IpAddress::Ipv4(self.partner_ip_addr), PORT);
-        for mut socket in self.sockets.iter_mut() {
-            Network::push_udp_packet(&mut socket, endpoint, data);
+        let endpoint = self.partner.endpoint();
+        let result = {
+            let mut socket = self.sockets.get::<UdpSocket>(self.game_socket);
+            Network::push_udp_packet(&mut socket, endpoint, data)
+        };
+        match result {
+            Ok(true) => self.stats.sent += 1,
+            Ok(false) => self.stats.dropped += 1,
+            Err(e) => self.record_error(e),
         }
     }
 
-    fn push_udp_packet(socket: &mut Socket, endpoint: IpEndpoint, data: &[u8]) {
-        if let Socket::Udp(ref mut socket) = socket {
-            if socket.can_send() {
-                let result = socket.send_slice(data, endpoint);
-                match result {
-                    Ok(_) => {}
-                    Err(e) => {match hprintln!("error {:?}", e) {_ => {}}}
+    fn push_udp_packet(socket: &mut UdpSocket, endpoint: IpEndpoint, data: &[u8]) -> Result<bool, smoltcp::Error> {
+        if socket.can_send() {
+            let result = socket.send_slice(data, endpoint);
+            match result {
+                Ok(_) => return Ok(true),
+                Err(e) => {
+                    log_warning!("error {:?}", e);
+                    return Err(e);
                 }
             }
         }
+        Ok(false)
+    }
+
+    /**
+     * send a ping to the other board, the round trip time is updated once the reply arrives
+     */
+    pub fn send_ping(&mut self) {
+        let ping = PingPacket::new(system_clock::ms() as u32);
+        self.send_udp_packet(&ping.serialize());
+    }
+
+    fn handle_ping(&mut self, ping: PingPacket) {
+        if ping.is_reply {
+            let now = system_clock::ms() as u32;
+            self.stats.rtt_ms = Some(now.wrapping_sub(ping.timestamp) as usize);
+        } else {
+            self.send_udp_packet(&ping.reply().serialize());
+        }
+    }
+
+    /**
+     * count a received packet that the game could not use
+     */
+    pub fn count_dropped(&mut self) {
+        self.stats.dropped += 1;
+    }
+
+    fn record_error(&mut self, error: smoltcp::Error) {
+        match error {
+            smoltcp::Error::Exhausted | smoltcp::Error::Unrecognized => {}
+            e => {
+                self.stats.errors += 1;
+                self.stats.last_error = Some(e);
+            }
+        }
+    }
+
+    pub fn diagnostics(&mut self) -> Diagnostics {
+        let device = self.ethernet_interface.device();
+        Diagnostics {
+            link_up: ethernet::link_up(&mut self.ethernet_mac),
+            link: device.link(),
+            ip_addr: self.ip_addr,
+            partner: self.partner,
+            stats: self.stats,
+            device: device.statistics(),
+        }
     }
 }
 
 pub fn init(
     rcc: &mut RCC, 
     syscfg: &mut SYSCFG, 
-    ethernet_mac: &mut ETHERNET_MAC, 
+    mut ethernet_mac: ETHERNET_MAC,
     ethernet_dma: ETHERNET_DMA,
-    is_server: bool) -> Result<Network, ethernet::PhyError> {
-    let ethernet_addr = if is_server {SERVER_ETH_ADDR} else {CLIENT_ETH_ADDR};
-    let ip_addr = if is_server {SERVER_IP_ADDR} else {CLIENT_IP_ADDR};
-    let partner_ip_addr = if is_server {CLIENT_IP_ADDR} else {SERVER_IP_ADDR};
+    is_server: bool,
+    address_family: AddressFamily) -> Result<Network, ethernet::PhyError> {
+    let ethernet_addr = ethernet::unique_ethernet_address();
+    let ipv4_addr = if is_server {SERVER_IP_ADDR} else {CLIENT_IP_ADDR};
+    let (ip_addr, partner) = match address_family {
+        AddressFamily::Ipv4 => {
+            let partner_ip_addr = if is_server {CLIENT_IP_ADDR} else {SERVER_IP_ADDR};
+            (IpAddress::Ipv4(ipv4_addr), Partner::Known(IpEndpoint::new(IpAddress::Ipv4(partner_ip_addr), PORT)))
+        }
+        AddressFamily::Ipv6 => {
+            let link_local_addr = ethernet::ipv6_link_local_address(ethernet_addr);
+            (IpAddress::Ipv6(link_local_addr), Partner::Discovering(IpEndpoint::new(IpAddress::Ipv6(DISCOVERY_GROUP), PORT)))
+        }
+    };
     let ethernet_interface = ethernet::EthernetDevice::new(
         Default::default(),
         Default::default(),
         rcc,
         syscfg,
-        ethernet_mac,
+        &mut ethernet_mac,
         ethernet_dma,
         ethernet_addr,
-        ip_addr
+        ipv4_addr
     ).map(|device| {
         let iface = device.into_interface();
         let prev_ip_addr = iface.ipv4_addr().unwrap();
@@ -119,50 +392,97 @@ pub fn init(
     if let Err(e) = ethernet_interface { return Err(e);}
 
     let mut sockets = SocketSet::new(Vec::new());
-    let endpoint = IpEndpoint::new(IpAddress::Ipv4(ip_addr), PORT);
-    match hprintln!("IP: {:?}", ip_addr) {_ => {}}
+    //with IPv6 the socket must not be bound to our own address, otherwise packets to the discovery group are rejected
+    let endpoint = match address_family {
+        AddressFamily::Ipv4 => IpEndpoint::new(ip_addr, PORT),
+        AddressFamily::Ipv6 => IpEndpoint::from(PORT),
+    };
+    log_info!("IP: {}", ip_addr);
 
     let udp_rx_buffer = UdpSocketBuffer::new(vec![UdpPacketMetadata::EMPTY; 20], vec![0u8; 512]);
     let udp_tx_buffer = UdpSocketBuffer::new(vec![UdpPacketMetadata::EMPTY; 20], vec![0u8; 512]);
     let mut udp_socket = UdpSocket::new(udp_rx_buffer, udp_tx_buffer);
     udp_socket.bind(endpoint).unwrap();
-    sockets.add(udp_socket);
+    let game_socket = sockets.add(udp_socket);
+
+    let syslog_rx_buffer = UdpSocketBuffer::new(vec![UdpPacketMetadata::EMPTY; 1], vec![0u8; 0]);
+    let syslog_tx_buffer = UdpSocketBuffer::new(vec![UdpPacketMetadata::EMPTY; 8], vec![0u8; 1024]);
+    let mut syslog_socket = UdpSocket::new(syslog_rx_buffer, syslog_tx_buffer);
+    syslog_socket.bind(SYSLOG_LOCAL_PORT).unwrap();
+    let syslog_socket = sockets.add(syslog_socket);
 
     Ok(Network {
         ethernet_interface: ethernet_interface.unwrap().0,
         sockets,
-        partner_ip_addr,
+        game_socket,
+        syslog_socket,
+        syslog_queue: None,
+        pcap_server: None,
+        screenshot_server: None,
+        ethernet_mac,
+        ip_addr,
+        partner,
+        stats: Statistics::default(),
     })
 }
 
-pub trait Connection {
-    fn send_shoot(&mut self, network: &mut Network, shoot: ShootPacket);
-    fn recv_shoot(&mut self, network: &mut Network) -> Option<ShootPacket>;
-    fn send_feedback(&mut self, network: &mut Network, feedback: FeedbackPacket);
-    fn recv_feedback(&mut self, network: &mut Network) -> Option<FeedbackPacket>;
-    fn is_other_connected(&mut self, network: &mut Network) -> bool;
-    fn send_whoami(&mut self, network: &mut Network);
-}
-
-pub struct EthClient {
-    pub is_server: bool
+/**
+ * the other board, reached through the network. The button captures a screenshot, see Network::capture_screenshot
+ */
+pub struct EthClient<B> {
+    network: Network,
+    pub is_server: bool,
+    screenshot_button: B,
+    screenshot_button_pressed: bool,
 }
 
-impl EthClient {
-    pub fn new(server: bool) -> EthClient {
+impl<B: InputPin> EthClient<B> {
+    pub fn new(network: Network, server: bool, screenshot_button: B) -> EthClient<B> {
         EthClient {
-            is_server: server
+            network,
+            is_server: server,
+            screenshot_button,
+            screenshot_button_pressed: false,
         }
     }
+
+    pub fn is_other_connected(&mut self) -> bool {
+        let result = self.network.get_udp_packet();
+        match result {
+            Ok(value) => if let Some(data) = value {
+                if data.len() == WhoamiPacket::len() {
+                    return true;
+                }
+                if data.len() == FeedbackPacket::len() {
+                    return true;
+                }
+                if data.len() == ShootPacket::len() {
+                    return true;
+                }
+            },
+            Err(e) => {
+                log_debug!("errortest: {:?}", e);
+            }
+        }
+        false
+    }
+
+    pub fn send_whoami(&mut self) {
+        self.network.send_udp_packet(&WhoamiPacket {is_server: self.is_server}.serialize());
+    }
 }
 
-impl Connection for EthClient {
-    fn send_shoot(&mut self, network: &mut Network, shoot: ShootPacket) {
-        network.send_udp_packet(&shoot.serialize());
+impl<B: InputPin> Connection for EthClient<B> {
+    fn shoots_first(&self) -> bool {
+        self.is_server
     }
 
-    fn recv_shoot(&mut self, network: &mut Network) -> Option<ShootPacket> {
-        let result = network.get_udp_packet();
+    fn send_shoot(&mut self, shoot: ShootPacket) {
+        self.network.send_udp_packet(&shoot.serialize());
+    }
+
+    fn recv_shoot(&mut self) -> Option<ShootPacket> {
+        let result = self.network.get_udp_packet();
         match result {
             Ok(value) => if let Some(data) = value {
                 if data.len() == ShootPacket::len() {
@@ -170,22 +490,24 @@ impl Connection for EthClient {
                     return Some(shoot);
                 }
                 else {
-                    match hprintln!("wrong package length") {_ => {}}
+                    self.network.count_dropped();
+                    log_warning!("wrong package length");
                 }
             },
             Err(e) => {
-                match hprintln!("error: {:?}", e) {_ => {}}
+                log_error!("error: {:?}", e);
             }
         }
         None
     }
 
-    fn send_feedback(&mut self, network: &mut Network, feedback: FeedbackPacket) {
-        network.send_udp_packet(&feedback.serialize());
+    fn send_feedback(&mut self, feedback: FeedbackPacket) {
+        self.network.send_udp_packet(&feedback.serialize());
+        self.network.poll_all();
     }
 
-    fn recv_feedback(&mut self, network: &mut Network) -> Option<FeedbackPacket> {
-        let result = network.get_udp_packet();
+    fn recv_feedback(&mut self) -> Option<FeedbackPacket> {
+        let result = self.network.get_udp_packet();
         match result {
             Ok(value) => if let Some(data) = value {
                 if data.len() == FeedbackPacket::len() {
@@ -196,34 +518,30 @@ impl Connection for EthClient {
             Err(smoltcp::Error::Exhausted) => {}
             Err(smoltcp::Error::Unrecognized) => {}
             Err(e) => {
-                match hprintln!("error: {:?}", e) {_ => {}}
+                log_error!("error: {:?}", e);
             }
         }
         None
     }
 
-    fn is_other_connected(&mut self, network: &mut Network) -> bool {
-        let result = network.get_udp_packet();
-        match result {
-            Ok(value) => if let Some(data) = value {
-                if data.len() == WhoamiPacket::len() {
-                    return true;
-                }
-                if data.len() == FeedbackPacket::len() {
-                    return true;
-                }
-                if data.len() == ShootPacket::len() {
-                    return true;
-                }
-            },
-            Err(e) => {
-                match hprintln!("errortest: {:?}", e) {_ => {}}
-            }
+    fn poll(&mut self) {
+        let pressed = self.screenshot_button.get();
+        if pressed && !self.screenshot_button_pressed {
+            self.network.capture_screenshot();
         }
-        false
+        self.screenshot_button_pressed = pressed;
+    }
+
+    fn receive_pings(&mut self) {
+        //pings are handled while receiving, other packets are of no use on the diagnostics screen
+        match self.network.get_udp_packet() {_ => {}}
+    }
+
+    fn send_ping(&mut self) {
+        self.network.send_ping();
     }
 
-    fn send_whoami(&mut self, network: &mut Network) {
-        network.send_udp_packet(&WhoamiPacket {is_server: self.is_server}.serialize());
+    fn diagnostics(&mut self) -> Vec<String> {
+        self.network.diagnostics().lines()
     }
 }
\ No newline at end of file
@@ -21,6 +21,12 @@ pub struct WhoamiPacket {
     pub is_server: bool,
 }
 
+#[derive(Debug, Copy, Clone)]
+pub struct PingPacket {
+    pub is_reply: bool,
+    pub timestamp: u32,
+}
+
 impl ShootPacket {
     pub fn new(l: u8, c: u8) -> ShootPacket {
         ShootPacket {
@@ -48,6 +54,25 @@ impl WhoamiPacket {
     }
 }
 
+impl PingPacket {
+    pub fn new(timestamp: u32) -> PingPacket {
+        PingPacket {
+            is_reply: false,
+            timestamp,
+        }
+    }
+
+    /**
+     * the answer to this ping, carrying the timestamp of the request back to the sender
+     */
+    pub fn reply(&self) -> PingPacket {
+        PingPacket {
+            is_reply: true,
+            timestamp: self.timestamp,
+        }
+    }
+}
+
 pub trait Serializable {
     fn serialize(&self) -> Vec<u8>;
     fn deserialize(input: &[u8]) -> Self;
@@ -124,4 +149,28 @@ impl Serializable for WhoamiPacket {
     fn len() -> usize {
         1
     }
-}
\ No newline at end of file
+}
+
+impl Serializable for PingPacket {
+    fn serialize(&self) -> Vec<u8> {
+        let mut result = Vec::new();
+        if self.is_reply {
+            result.push(255);
+        } else {
+            result.push(0);
+        }
+        result.extend_from_slice(&self.timestamp.to_be_bytes());
+        result
+    }
+
+    fn deserialize(input: &[u8]) -> PingPacket {
+        PingPacket {
+            is_reply: input[0] == 255,
+            timestamp: u32::from_be_bytes([input[1], input[2], input[3], input[4]]),
+        }
+    }
+
+    fn len() -> usize {
+        5
+    }
+}
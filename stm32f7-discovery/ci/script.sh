@@ -5,6 +5,7 @@ main() {
     cargo build --release
     cargo build --examples
     cargo build --examples --release
+    cargo build --features embedded-graphics
 }
 
 main
@@ -35,31 +35,30 @@ use sh::hio::{self, HStdout};
 use smoltcp::{
     socket::{Socket, TcpSocket, TcpSocketBuffer, UdpPacketMetadata, UdpSocket, UdpSocketBuffer},
     time::Instant,
-    wire::{EthernetAddress, IpEndpoint},
+    wire::IpEndpoint,
 };
 use stm32f7::stm32f7x6::{
-    self as device, CorePeripherals, Interrupt, Peripherals, ETHERNET_DMA, ETHERNET_MAC, RCC, SAI2,
+    CorePeripherals, Interrupt, Peripherals, ETHERNET_DMA, ETHERNET_MAC, RCC, SAI2,
     SYSCFG,
 };
 use stm32f7_discovery::{
     ethernet,
     future_mutex::FutureMutex,
     gpio::{GpioPort, InputPin, OutputPin},
-    i2c::I2C,
     init,
     interrupts::{self, InterruptRequest, Priority},
     lcd::{self, AudioWriter, Color, Framebuffer, Layer},
     random::Rng,
     sd,
     system_clock::{self, Hz},
-    task_runtime, touch,
+    task_runtime,
+    touch::{self, TouchEvent},
 };
 
 #[global_allocator]
 static ALLOCATOR: CortexMHeap = CortexMHeap::empty();
 
 const HEAP_SIZE: usize = 50 * 1024; // in bytes
-const ETH_ADDR: EthernetAddress = EthernetAddress([0x00, 0x08, 0xdc, 0xab, 0xcd, 0xef]);
 
 #[entry]
 fn main() -> ! {
@@ -120,14 +119,13 @@ fn run() -> ! {
 
     lcd.set_background_color(Color::from_hex(0x006600));
     let layer_1 = lcd.layer_1().unwrap();
-    let mut layer_2 = lcd.layer_2().unwrap();
-
-    layer_2.clear();
+    // the color lookup table holds the ANSI colors of the console
+    let layer_2 = lcd.layer_2_clut(&lcd::console::PALETTE).unwrap();
 
     // Make `println` print to the LCD
     lcd::init_stdout(layer_2);
 
-    println!("Hello World");
+    println!("\x1b[32mHello World\x1b[0m");
 
     // example allocation
     let _xs = vec![1, 2, 3];
@@ -178,6 +176,9 @@ fn run() -> ! {
     // start the timer counter
     tim6.cr1.modify(|_, w| w.cen().set_bit());
 
+    // switch the framebuffers of double buffered layers in the vertical blanking period
+    lcd.enable_line_interrupt();
+
     interrupts::scope(
         &mut nvic,
         &mut nvic_stir,
@@ -261,6 +262,12 @@ fn run() -> ! {
                 })
                 .expect("registering exti15_10 interrupt failed");
 
+            // Interrupt handler for the LTDC line interrupt, which is triggered at the start of
+            // each vertical blanking period.
+            interrupt_table
+                .register(InterruptRequest::LTDC, Priority::P1, lcd::handle_line_interrupt)
+                .expect("registering ltdc interrupt failed");
+
             let idle_stream = task_runtime::IdleStream::new(idle_waker_sink.clone());
 
             // ethernet
@@ -270,9 +277,15 @@ fn run() -> ! {
             let i2c_3_mutex = Arc::new(FutureMutex::new(i2c_3));
             let layer_1_mutex = Arc::new(FutureMutex::new(layer_1));
 
-            let touch_task = TouchTask {
+            let (touch_event_sink, touch_event_stream) = mpsc::unbounded();
+            let touch_event_task = touch::event_task(
                 touch_int_stream,
-                i2c_3_mutex: i2c_3_mutex.clone(),
+                idle_stream.clone(),
+                i2c_3_mutex,
+                touch_event_sink,
+            );
+            let touch_task = TouchTask {
+                touch_event_stream,
                 layer_mutex: layer_1_mutex.clone(),
             };
 
@@ -281,6 +294,7 @@ fn run() -> ! {
             let mut executor = task_runtime::Executor::new();
             executor.spawn_local(button_task(button_stream)).unwrap();
             executor.spawn_local(tim6_task(tim6_stream)).unwrap();
+            executor.spawn_local(touch_event_task).unwrap();
             executor.spawn_local(touch_task.run()).unwrap();
             executor
                 .spawn_local(count_up_on_idle_task(idle_stream.clone()))
@@ -335,37 +349,38 @@ async fn tim6_task(tim6_stream: impl Stream<Item = ()>) {
 
 struct TouchTask<S, F>
 where
-    S: Stream<Item = ()>,
+    S: Stream<Item = TouchEvent>,
     F: Framebuffer,
 {
-    touch_int_stream: S,
-    i2c_3_mutex: Arc<FutureMutex<I2C<device::I2C3>>>,
+    touch_event_stream: S,
     layer_mutex: Arc<FutureMutex<Layer<F>>>,
 }
 
 impl<S, F> TouchTask<S, F>
 where
-    S: Stream<Item = ()>,
+    S: Stream<Item = TouchEvent>,
     F: Framebuffer,
 {
     async fn run(self) {
         let Self {
-            touch_int_stream,
-            i2c_3_mutex,
+            touch_event_stream,
             layer_mutex,
         } = self;
-        pin_mut!(touch_int_stream);
+        pin_mut!(touch_event_stream);
         await!(layer_mutex.with(|l| l.clear()));
         loop {
-            await!(touch_int_stream.next()).expect("touch channel closed");
-            let touches = await!(i2c_3_mutex.with(|i2c_3| touch::touches(i2c_3))).unwrap();
-            await!(layer_mutex.with(|layer| for touch in touches {
-                layer.print_point_color_at(
-                    touch.x as usize,
-                    touch.y as usize,
-                    Color::from_hex(0xffff00),
-                );
-            }))
+            let event = await!(touch_event_stream.next()).expect("touch event channel closed");
+            let (touch, color) = match event {
+                TouchEvent::Down(touch) | TouchEvent::Move(touch) => (touch, 0xffff00),
+                TouchEvent::LongPress(touch) => (touch, 0xff0000),
+                TouchEvent::Tap(touch) => (touch, 0x00ff00),
+                _ => continue,
+            };
+            await!(layer_mutex.with(|layer| layer.print_point_color_at(
+                touch.x as usize,
+                touch.y as usize,
+                Color::from_hex(color),
+            )))
         }
     }
 }
@@ -502,7 +517,7 @@ where
             &mut self.syscfg,
             &mut self.ethernet_mac,
             self.ethernet_dma,
-            ETH_ADDR,
+            ethernet::unique_ethernet_address(),
         )
         .map(|device| device.into_interface());
         let mut iface = match ethernet_interface {
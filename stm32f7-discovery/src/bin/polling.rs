@@ -30,7 +30,7 @@ use smoltcp::{
         UdpPacketMetadata, UdpSocket, UdpSocketBuffer,
     },
     time::Instant,
-    wire::{EthernetAddress, IpCidr, IpEndpoint, Ipv4Address},
+    wire::{IpCidr, IpEndpoint, Ipv4Address},
 };
 use stm32f7::stm32f7x6::{CorePeripherals, Interrupt, Peripherals};
 use stm32f7_discovery::{
@@ -49,7 +49,6 @@ use stm32f7_discovery::{
 static ALLOCATOR: CortexMHeap = CortexMHeap::empty();
 
 const HEAP_SIZE: usize = 50 * 1024; // in bytes
-const ETH_ADDR: EthernetAddress = EthernetAddress([0x00, 0x08, 0xdc, 0xab, 0xcd, 0xef]);
 
 #[entry]
 fn main() -> ! {
@@ -97,17 +96,18 @@ fn main() -> ! {
     pins.display_enable.set(true);
     pins.backlight.set(true);
 
+    // Initialize the allocator BEFORE you use it
+    unsafe { ALLOCATOR.init(rt::heap_start() as usize, HEAP_SIZE) }
+
     let mut layer_1 = lcd.layer_1().unwrap();
-    let mut layer_2 = lcd.layer_2().unwrap();
+    // the color lookup table holds the ANSI colors of the console
+    let layer_2 = lcd.layer_2_clut(&lcd::console::PALETTE).unwrap();
 
     layer_1.clear();
-    layer_2.clear();
+    // Make `println` print to the LCD, swipe up and down to scroll through older lines
     lcd::init_stdout(layer_2);
 
-    println!("Hello World");
-
-    // Initialize the allocator BEFORE you use it
-    unsafe { ALLOCATOR.init(rt::heap_start() as usize, HEAP_SIZE) }
+    println!("\x1b[32mHello World\x1b[0m");
 
     let _xs = vec![1, 2, 3];
 
@@ -144,7 +144,7 @@ fn main() -> ! {
         &mut syscfg,
         &mut ethernet_mac,
         ethernet_dma,
-        ETH_ADDR,
+        ethernet::unique_ethernet_address(),
     )
     .map(|device| {
         let iface = device.into_interface();
@@ -167,6 +167,7 @@ fn main() -> ! {
 
     let mut previous_button_state = pins.button.get();
     let mut audio_writer = AudioWriter::new();
+    let mut gestures = touch::GestureRecognizer::default();
     loop {
         // poll button state
         let current_button_state = pins.button.get();
@@ -182,13 +183,17 @@ fn main() -> ! {
         }
 
         // poll for new touch data
-        for touch in &touch::touches(&mut i2c_3).unwrap() {
+        let touches = touch::touches(&mut i2c_3).unwrap();
+        for touch in &touches {
             layer_1.print_point_color_at(
                 touch.x as usize,
                 touch.y as usize,
                 Color::from_hex(0xffff00),
             );
         }
+        if let Some(gesture) = gestures.update(&touches, system_clock::ms()) {
+            lcd::stdout::handle_gesture(&gesture);
+        }
 
         // poll for new audio data
         while sai_2.bsr.read().freq().bit_is_clear() {} // fifo_request_flag
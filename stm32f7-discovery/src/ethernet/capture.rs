@@ -0,0 +1,278 @@
+//! Records sent and received ethernet frames for protocol debugging.
+//!
+//! The capture is opt-in, see [`EthernetDevice::start_capture`](super::EthernetDevice). Frames
+//! are stored in a ring buffer together with a timestamp from
+//! [`system_clock::ms`](crate::system_clock::ms). The recorded frames can be exported in the
+//! classic pcap format, which can be opened with Wireshark or tcpdump. [`PcapServer`] serves the
+//! file over TCP, e.g. `nc 192.168.42.1 5555 > capture.pcap`.
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use byteorder::{ByteOrder, LittleEndian};
+use smoltcp::socket::{SocketHandle, SocketSet, TcpSocket, TcpSocketBuffer};
+
+/// The default TCP port of the [`PcapServer`].
+pub const PCAP_PORT: u16 = 5555;
+
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+const PCAP_VERSION_MAJOR: u16 = 2;
+const PCAP_VERSION_MINOR: u16 = 4;
+const LINKTYPE_ETHERNET: u32 = 1;
+const PCAP_HEADER_LEN: usize = 24;
+const PCAP_RECORD_HEADER_LEN: usize = 16;
+
+/// The direction of a captured frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// The frame was received.
+    Rx,
+    /// The frame was transmitted.
+    Tx,
+}
+
+/// A single captured frame.
+pub struct Frame {
+    /// The milliseconds since boot at which the frame was captured.
+    pub timestamp_ms: usize,
+    /// Whether the frame was received or transmitted.
+    pub direction: Direction,
+    /// The length of the frame on the wire.
+    pub original_len: usize,
+    /// The captured bytes, at most `snap_len` bytes of the frame.
+    pub data: Vec<u8>,
+}
+
+/// A ring buffer of captured frames.
+pub struct Capture {
+    frames: VecDeque<Frame>,
+    capacity: usize,
+    used: usize,
+    snap_len: usize,
+    dropped: usize,
+}
+
+impl Capture {
+    /// Creates a capture that stores at most `capacity` bytes of frame data.
+    ///
+    /// Only the first `snap_len` bytes of each frame are stored. When the buffer is full, the
+    /// oldest frames are discarded.
+    pub fn new(capacity: usize, snap_len: usize) -> Capture {
+        assert!(snap_len <= capacity, "snap_len must not exceed the capture capacity");
+        Capture {
+            frames: VecDeque::new(),
+            capacity,
+            used: 0,
+            snap_len,
+            dropped: 0,
+        }
+    }
+
+    /// Records a frame with the current timestamp.
+    pub fn record(&mut self, direction: Direction, frame: &[u8]) {
+        self.record_at(direction, frame, crate::system_clock::ms());
+    }
+
+    fn record_at(&mut self, direction: Direction, frame: &[u8], timestamp_ms: usize) {
+        let len = frame.len().min(self.snap_len);
+        while self.used + len > self.capacity {
+            match self.frames.pop_front() {
+                Some(oldest) => {
+                    self.used -= oldest.data.len();
+                    self.dropped += 1;
+                }
+                None => break,
+            }
+        }
+        self.used += len;
+        self.frames.push_back(Frame {
+            timestamp_ms,
+            direction,
+            original_len: frame.len(),
+            data: Vec::from(&frame[..len]),
+        });
+    }
+
+    /// Returns the recorded frames, oldest first.
+    pub fn frames(&self) -> impl Iterator<Item = &Frame> {
+        self.frames.iter()
+    }
+
+    /// Returns the number of frames that were discarded because the buffer was full.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Discards all recorded frames.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.used = 0;
+        self.dropped = 0;
+    }
+
+    /// Returns the size of the pcap file in bytes.
+    pub fn pcap_len(&self) -> usize {
+        PCAP_HEADER_LEN + self.used + self.frames.len() * PCAP_RECORD_HEADER_LEN
+    }
+
+    /// Serializes the recorded frames as pcap file.
+    pub fn to_pcap(&self) -> Vec<u8> {
+        let mut pcap = Vec::with_capacity(self.pcap_len());
+        self.write_pcap(|bytes| pcap.extend_from_slice(bytes));
+        pcap
+    }
+
+    /// Serializes the recorded frames as pcap file and passes the bytes to `write` in chunks.
+    pub fn write_pcap<F: FnMut(&[u8])>(&self, mut write: F) {
+        let mut header = [0; PCAP_HEADER_LEN];
+        LittleEndian::write_u32(&mut header[0..4], PCAP_MAGIC);
+        LittleEndian::write_u16(&mut header[4..6], PCAP_VERSION_MAJOR);
+        LittleEndian::write_u16(&mut header[6..8], PCAP_VERSION_MINOR);
+        // bytes 8..16: time zone offset and timestamp accuracy, both zero
+        LittleEndian::write_u32(&mut header[16..20], self.snap_len as u32);
+        LittleEndian::write_u32(&mut header[20..24], LINKTYPE_ETHERNET);
+        write(&header);
+
+        for frame in self.frames.iter() {
+            let mut record = [0; PCAP_RECORD_HEADER_LEN];
+            let seconds = frame.timestamp_ms / 1000;
+            let microseconds = (frame.timestamp_ms % 1000) * 1000;
+            LittleEndian::write_u32(&mut record[0..4], seconds as u32);
+            LittleEndian::write_u32(&mut record[4..8], microseconds as u32);
+            LittleEndian::write_u32(&mut record[8..12], frame.data.len() as u32);
+            LittleEndian::write_u32(&mut record[12..16], frame.original_len as u32);
+            write(&record);
+            write(&frame.data);
+        }
+    }
+}
+
+/// A TCP server that sends a snapshot of the capture to every client that connects.
+pub struct PcapServer {
+    handle: SocketHandle,
+    port: u16,
+    pending: Option<(Vec<u8>, usize)>,
+}
+
+impl PcapServer {
+    /// Adds a listening TCP socket on `port` to the socket set.
+    pub fn new(sockets: &mut SocketSet<'_, '_, 'static>, port: u16) -> PcapServer {
+        let rx_buffer = TcpSocketBuffer::new(vec![0; 64]);
+        let tx_buffer = TcpSocketBuffer::new(vec![0; super::MTU]);
+        let mut socket = TcpSocket::new(rx_buffer, tx_buffer);
+        socket.listen(port).unwrap();
+        PcapServer {
+            handle: sockets.add(socket),
+            port,
+            pending: None,
+        }
+    }
+
+    /// Sends the next part of the pcap file if a client is connected.
+    ///
+    /// Should be called after each poll of the interface. The snapshot of the capture is taken
+    /// when the client connects; the connection is closed after the whole file was sent.
+    pub fn poll(&mut self, sockets: &mut SocketSet, capture: &Capture) {
+        let mut socket = sockets.get::<TcpSocket>(self.handle);
+        if !socket.is_open() {
+            self.pending = None;
+            socket.listen(self.port).unwrap();
+            return;
+        }
+        if !socket.may_send() {
+            if self.pending.is_some() {
+                // the client went away before the download finished
+                self.pending = None;
+                socket.close();
+            }
+            return;
+        }
+
+        let (data, offset) = self.pending.get_or_insert_with(|| (capture.to_pcap(), 0));
+        if *offset < data.len() {
+            if let Ok(sent) = socket.send_slice(&data[*offset..]) {
+                *offset += sent;
+            }
+        }
+        if *offset >= data.len() {
+            socket.close();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Capture, Direction, PCAP_HEADER_LEN, PCAP_RECORD_HEADER_LEN};
+    use alloc::vec::Vec;
+    use byteorder::{ByteOrder, LittleEndian};
+
+    /// Returns the `(ts_sec, ts_usec, incl_len, orig_len)` fields of the record header at `offset`.
+    fn record_header(pcap: &[u8], offset: usize) -> (u32, u32, u32, u32) {
+        let field = |i: usize| LittleEndian::read_u32(&pcap[offset + i * 4..offset + i * 4 + 4]);
+        (field(0), field(1), field(2), field(3))
+    }
+
+    #[test]
+    fn global_header() {
+        let capture = Capture::new(100, 40);
+        let pcap = capture.to_pcap();
+        assert_eq!(pcap.len(), PCAP_HEADER_LEN);
+        assert_eq!(pcap.len(), capture.pcap_len());
+        assert_eq!(pcap[0..4], [0xd4, 0xc3, 0xb2, 0xa1]);
+        assert_eq!(LittleEndian::read_u16(&pcap[4..6]), 2);
+        assert_eq!(LittleEndian::read_u16(&pcap[6..8]), 4);
+        assert_eq!(pcap[8..16], [0; 8]);
+        assert_eq!(LittleEndian::read_u32(&pcap[16..20]), 40);
+        assert_eq!(LittleEndian::read_u32(&pcap[20..24]), 1);
+    }
+
+    #[test]
+    fn short_frames_are_stored_completely() {
+        let mut capture = Capture::new(100, 40);
+        capture.record_at(Direction::Rx, &[1; 30], 1500);
+        capture.record_at(Direction::Tx, &[2; 40], 1501);
+        let pcap = capture.to_pcap();
+        assert_eq!(pcap.len(), capture.pcap_len());
+        assert_eq!(record_header(&pcap, PCAP_HEADER_LEN), (1, 500_000, 30, 30));
+        let data = PCAP_HEADER_LEN + PCAP_RECORD_HEADER_LEN;
+        assert_eq!(pcap[data..data + 30], [1; 30][..]);
+        let second = data + 30;
+        assert_eq!(record_header(&pcap, second), (1, 501_000, 40, 40));
+        assert_eq!(pcap[second + PCAP_RECORD_HEADER_LEN..], [2; 40][..]);
+    }
+
+    #[test]
+    fn wrapped_ring() {
+        let mut capture = Capture::new(100, 40);
+        capture.record_at(Direction::Rx, &[1; 30], 1500);
+        capture.record_at(Direction::Tx, &[2; 60], 2001);
+        // the third frame does not fit anymore, so the first one is discarded
+        capture.record_at(Direction::Rx, &[3; 50], 3250);
+        assert_eq!(capture.dropped(), 1);
+        let directions: Vec<_> = capture.frames().map(|frame| frame.direction).collect();
+        assert_eq!(directions, [Direction::Tx, Direction::Rx]);
+
+        let pcap = capture.to_pcap();
+        let len = PCAP_HEADER_LEN + 2 * PCAP_RECORD_HEADER_LEN + 2 * 40;
+        assert_eq!(pcap.len(), len);
+        assert_eq!(pcap.len(), capture.pcap_len());
+        assert_eq!(LittleEndian::read_u32(&pcap[16..20]), 40);
+
+        let first = PCAP_HEADER_LEN;
+        assert_eq!(record_header(&pcap, first), (2, 1000, 40, 60));
+        let data = first + PCAP_RECORD_HEADER_LEN;
+        assert_eq!(pcap[data..data + 40], [2; 40][..]);
+
+        let second = data + 40;
+        assert_eq!(record_header(&pcap, second), (3, 250_000, 40, 50));
+        let data = second + PCAP_RECORD_HEADER_LEN;
+        assert_eq!(pcap[data..], [3; 40][..]);
+
+        let mut chunks = Vec::new();
+        capture.write_pcap(|bytes| chunks.extend_from_slice(bytes));
+        assert_eq!(chunks, pcap);
+
+        capture.clear();
+        assert_eq!(capture.to_pcap().len(), PCAP_HEADER_LEN);
+        assert_eq!(capture.dropped(), 0);
+    }
+}
@@ -9,7 +9,7 @@ pub fn init(
     syscfg: &mut SYSCFG,
     ethernet_mac: &mut ETHERNET_MAC,
     ethernet_dma: &mut ETHERNET_DMA,
-) -> Result<(), PhyError> {
+) -> Result<phy::AutoNegotiationResult, PhyError> {
     // TODO delay after writes?
 
     // enable syscfg clock
@@ -96,7 +96,7 @@ pub fn init(
         w.saif().normal(); // Source address inverse filtering
         w.daif().normal(); // Destination address inverse filtering
         w.bfd().enabled(); // broadcast frames disable
-        w.ram().disabled(); // pass all multicast
+        w.ram().enabled(); // pass all multicast (required for IPv6 neighbor discovery)
         w.hu().perfect(); // hash unicast
         w.hm().perfect(); // hash multicast
         w.pcf().prevent_all(); // pass control frames
@@ -176,7 +176,7 @@ pub fn init(
         w.maca0l().bits(0 << 24 | 0 << 16 | 0 << 8 | 2) // low register
     });
 
-    Ok(())
+    Ok(auto_neg_result)
 }
 
 pub fn start(ethernet_mac: &mut ETHERNET_MAC, ethernet_dma: &mut ETHERNET_DMA) {
@@ -1,9 +1,13 @@
 //! Provides abstractions for the ethernet device.
 
+pub use self::phy::{link_up, AutoNegotiationResult, Speed};
 pub use init::PhyError;
 
+use self::capture::{Capture, Direction};
 use alloc::boxed::Box;
 use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::ptr;
 
 use stm32f7::stm32f7x6::{ETHERNET_DMA, ETHERNET_MAC, RCC, SYSCFG};
 use volatile::Volatile;
@@ -11,8 +15,9 @@ use volatile::Volatile;
 use smoltcp::iface::{EthernetInterface, EthernetInterfaceBuilder, Routes};
 use smoltcp::phy::{Device, DeviceCapabilities};
 use smoltcp::time::Instant;
-use smoltcp::wire::{EthernetAddress, IpCidr, Ipv4Address};
+use smoltcp::wire::{EthernetAddress, IpCidr, Ipv4Address, Ipv6Address};
 
+pub mod capture;
 mod init;
 mod phy;
 mod rx;
@@ -21,6 +26,65 @@ mod tx;
 /// The maximum transmission unit.
 pub const MTU: usize = 1536;
 
+/// Address of the 96-bit unique device ID register (see section 45.1 of the reference manual).
+const DEVICE_ID_ADDRESS: usize = 0x1FF0_F420;
+
+/// Returns the 96-bit unique device ID of the microcontroller.
+pub fn device_id() -> [u8; 12] {
+    use byteorder::{ByteOrder, LittleEndian};
+
+    let mut id = [0; 12];
+    for (i, chunk) in id.chunks_mut(4).enumerate() {
+        let word_ptr = (DEVICE_ID_ADDRESS + i * 4) as *const u32;
+        LittleEndian::write_u32(chunk, unsafe { ptr::read_volatile(word_ptr) });
+    }
+    id
+}
+
+/// Derives a stable MAC address from the unique device ID of the microcontroller.
+///
+/// The device ID is hashed with 64-bit FNV-1a and the lowest six bytes of the hash are used as
+/// address. The address is marked as locally administered and unicast, so it never collides
+/// with a vendor assigned address. Since the device ID is fixed at the factory, each board
+/// always gets the same address.
+pub fn unique_ethernet_address() -> EthernetAddress {
+    ethernet_address_from_id(&device_id())
+}
+
+/// Derives the IPv6 link-local address (`fe80::/64`) of the passed ethernet address.
+///
+/// The interface identifier is the modified EUI-64 of the ethernet address as described in
+/// RFC 4291, appendix A.
+pub fn ipv6_link_local_address(ethernet_address: EthernetAddress) -> Ipv6Address {
+    let mac = ethernet_address.as_bytes();
+    let mut bytes = [0; 16];
+    bytes[0] = 0xfe;
+    bytes[1] = 0x80;
+    bytes[8..11].copy_from_slice(&mac[..3]);
+    bytes[8] ^= 0x02; // invert the universal/local bit
+    bytes[11] = 0xff;
+    bytes[12] = 0xfe;
+    bytes[13..].copy_from_slice(&mac[3..]);
+    Ipv6Address(bytes)
+}
+
+fn ethernet_address_from_id(id: &[u8]) -> EthernetAddress {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let hash = id.iter().fold(FNV_OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    });
+
+    let mut bytes = [0; 6];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = (hash >> (8 * i)) as u8;
+    }
+    bytes[0] &= !0x01; // unicast
+    bytes[0] |= 0x02; // locally administered
+    EthernetAddress(bytes)
+}
+
 /// Represents an ethernet device that allows sending and receiving packets.
 ///
 /// This struct implements the [smoltcp::phy::Device] trait.
@@ -30,6 +94,8 @@ pub struct EthernetDevice {
     ethernet_dma: ETHERNET_DMA,
     ethernet_address: EthernetAddress,
     addr: Ipv4Address,
+    link: AutoNegotiationResult,
+    capture: RefCell<Option<Capture>>,
 }
 
 impl EthernetDevice {
@@ -55,7 +121,7 @@ impl EthernetDevice {
     ) -> Result<Self, PhyError> {
         use byteorder::{ByteOrder, LittleEndian};
 
-        init::init(rcc, syscfg, ethernet_mac, &mut ethernet_dma)?;
+        let link = init::init(rcc, syscfg, ethernet_mac, &mut ethernet_dma)?;
 
         let rx_device = RxDevice::new(rx_config)?;
         let tx_device = TxDevice::new(tx_config);
@@ -84,15 +150,56 @@ impl EthernetDevice {
             ethernet_dma: ethernet_dma,
             ethernet_address: ethernet_address,
             addr,
+            link,
+            capture: RefCell::new(None),
         })
     }
 
+    /// Starts recording all received and transmitted frames, see the [capture] module.
+    ///
+    /// Replaces a previously started capture.
+    pub fn start_capture(&mut self, capture: Capture) {
+        *self.capture.get_mut() = Some(capture);
+    }
+
+    /// Stops recording frames and returns the capture.
+    pub fn stop_capture(&mut self) -> Option<Capture> {
+        self.capture.get_mut().take()
+    }
+
+    /// Returns the running capture, if any.
+    pub fn capture(&mut self) -> Option<&mut Capture> {
+        self.capture.get_mut().as_mut()
+    }
+
+    /// Returns the link speed and duplex mode that were negotiated during initialization.
+    pub fn link(&self) -> AutoNegotiationResult {
+        self.link
+    }
+
+    /// Returns the number of sent and received frames and the receive errors reported by the
+    /// DMA descriptors since the device was created.
+    pub fn statistics(&self) -> Statistics {
+        Statistics {
+            rx_frames: self.rx.frames,
+            tx_frames: self.tx.frames,
+            rx_errors: self.rx.errors,
+        }
+    }
+
     /// Transforms the ethernet device into a smoltcp ethernet network interface.
+    ///
+    /// Besides the IPv4 address passed to [`new`](EthernetDevice::new), the interface gets the
+    /// IPv6 link-local address derived from the ethernet address, see
+    /// [`ipv6_link_local_address`]. Neighbor discovery for IPv6 is handled by smoltcp.
     pub fn into_interface<'a>(self) -> EthernetInterface<'a, 'a, 'a, Self> {
         use alloc::collections::BTreeMap;
         use smoltcp::iface::NeighborCache;
 
-        let ip_addrs = [IpCidr::new(self.addr.into(), 0)];
+        let ip_addrs = [
+            IpCidr::new(self.addr.into(), 0),
+            IpCidr::new(ipv6_link_local_address(self.ethernet_address).into(), 64),
+        ];
         let routes_storage = Box::leak(Box::new([None; 1]));
         let routes = Routes::new(&mut routes_storage[..]);
 
@@ -122,10 +229,14 @@ impl<'a> Device<'a> for EthernetDevice {
         if !self.rx.new_data_received() {
             return None;
         }
-        let rx = RxToken { rx: &mut self.rx };
+        let rx = RxToken {
+            rx: &mut self.rx,
+            capture: &self.capture,
+        };
         let tx = TxToken {
             tx: &mut self.tx,
             ethernet_dma: &mut self.ethernet_dma,
+            capture: &self.capture,
         };
         Some((rx, tx))
     }
@@ -137,6 +248,7 @@ impl<'a> Device<'a> for EthernetDevice {
         Some(TxToken {
             tx: &mut self.tx,
             ethernet_dma: &mut self.ethernet_dma,
+            capture: &self.capture,
         })
     }
 
@@ -152,6 +264,7 @@ impl<'a> Device<'a> for EthernetDevice {
 /// Used in the [Device] trait.
 pub struct RxToken<'a> {
     rx: &'a mut RxDevice,
+    capture: &'a RefCell<Option<Capture>>,
 }
 
 impl<'a> ::smoltcp::phy::RxToken for RxToken<'a> {
@@ -159,10 +272,18 @@ impl<'a> ::smoltcp::phy::RxToken for RxToken<'a> {
     where
         F: FnOnce(&[u8]) -> ::smoltcp::Result<R>,
     {
-        self.rx.receive(f).map_err(|err| match err {
-            ReceiveError::Processing(e) => e,
-            _ => ::smoltcp::Error::Truncated,
-        })
+        let capture = self.capture;
+        self.rx
+            .receive(|data| {
+                if let Some(ref mut capture) = *capture.borrow_mut() {
+                    capture.record(Direction::Rx, data);
+                }
+                f(data)
+            })
+            .map_err(|err| match err {
+                ReceiveError::Processing(e) => e,
+                _ => ::smoltcp::Error::Truncated,
+            })
     }
 }
 
@@ -172,6 +293,7 @@ impl<'a> ::smoltcp::phy::RxToken for RxToken<'a> {
 pub struct TxToken<'a> {
     tx: &'a mut TxDevice,
     ethernet_dma: &'a mut ETHERNET_DMA,
+    capture: &'a RefCell<Option<Capture>>,
 }
 
 impl<'a> ::smoltcp::phy::TxToken for TxToken<'a> {
@@ -181,6 +303,9 @@ impl<'a> ::smoltcp::phy::TxToken for TxToken<'a> {
     {
         let mut data = vec![0; len].into_boxed_slice();
         let ret = f(&mut data)?;
+        if let Some(ref mut capture) = *self.capture.borrow_mut() {
+            capture.record(Direction::Tx, &data);
+        }
         self.tx.insert(data);
         self.start_send();
         Ok(ret)
@@ -208,6 +333,62 @@ impl<'a> TxToken<'a> {
     }
 }
 
+/// Frame counters of an [EthernetDevice].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Statistics {
+    /// The number of frames that were received without errors.
+    pub rx_frames: usize,
+    /// The number of frames that were handed to the DMA for transmission.
+    pub tx_frames: usize,
+    /// The number of received frames that were dropped because of a descriptor error.
+    pub rx_errors: RxErrorCounters,
+}
+
+/// Counts the error flags of the receive descriptors, one counter per [ReceiveError] kind.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RxErrorCounters {
+    /// See [ReceiveError::Crc].
+    pub crc: usize,
+    /// See [ReceiveError::Receive].
+    pub receive: usize,
+    /// See [ReceiveError::WatchdogTimeout].
+    pub watchdog_timeout: usize,
+    /// See [ReceiveError::LateCollision].
+    pub late_collision: usize,
+    /// See [ReceiveError::GiantFrame].
+    pub giant_frame: usize,
+    /// See [ReceiveError::Overflow].
+    pub overflow: usize,
+    /// See [ReceiveError::Descriptor].
+    pub descriptor: usize,
+}
+
+impl RxErrorCounters {
+    /// Returns the sum of all counters.
+    pub fn total(&self) -> usize {
+        self.crc
+            + self.receive
+            + self.watchdog_timeout
+            + self.late_collision
+            + self.giant_frame
+            + self.overflow
+            + self.descriptor
+    }
+
+    fn count(&mut self, error: &ReceiveError) {
+        match error {
+            ReceiveError::Crc => self.crc += 1,
+            ReceiveError::Receive => self.receive += 1,
+            ReceiveError::WatchdogTimeout => self.watchdog_timeout += 1,
+            ReceiveError::LateCollision => self.late_collision += 1,
+            ReceiveError::GiantFrame => self.giant_frame += 1,
+            ReceiveError::Overflow => self.overflow += 1,
+            ReceiveError::Descriptor => self.descriptor += 1,
+            ReceiveError::Processing(_) => {}
+        }
+    }
+}
+
 /// An error that occurred while receiving a packet.
 #[derive(Debug, PartialEq, Eq)]
 pub enum ReceiveError {
@@ -236,6 +417,8 @@ struct RxDevice {
     buffer: Box<[u8]>,
     descriptors: Box<[Volatile<rx::RxDescriptor>]>,
     next_descriptor: usize,
+    frames: usize,
+    errors: RxErrorCounters,
 }
 
 impl RxDevice {
@@ -263,6 +446,8 @@ impl RxDevice {
             buffer: buffer,
             descriptors: descriptors.into_boxed_slice(),
             next_descriptor: 0,
+            frames: 0,
+            errors: RxErrorCounters::default(),
         })
     }
 
@@ -331,8 +516,12 @@ impl RxDevice {
         }
 
         let ret = match error {
-            Some(error) => Err(error),
+            Some(error) => {
+                self.errors.count(&error);
+                Err(error)
+            }
             None => {
+                self.frames += 1;
                 // read data and pass it to processing function
                 let offset = self.config.descriptor_buffer_offset(descriptor_index);
                 let len = last_descriptor.frame_len();
@@ -360,6 +549,7 @@ impl RxDevice {
 struct TxDevice {
     descriptors: Box<[Volatile<tx::TxDescriptor>]>,
     next_descriptor: usize,
+    frames: usize,
 }
 
 impl TxDevice {
@@ -380,6 +570,7 @@ impl TxDevice {
         TxDevice {
             descriptors: descriptors.into_boxed_slice(),
             next_descriptor: 0,
+            frames: 0,
         }
     }
 
@@ -391,6 +582,7 @@ impl TxDevice {
         while self.descriptors[self.next_descriptor].read().own() {}
         self.descriptors[self.next_descriptor].update(|d| d.set_data(data));
         self.next_descriptor = (self.next_descriptor + 1) % self.descriptors.len();
+        self.frames += 1;
 
         self.cleanup();
     }
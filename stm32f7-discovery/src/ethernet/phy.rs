@@ -23,17 +23,31 @@ pub enum Error {
     AutoNegotiationTimeout,
 }
 
+/// The link parameters that the PHY agreed on with its link partner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct AutoNegotiationResult {
+    /// Whether the link operates in full-duplex (`true`) or half-duplex (`false`) mode.
     pub duplex: bool,
+    /// The link speed.
     pub speed: Speed,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// The speed of an ethernet link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Speed {
+    /// 10 Mbit/s
     Speed10M,
+    /// 100 Mbit/s
     Speed100M,
 }
 
+/// Returns whether the PHY currently reports an established link.
+pub fn link_up(ethernet_mac: &mut ETHERNET_MAC) -> bool {
+    // the link status bit is latched low, so the first read only clears a past link failure
+    phy_read(ethernet_mac, LAN8742A_PHY_ADDRESS, BASIC_STATUS_REG);
+    phy_read(ethernet_mac, LAN8742A_PHY_ADDRESS, BASIC_STATUS_REG).get_bit(2)
+}
+
 pub fn init(ethernet_mac: &mut ETHERNET_MAC) -> Result<AutoNegotiationResult, Error> {
     // reset PHY
     phy_write(
@@ -48,16 +62,16 @@ pub fn init(ethernet_mac: &mut ETHERNET_MAC) -> Result<AutoNegotiationResult, Er
     while phy_read(ethernet_mac, LAN8742A_PHY_ADDRESS, BASIC_CONTROL_REG) & PHY_RESET != 0 {}
 
     // wait for link bit
-    hprint!("wait for ethernet link");
+    log_info!("wait for ethernet link");
     let timeout_ticks = system_clock::ms_to_ticks(TIMEOUT_MS);
     let ticks = system_clock::ticks();
     while !phy_read(ethernet_mac, LAN8742A_PHY_ADDRESS, BASIC_STATUS_REG).get_bit(2) {
         if system_clock::ticks() - ticks > timeout_ticks {
-            hprintln!(" [TIMEOUT]");
+            log_error!("wait for ethernet link [TIMEOUT]");
             return Err(Error::LinkTimeout); // timeout
         }
     }
-    hprintln!(" [OK]");
+    log_info!("wait for ethernet link [OK]");
 
     // enable auto-negotiation
     phy_write(
@@ -68,15 +82,15 @@ pub fn init(ethernet_mac: &mut ETHERNET_MAC) -> Result<AutoNegotiationResult, Er
     );
 
     // wait until auto-negotiation complete bit is set
-    hprint!("wait for auto negotiation of ethernet speed");
+    log_info!("wait for auto negotiation of ethernet speed");
     let ticks = system_clock::ticks();
     while !phy_read(ethernet_mac, LAN8742A_PHY_ADDRESS, BASIC_STATUS_REG).get_bit(5) {
         if system_clock::ticks() - ticks > timeout_ticks {
-            hprintln!(" [TIMEOUT]");
+            log_error!("wait for auto negotiation of ethernet speed [TIMEOUT]");
             return Err(Error::AutoNegotiationTimeout); // timeout
         }
     }
-    hprintln!(" [OK]");
+    log_info!("wait for auto negotiation of ethernet speed [OK]");
 
     let ssr = phy_read(ethernet_mac, LAN8742A_PHY_ADDRESS, SPECIAL_STATUS_REG);
     // auto-negotiation done bit should be set
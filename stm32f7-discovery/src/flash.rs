@@ -0,0 +1,132 @@
+//! Erasing and programming the internal flash memory.
+//!
+//! The flash of the STM32F746NG is divided into eight sectors. The program is placed at the
+//! start of the flash, so only the last sector is used for storing data. It is excluded from
+//! the `FLASH` region in `memory.x`.
+
+use core::ptr;
+use stm32f7::stm32f7x6::FLASH;
+
+/// The number of the last flash sector.
+pub const DATA_SECTOR: u8 = 7;
+/// Start address of the last flash sector.
+pub const DATA_SECTOR_START: usize = 0x080C_0000;
+/// Length of the last flash sector in bytes.
+pub const DATA_SECTOR_LENGTH: usize = 256 * 1024;
+
+const KEY_1: u32 = 0x4567_0123;
+const KEY_2: u32 = 0xCDEF_89AB;
+
+/// Errors that can happen while erasing or programming the flash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The passed address range lies outside of [`DATA_SECTOR`].
+    OutOfRange,
+    /// The addressed memory is write protected.
+    WriteProtection,
+    /// The data was not aligned to the programming size.
+    ProgrammingAlignment,
+    /// The programming size does not match the previous write operations.
+    ProgrammingParallelism,
+    /// The erase sequence was not correct.
+    EraseSequence,
+    /// The flash reported a failed operation.
+    Operation,
+}
+
+/// Provides erase and program operations on the data sector.
+///
+/// The flash is unlocked on creation and locked again when this struct is dropped.
+pub struct Flash<'a> {
+    flash: &'a mut FLASH,
+}
+
+impl<'a> Flash<'a> {
+    /// Unlocks the flash control register.
+    pub fn unlock(flash: &'a mut FLASH) -> Flash<'a> {
+        if flash.cr.read().lock().bit_is_set() {
+            flash.keyr.write(|w| unsafe { w.key().bits(KEY_1) });
+            flash.keyr.write(|w| unsafe { w.key().bits(KEY_2) });
+        }
+        Flash { flash }
+    }
+
+    /// Erases the whole data sector, which sets all bytes to `0xff`.
+    pub fn erase_data_sector(&mut self) -> Result<(), Error> {
+        self.wait_ready()?;
+        self.flash.cr.modify(|_, w| unsafe {
+            w.psize().bits(0b00); // byte parallelism, valid for all supply voltages
+            w.snb().bits(DATA_SECTOR);
+            w.ser().set_bit()
+        });
+        self.flash.cr.modify(|_, w| w.strt().set_bit());
+        let result = self.wait_ready();
+        self.flash.cr.modify(|_, w| w.ser().clear_bit());
+        result
+    }
+
+    /// Programs `data` to the passed address, which must lie in the erased data sector.
+    pub fn program(&mut self, address: usize, data: &[u8]) -> Result<(), Error> {
+        if address < DATA_SECTOR_START
+            || address + data.len() > DATA_SECTOR_START + DATA_SECTOR_LENGTH
+        {
+            return Err(Error::OutOfRange);
+        }
+        self.wait_ready()?;
+        self.flash.cr.modify(|_, w| unsafe {
+            w.psize().bits(0b00);
+            w.pg().set_bit()
+        });
+        let mut result = Ok(());
+        for (i, &byte) in data.iter().enumerate() {
+            unsafe { ptr::write_volatile((address + i) as *mut u8, byte) };
+            result = self.wait_ready();
+            if result.is_err() {
+                break;
+            }
+        }
+        self.flash.cr.modify(|_, w| w.pg().clear_bit());
+        result
+    }
+
+    /// Waits until the current operation is finished and returns its errors.
+    fn wait_ready(&mut self) -> Result<(), Error> {
+        while self.flash.sr.read().bsy().bit_is_set() {}
+        let sr = self.flash.sr.read();
+        let result = if sr.wrperr().bit_is_set() {
+            Err(Error::WriteProtection)
+        } else if sr.pgaerr().bit_is_set() {
+            Err(Error::ProgrammingAlignment)
+        } else if sr.pgperr().bit_is_set() {
+            Err(Error::ProgrammingParallelism)
+        } else if sr.erserr().bit_is_set() {
+            Err(Error::EraseSequence)
+        } else if sr.operr().bit_is_set() {
+            Err(Error::Operation)
+        } else {
+            Ok(())
+        };
+        // the flags are cleared by writing a one
+        self.flash.sr.write(|w| {
+            w.eop().set_bit();
+            w.operr().set_bit();
+            w.wrperr().set_bit();
+            w.pgaerr().set_bit();
+            w.pgperr().set_bit();
+            w.erserr().set_bit()
+        });
+        result
+    }
+}
+
+impl<'a> Drop for Flash<'a> {
+    fn drop(&mut self) {
+        self.flash.cr.modify(|_, w| w.lock().set_bit());
+    }
+}
+
+/// Returns the bytes of the data sector starting at `offset`.
+pub fn data_sector(offset: usize, len: usize) -> &'static [u8] {
+    assert!(offset + len <= DATA_SECTOR_LENGTH);
+    unsafe { core::slice::from_raw_parts((DATA_SECTOR_START + offset) as *const u8, len) }
+}
@@ -0,0 +1,447 @@
+//! Decoder for images in the Windows bitmap (BMP) format.
+//!
+//! Supported are uncompressed images with 1, 4 or 8 bits per pixel and a palette, and images
+//! with 16, 24 or 32 bits per pixel, optionally with bit field masks and an alpha channel.
+//! Rows can be stored bottom-up (the default) or top-down.
+
+use super::{Color, Framebuffer, Layer, Point, Rect};
+use byteorder::{ByteOrder, LittleEndian};
+
+const FILE_HEADER_SIZE: usize = 14;
+const INFO_HEADER_SIZE: usize = 40;
+/// Images with a larger side are rejected, which also prevents overflows in size computations.
+const MAX_SIDE: usize = 1 << 15;
+
+const COMPRESSION_RGB: u32 = 0;
+const COMPRESSION_BITFIELDS: u32 = 3;
+const COMPRESSION_ALPHABITFIELDS: u32 = 6;
+
+/// Errors that can happen while parsing a BMP file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The data ends before the end of the headers, the palette, or the pixel data.
+    Truncated,
+    /// The file does not start with `BM`.
+    InvalidSignature,
+    /// The info header has the passed size, which belongs to an unsupported version.
+    UnsupportedHeader(u32),
+    /// The image uses the passed number of bits per pixel, which is not supported.
+    UnsupportedBitDepth(u16),
+    /// The image uses the passed compression method, which is not supported.
+    UnsupportedCompression(u32),
+    /// The width or height is zero or too large.
+    InvalidDimensions,
+    /// The number of palette entries does not fit the bit depth.
+    InvalidPalette,
+}
+
+/// A bit field of a pixel value, described by its shift and width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Channel {
+    shift: u32,
+    bits: u32,
+}
+
+impl Channel {
+    fn from_mask(mask: u32) -> Channel {
+        if mask == 0 {
+            return Channel { shift: 0, bits: 0 };
+        }
+        let shift = mask.trailing_zeros();
+        Channel {
+            shift,
+            bits: (!(mask >> shift)).trailing_zeros(),
+        }
+    }
+
+    /// Extracts the channel from the pixel value and scales it to 8 bits.
+    fn extract(self, value: u32) -> Option<u8> {
+        if self.bits == 0 {
+            return None;
+        }
+        let max = (1u64 << self.bits) - 1;
+        let raw = u64::from(value >> self.shift) & max;
+        Some((raw * 255 / max) as u8)
+    }
+}
+
+/// The red, green, blue and alpha masks of 16 and 32 bit images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Masks {
+    red: Channel,
+    green: Channel,
+    blue: Channel,
+    alpha: Channel,
+}
+
+impl Masks {
+    fn new(red: u32, green: u32, blue: u32, alpha: u32) -> Masks {
+        Masks {
+            red: Channel::from_mask(red),
+            green: Channel::from_mask(green),
+            blue: Channel::from_mask(blue),
+            alpha: Channel::from_mask(alpha),
+        }
+    }
+
+    fn color(&self, value: u32) -> Color {
+        Color {
+            red: self.red.extract(value).unwrap_or(0),
+            green: self.green.extract(value).unwrap_or(0),
+            blue: self.blue.extract(value).unwrap_or(0),
+            alpha: self.alpha.extract(value).unwrap_or(255),
+        }
+    }
+}
+
+/// A parsed BMP image, which borrows the file data.
+#[derive(Debug, Clone, Copy)]
+pub struct Bmp<'a> {
+    pixels: &'a [u8],
+    palette: &'a [u8],
+    width: usize,
+    height: usize,
+    bits_per_pixel: u16,
+    stride: usize,
+    top_down: bool,
+    masks: Option<Masks>,
+}
+
+impl<'a> Bmp<'a> {
+    /// Parses the headers of the BMP file and checks that the pixel data is complete.
+    pub fn parse(data: &'a [u8]) -> Result<Bmp<'a>, Error> {
+        if data.len() < FILE_HEADER_SIZE + INFO_HEADER_SIZE {
+            return Err(Error::Truncated);
+        }
+        if &data[0..2] != b"BM" {
+            return Err(Error::InvalidSignature);
+        }
+        let pixel_offset = LittleEndian::read_u32(&data[10..14]) as usize;
+        let header_size = LittleEndian::read_u32(&data[14..18]);
+        if header_size < INFO_HEADER_SIZE as u32 {
+            return Err(Error::UnsupportedHeader(header_size));
+        }
+        let header_end = FILE_HEADER_SIZE
+            .checked_add(header_size as usize)
+            .ok_or(Error::Truncated)?;
+        if data.len() < header_end {
+            return Err(Error::Truncated);
+        }
+
+        let width = LittleEndian::read_i32(&data[18..22]);
+        let height = LittleEndian::read_i32(&data[22..26]);
+        let bits_per_pixel = LittleEndian::read_u16(&data[28..30]);
+        let compression = LittleEndian::read_u32(&data[30..34]);
+        let colors_used = LittleEndian::read_u32(&data[46..50]) as usize;
+
+        // a negative height marks an image that is stored top-down
+        let top_down = height < 0;
+        let (width, height) = (width as i64, (height as i64).abs());
+        if width <= 0 || height == 0 || width as usize > MAX_SIDE || height as usize > MAX_SIDE {
+            return Err(Error::InvalidDimensions);
+        }
+        let (width, height) = (width as usize, height as usize);
+
+        match bits_per_pixel {
+            1 | 4 | 8 | 16 | 24 | 32 => {}
+            _ => return Err(Error::UnsupportedBitDepth(bits_per_pixel)),
+        }
+
+        // the masks directly follow the 40 byte info header, either as part of a newer header
+        // version or as separate fields
+        let mut palette_start = header_end;
+        let masks = match compression {
+            COMPRESSION_RGB => match bits_per_pixel {
+                16 => Some(Masks::new(0x7c00, 0x03e0, 0x001f, 0)),
+                // the fourth byte of 32 bit pixels is unused unless an alpha mask is given
+                32 => Some(Masks::new(0x00ff_0000, 0x0000_ff00, 0x0000_00ff, 0)),
+                _ => None,
+            },
+            COMPRESSION_BITFIELDS | COMPRESSION_ALPHABITFIELDS
+                if bits_per_pixel == 16 || bits_per_pixel == 32 =>
+            {
+                let with_alpha = compression == COMPRESSION_ALPHABITFIELDS || header_size >= 56;
+                let mask_count = if with_alpha { 4 } else { 3 };
+                let masks_start = FILE_HEADER_SIZE + INFO_HEADER_SIZE;
+                let masks_end = masks_start + 4 * mask_count;
+                if data.len() < masks_end {
+                    return Err(Error::Truncated);
+                }
+                palette_start = palette_start.max(masks_end);
+                let mask = |i: usize| {
+                    LittleEndian::read_u32(&data[masks_start + 4 * i..masks_start + 4 * i + 4])
+                };
+                let alpha = if with_alpha { mask(3) } else { 0 };
+                Some(Masks::new(mask(0), mask(1), mask(2), alpha))
+            }
+            _ => return Err(Error::UnsupportedCompression(compression)),
+        };
+
+        let palette = if bits_per_pixel <= 8 {
+            let max_colors = 1 << bits_per_pixel;
+            let colors = if colors_used == 0 {
+                max_colors
+            } else {
+                colors_used
+            };
+            if colors > max_colors {
+                return Err(Error::InvalidPalette);
+            }
+            let palette_end = palette_start + 4 * colors;
+            if data.len() < palette_end {
+                return Err(Error::Truncated);
+            }
+            &data[palette_start..palette_end]
+        } else {
+            &data[0..0]
+        };
+
+        let stride = (width * bits_per_pixel as usize + 31) / 32 * 4;
+        // the largest accepted image needs 2^32 bytes, which overflows on the 32 bit target
+        let pixels_end = stride
+            .checked_mul(height)
+            .and_then(|n| pixel_offset.checked_add(n))
+            .ok_or(Error::Truncated)?;
+        if pixel_offset < header_end || data.len() < pixels_end {
+            return Err(Error::Truncated);
+        }
+
+        Ok(Bmp {
+            pixels: &data[pixel_offset..pixels_end],
+            palette,
+            width,
+            height,
+            bits_per_pixel,
+            stride,
+            top_down,
+            masks,
+        })
+    }
+
+    /// Returns the width of the image in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the image in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the color of the pixel at the specified coordinates, where (0, 0) is the top
+    /// left corner.
+    ///
+    /// Pixels that refer to a missing palette entry are transparent.
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        assert!(x < self.width && y < self.height);
+        let row = if self.top_down {
+            y
+        } else {
+            self.height - 1 - y
+        };
+        let row = &self.pixels[row * self.stride..(row + 1) * self.stride];
+        match self.bits_per_pixel {
+            1 | 4 | 8 => {
+                let bits = self.bits_per_pixel as usize;
+                let bit_offset = x * bits;
+                let byte = row[bit_offset / 8];
+                // the leftmost pixel is stored in the most significant bits
+                let shift = 8 - bits - bit_offset % 8;
+                let index = (byte >> shift) as usize & ((1 << bits) - 1);
+                self.palette_color(index)
+            }
+            16 => {
+                let value = LittleEndian::read_u16(&row[x * 2..x * 2 + 2]);
+                self.mask_color(u32::from(value))
+            }
+            24 => Color::rgb(row[x * 3 + 2], row[x * 3 + 1], row[x * 3]),
+            _ => {
+                let value = LittleEndian::read_u32(&row[x * 4..x * 4 + 4]);
+                self.mask_color(value)
+            }
+        }
+    }
+
+    fn palette_color(&self, index: usize) -> Color {
+        match self.palette.get(index * 4..index * 4 + 4) {
+            Some(entry) => Color::rgb(entry[2], entry[1], entry[0]),
+            None => Color::from_argb8888(0),
+        }
+    }
+
+    fn mask_color(&self, value: u32) -> Color {
+        match self.masks {
+            Some(ref masks) => masks.color(value),
+            None => Color::from_argb8888(0),
+        }
+    }
+}
+
+impl<T: Framebuffer> Layer<T> {
+    /// Draws the image with its top left corner at `position`, clipped to the display.
+    ///
+    /// Like [`blit`](Layer::blit), the alpha channel of the image is copied and not blended.
+    pub fn draw_bmp(&mut self, position: Point, bmp: &Bmp) {
+        let target = Rect::new(position.x, position.y, bmp.width(), bmp.height());
+        let visible = match target.intersection(&Rect::screen()) {
+            Some(visible) => visible,
+            None => return,
+        };
+        self.dirty.add(visible);
+        for y in visible.y..visible.bottom() {
+            for x in visible.x..visible.right() {
+                let color = bmp.pixel(x - position.x, y - position.y);
+                self.framebuffer.set_pixel(x, y, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bmp, Error};
+    use crate::lcd::{Color, Layer, MemoryFramebuffer, Point, HEIGHT, WIDTH};
+    use alloc::vec::Vec;
+    use byteorder::{ByteOrder, LittleEndian};
+
+    /// Builds a BMP file with a 40 byte info header.
+    fn bmp(
+        width: i32,
+        height: i32,
+        bits_per_pixel: u16,
+        compression: u32,
+        extra: &[u8],
+        pixels: &[u8],
+    ) -> Vec<u8> {
+        let pixel_offset = 54 + extra.len();
+        let mut data = vec![0; pixel_offset];
+        data[0..2].copy_from_slice(b"BM");
+        LittleEndian::write_u32(&mut data[2..6], (pixel_offset + pixels.len()) as u32);
+        LittleEndian::write_u32(&mut data[10..14], pixel_offset as u32);
+        LittleEndian::write_u32(&mut data[14..18], 40);
+        LittleEndian::write_i32(&mut data[18..22], width);
+        LittleEndian::write_i32(&mut data[22..26], height);
+        LittleEndian::write_u16(&mut data[26..28], 1);
+        LittleEndian::write_u16(&mut data[28..30], bits_per_pixel);
+        LittleEndian::write_u32(&mut data[30..34], compression);
+        data[54..].copy_from_slice(extra);
+        data.extend_from_slice(pixels);
+        data
+    }
+
+    const RED: Color = Color {
+        red: 255,
+        green: 0,
+        blue: 0,
+        alpha: 255,
+    };
+    const BLUE: Color = Color {
+        red: 0,
+        green: 0,
+        blue: 255,
+        alpha: 255,
+    };
+
+    #[test]
+    fn bottom_up_24_bit() {
+        // two rows of two pixels, each padded to four bytes, the bottom row comes first
+        let pixels = [
+            0, 0, 255, 0, 0, 0, 0, 0, // bottom: red, black
+            255, 0, 0, 255, 255, 255, 0, 0, // top: blue, white
+        ];
+        let data = bmp(2, 2, 24, 0, &[], &pixels);
+        let image = Bmp::parse(&data).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 2));
+        assert_eq!(image.pixel(0, 0), BLUE);
+        assert_eq!(image.pixel(1, 0), Color::rgb(255, 255, 255));
+        assert_eq!(image.pixel(0, 1), RED);
+        assert_eq!(image.pixel(1, 1), Color::rgb(0, 0, 0));
+    }
+
+    #[test]
+    fn top_down_palettes() {
+        let palette = [255, 0, 0, 0, 0, 0, 255, 0];
+        // 1 bit: pixels 0, 1, 1 in the most significant bits
+        let data = bmp(3, -1, 1, 0, &palette, &[0b0110_0000, 0, 0, 0]);
+        let image = Bmp::parse(&data).unwrap();
+        assert_eq!(image.pixel(0, 0), BLUE);
+        assert_eq!(image.pixel(2, 0), RED);
+
+        // 4 bit with only two palette entries, so index 2 is transparent
+        let mut extra = [0; 8];
+        extra.copy_from_slice(&palette);
+        let mut data = bmp(3, -2, 4, 0, &extra, &[0x10, 0x20, 0, 0, 0x01, 0x00, 0, 0]);
+        LittleEndian::write_u32(&mut data[46..50], 2);
+        let image = Bmp::parse(&data).unwrap();
+        assert_eq!(image.pixel(0, 0), RED);
+        assert_eq!(image.pixel(1, 0), BLUE);
+        assert_eq!(image.pixel(2, 0), Color::from_argb8888(0));
+        assert_eq!(image.pixel(1, 1), RED);
+
+        // 8 bit
+        let mut data = bmp(1, -1, 8, 0, &palette, &[1, 0, 0, 0]);
+        LittleEndian::write_u32(&mut data[46..50], 2);
+        assert_eq!(Bmp::parse(&data).unwrap().pixel(0, 0), RED);
+    }
+
+    #[test]
+    fn masked_16_and_32_bit() {
+        // 16 bit defaults to 5-5-5
+        let data = bmp(1, 1, 16, 0, &[], &[0x00, 0x7c, 0, 0]);
+        assert_eq!(Bmp::parse(&data).unwrap().pixel(0, 0), RED);
+
+        // 32 bit with an alpha mask
+        let mut masks = [0; 16];
+        LittleEndian::write_u32(&mut masks[0..4], 0x00ff_0000);
+        LittleEndian::write_u32(&mut masks[4..8], 0x0000_ff00);
+        LittleEndian::write_u32(&mut masks[8..12], 0x0000_00ff);
+        LittleEndian::write_u32(&mut masks[12..16], 0xff00_0000);
+        let data = bmp(1, 1, 32, 6, &masks, &[0xff, 0, 0, 0x80]);
+        assert_eq!(
+            Bmp::parse(&data).unwrap().pixel(0, 0),
+            Color::rgba(0, 0, 255, 0x80)
+        );
+    }
+
+    #[test]
+    fn invalid_files_are_rejected() {
+        let data = bmp(2, 2, 24, 0, &[], &[0; 16]);
+        assert!(Bmp::parse(&data).is_ok());
+        assert_eq!(Bmp::parse(&data[..60]).unwrap_err(), Error::Truncated);
+        assert_eq!(Bmp::parse(&data[..20]).unwrap_err(), Error::Truncated);
+
+        let mut wrong = data.clone();
+        wrong[0] = b'X';
+        assert_eq!(Bmp::parse(&wrong).unwrap_err(), Error::InvalidSignature);
+
+        let data = bmp(2, 2, 8, 1, &[], &[0; 8]);
+        assert_eq!(
+            Bmp::parse(&data).unwrap_err(),
+            Error::UnsupportedCompression(1)
+        );
+        let data = bmp(2, 2, 2, 0, &[], &[0; 8]);
+        assert_eq!(Bmp::parse(&data).unwrap_err(), Error::UnsupportedBitDepth(2));
+        let data = bmp(0, 2, 24, 0, &[], &[]);
+        assert_eq!(Bmp::parse(&data).unwrap_err(), Error::InvalidDimensions);
+    }
+
+    #[test]
+    fn oversized_pixel_data_is_rejected() {
+        // the pixel data of the largest accepted size overflows a 32 bit usize
+        let data = bmp(32768, 32768, 32, 0, &[], &[0; 16]);
+        assert_eq!(Bmp::parse(&data).unwrap_err(), Error::Truncated);
+        let data = bmp(32768, -32768, 32, 0, &[], &[0; 16]);
+        assert_eq!(Bmp::parse(&data).unwrap_err(), Error::Truncated);
+    }
+
+    #[test]
+    fn draw_bmp_is_clipped() {
+        let pixels = [0, 0, 255, 0, 0, 255, 0, 0];
+        let data = bmp(2, 1, 24, 0, &[], &pixels);
+        let image = Bmp::parse(&data).unwrap();
+        let mut layer = Layer::new(MemoryFramebuffer::new());
+        layer.draw_bmp(Point::new(WIDTH - 1, HEIGHT - 1), &image);
+        assert_eq!(layer.framebuffer().pixel(WIDTH - 1, HEIGHT - 1), RED);
+        assert_eq!(layer.dirty_regions().iter().count(), 1);
+    }
+}
@@ -1,5 +1,5 @@
 /// Represents a color with alpha, red, green, and blue channels.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Color {
     /// The red channel.
     pub red: u8,
@@ -79,13 +79,94 @@ impl Color {
     /// Creates a color from the passed ARGB1555 value.
     pub fn from_argb1555(color: u16) -> Color {
         Color {
-            alpha: ((color >> 8) & 0x80) as u8,
-            red: ((color >> 7) & 0xf8) as u8,
-            green: ((color >> 2) & 0xf8) as u8,
-            blue: ((color << 3) & 0xf8) as u8,
+            alpha: if color & 0x8000 == 0 { 0 } else { 255 },
+            red: expand_5_bits(color >> 10),
+            green: expand_5_bits(color >> 5),
+            blue: expand_5_bits(color),
         }
     }
 
+    /// Converts the color to RGB565, dropping the alpha channel.
+    pub fn to_rgb565(&self) -> u16 {
+        (u16::from(self.red) & 0xf8) << 8
+            | (u16::from(self.green) & 0xfc) << 3
+            | (u16::from(self.blue) & 0xf8) >> 3
+    }
+
+    /// Creates an opaque color from the passed RGB565 value.
+    pub fn from_rgb565(color: u16) -> Color {
+        let green = (color >> 5) & 0x3f;
+        Color {
+            red: expand_5_bits(color >> 11),
+            green: (green << 2 | green >> 4) as u8,
+            blue: expand_5_bits(color),
+            alpha: 255,
+        }
+    }
+
+    /// Converts the color to ARGB4444.
+    pub fn to_argb4444(&self) -> u16 {
+        (u16::from(self.alpha) & 0xf0) << 8
+            | (u16::from(self.red) & 0xf0) << 4
+            | (u16::from(self.green) & 0xf0)
+            | u16::from(self.blue) >> 4
+    }
+
+    /// Creates a color from the passed ARGB4444 value.
+    pub fn from_argb4444(color: u16) -> Color {
+        // repeat the four bits, so that 0xf becomes 0xff
+        let expand = |nibble: u16| ((nibble & 0xf) * 0x11) as u8;
+        Color {
+            alpha: expand(color >> 12),
+            red: expand(color >> 8),
+            green: expand(color >> 4),
+            blue: expand(color),
+        }
+    }
+
+    /// Returns the luminance of the color, as used by the L8, AL44 and AL88 formats.
+    pub fn luminance(&self) -> u8 {
+        ((u32::from(self.red) * 77 + u32::from(self.green) * 150 + u32::from(self.blue) * 29)
+            >> 8) as u8
+    }
+
+    /// Returns this color drawn over the `background` color with straight alpha.
+    pub fn blend_over(&self, background: Color) -> Color {
+        let alpha = u32::from(self.alpha);
+        let background_alpha = u32::from(background.alpha) * (255 - alpha) / 255;
+        let out_alpha = alpha + background_alpha;
+        if out_alpha == 0 {
+            return Color::rgba(0, 0, 0, 0);
+        }
+        let channel = |front: u8, back: u8| {
+            ((u32::from(front) * alpha + u32::from(back) * background_alpha) / out_alpha) as u8
+        };
+        Color {
+            red: channel(self.red, background.red),
+            green: channel(self.green, background.green),
+            blue: channel(self.blue, background.blue),
+            alpha: out_alpha as u8,
+        }
+    }
+
+    /// Returns the index of the palette color that is closest to this color, ignoring alpha.
+    ///
+    /// Returns 0 for an empty palette.
+    pub fn nearest_index(&self, palette: &[Color]) -> usize {
+        let distance = |other: &Color| {
+            let channel = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+            channel(self.red, other.red)
+                + channel(self.green, other.green)
+                + channel(self.blue, other.blue)
+        };
+        palette
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, color)| distance(color))
+            .map(|(index, _)| index)
+            .unwrap_or(0)
+    }
+
     /// Creates a color from the passed HSV value.
     pub fn from_hsv(hue: i32, saturation: f32, value: f32) -> Color {
         let mut h = hue % 360;
@@ -130,3 +211,9 @@ impl Color {
         )
     }
 }
+
+/// Scales the lowest 5 bits of `value` to 8 bits, so that 0x1f becomes 0xff.
+fn expand_5_bits(value: u16) -> u8 {
+    let value = value & 0x1f;
+    (value << 3 | value >> 2) as u8
+}
@@ -0,0 +1,718 @@
+//! Configuration of the LTDC layers.
+//!
+//! [`Lcd::configure_layer`](super::Lcd::configure_layer) sets up a layer with a [`LayerConfig`]
+//! and returns a [`LayerHandle`], which dereferences to a [`Layer`] for drawing. The handle also
+//! changes the window position, constant alpha, blending factors and visibility of the layer at
+//! runtime. There is at most one handle per layer.
+//!
+//! ```
+//! let config = LayerConfig::new(PixelFormat::Rgb565)
+//!     .with_window(Rect::new(40, 36, 400, 200))
+//!     .with_constant_alpha(192);
+//! let mut layer_1 = lcd.configure_layer(LtdcLayer::Layer1, config).unwrap();
+//! layer_1.clear();
+//! layer_1.move_window(Point::new(0, 0)).unwrap();
+//! ```
+
+use super::screenshot::LayerState;
+use super::{
+    Color, Framebuffer, Layer, LtdcLayer, Point, Rect, LAYER_1_LENGTH, LAYER_1_START,
+    LAYER_2_LENGTH, LAYER_2_START,
+};
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+use stm32f7::stm32f7x6::LTDC;
+
+/// The horizontal offset of the active display area, the sum of sync width and back porch.
+const H_OFFSET: u16 = 41 + 13;
+/// The vertical offset of the active display area, the sum of sync height and back porch.
+const V_OFFSET: u16 = 10 + 2;
+
+/// The registers of one layer, see `with_layer_registers`.
+struct LayerRegisters<'a, CR, WHPCR, WVPCR, PFCR, CACR, DCCR, BFCR, CFBAR, CFBLR, CFBLNR> {
+    cr: &'a CR,
+    whpcr: &'a WHPCR,
+    wvpcr: &'a WVPCR,
+    pfcr: &'a PFCR,
+    cacr: &'a CACR,
+    dccr: &'a DCCR,
+    bfcr: &'a BFCR,
+    cfbar: &'a CFBAR,
+    cfblr: &'a CFBLR,
+    cfblnr: &'a CFBLNR,
+}
+
+/// Binds the registers of the passed layer to `$regs` and evaluates `$body`.
+///
+/// The registers of the two layers have different types, so the body is expanded once per
+/// layer.
+macro_rules! with_layer_registers {
+    ($ltdc:expr, $layer:expr, |$regs:ident| $body:block) => {
+        match $layer {
+            LtdcLayer::Layer1 => {
+                let $regs = LayerRegisters {
+                    cr: &$ltdc.l1cr,
+                    whpcr: &$ltdc.l1whpcr,
+                    wvpcr: &$ltdc.l1wvpcr,
+                    pfcr: &$ltdc.l1pfcr,
+                    cacr: &$ltdc.l1cacr,
+                    dccr: &$ltdc.l1dccr,
+                    bfcr: &$ltdc.l1bfcr,
+                    cfbar: &$ltdc.l1cfbar,
+                    cfblr: &$ltdc.l1cfblr,
+                    cfblnr: &$ltdc.l1cfblnr,
+                };
+                $body
+            }
+            LtdcLayer::Layer2 => {
+                let $regs = LayerRegisters {
+                    cr: &$ltdc.l2cr,
+                    whpcr: &$ltdc.l2whpcr,
+                    wvpcr: &$ltdc.l2wvpcr,
+                    pfcr: &$ltdc.l2pfcr,
+                    cacr: &$ltdc.l2cacr,
+                    dccr: &$ltdc.l2dccr,
+                    bfcr: &$ltdc.l2bfcr,
+                    cfbar: &$ltdc.l2cfbar,
+                    cfblr: &$ltdc.l2cfblr,
+                    cfblnr: &$ltdc.l2cfblnr,
+                };
+                $body
+            }
+        }
+    };
+}
+
+/// The pixel formats supported by the LTDC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 8 bits for alpha, red, green and blue.
+    Argb8888,
+    /// 8 bits for red, green and blue, without alpha.
+    Rgb888,
+    /// 5 bits for red and blue and 6 bits for green, without alpha.
+    Rgb565,
+    /// 1 bit for alpha and 5 bits for red, green and blue.
+    Argb1555,
+    /// 4 bits for alpha, red, green and blue.
+    Argb4444,
+    /// 8 bits of luminance or a color lookup table index, without alpha.
+    L8,
+    /// 4 bits for alpha and 4 bits of luminance or a color lookup table index.
+    Al44,
+    /// 8 bits for alpha and 8 bits of luminance or a color lookup table index.
+    Al88,
+}
+
+impl PixelFormat {
+    /// Returns the number of bytes per pixel.
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Argb8888 => 4,
+            PixelFormat::Rgb888 => 3,
+            PixelFormat::Rgb565
+            | PixelFormat::Argb1555
+            | PixelFormat::Argb4444
+            | PixelFormat::Al88 => 2,
+            PixelFormat::L8 | PixelFormat::Al44 => 1,
+        }
+    }
+
+    /// Returns whether the format stores luminance values, which can be looked up in a palette.
+    pub fn has_luminance(self) -> bool {
+        match self {
+            PixelFormat::L8 | PixelFormat::Al44 | PixelFormat::Al88 => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the value of the pixel format register.
+    fn register_value(self) -> u8 {
+        match self {
+            PixelFormat::Argb8888 => 0b000,
+            PixelFormat::Rgb888 => 0b001,
+            PixelFormat::Rgb565 => 0b010,
+            PixelFormat::Argb1555 => 0b011,
+            PixelFormat::Argb4444 => 0b100,
+            PixelFormat::L8 => 0b101,
+            PixelFormat::Al44 => 0b110,
+            PixelFormat::Al88 => 0b111,
+        }
+    }
+
+    /// Returns the format for a value of the pixel format register.
+    fn from_register_value(value: u8) -> PixelFormat {
+        match value & 0b111 {
+            0b000 => PixelFormat::Argb8888,
+            0b001 => PixelFormat::Rgb888,
+            0b010 => PixelFormat::Rgb565,
+            0b011 => PixelFormat::Argb1555,
+            0b100 => PixelFormat::Argb4444,
+            0b101 => PixelFormat::L8,
+            0b110 => PixelFormat::Al44,
+            _ => PixelFormat::Al88,
+        }
+    }
+
+    /// Converts the color to a pixel value.
+    ///
+    /// For the luminance formats, the color is mapped to the nearest palette index if there is a
+    /// palette and to its luminance otherwise.
+    pub fn encode(self, color: Color, palette: Option<&[Color]>) -> u32 {
+        let luminance = |bits: u32| match palette {
+            // the palette index must fit into the bits of the format
+            Some(palette) => {
+                let entries = palette.len().min(1 << bits);
+                color.nearest_index(&palette[..entries]) as u32
+            }
+            None => u32::from(color.luminance()) >> (8 - bits),
+        };
+        match self {
+            PixelFormat::Argb8888 => color.to_argb8888(),
+            PixelFormat::Rgb888 => color.to_rgb888(),
+            PixelFormat::Rgb565 => u32::from(color.to_rgb565()),
+            PixelFormat::Argb1555 => u32::from(color.to_argb1555()),
+            PixelFormat::Argb4444 => u32::from(color.to_argb4444()),
+            PixelFormat::L8 => luminance(8),
+            PixelFormat::Al44 => u32::from(color.alpha & 0xf0) | luminance(4),
+            PixelFormat::Al88 => u32::from(color.alpha) << 8 | luminance(8),
+        }
+    }
+
+    /// Converts a pixel value back to a color, the inverse of [`encode`](PixelFormat::encode).
+    pub fn decode(self, value: u32, palette: Option<&[Color]>) -> Color {
+        let lookup = |luminance: u8, alpha: u8| {
+            let color = match palette {
+                Some(palette) => palette
+                    .get(usize::from(luminance))
+                    .cloned()
+                    .unwrap_or(Color::rgb(0, 0, 0)),
+                None => Color::rgb(luminance, luminance, luminance),
+            };
+            Color { alpha, ..color }
+        };
+        match self {
+            PixelFormat::Argb8888 => Color::from_argb8888(value),
+            PixelFormat::Rgb888 => Color::from_rgb888(value),
+            PixelFormat::Rgb565 => Color::from_rgb565(value as u16),
+            PixelFormat::Argb1555 => Color::from_argb1555(value as u16),
+            PixelFormat::Argb4444 => Color::from_argb4444(value as u16),
+            PixelFormat::L8 => lookup(value as u8, 255),
+            PixelFormat::Al44 => {
+                // with a palette, the 4 bits are an index and must not be scaled
+                let luminance = match palette {
+                    Some(_) => (value & 0xf) as u8,
+                    None => (value & 0xf) as u8 * 0x11,
+                };
+                lookup(luminance, (value & 0xf0) as u8 | (value >> 4 & 0xf) as u8)
+            }
+            PixelFormat::Al88 => lookup(value as u8, (value >> 8) as u8),
+        }
+    }
+}
+
+/// The factors that the LTDC uses to blend a layer with the layers below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendingFactor {
+    /// Only the constant alpha of the layer is used.
+    ConstantAlpha,
+    /// The alpha value of every pixel is multiplied with the constant alpha.
+    PixelAlphaTimesConstantAlpha,
+}
+
+/// Errors of the layer configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerError {
+    /// There already is a handle for the layer.
+    InUse,
+    /// The window is empty or not completely inside of the display.
+    InvalidWindow,
+    /// The framebuffer for the window does not fit into the memory of the layer.
+    FramebufferTooLarge,
+}
+
+/// The configuration of a layer.
+#[derive(Debug, Clone, Copy)]
+pub struct LayerConfig {
+    format: PixelFormat,
+    window: Rect,
+    constant_alpha: u8,
+    blending: (BlendingFactor, BlendingFactor),
+    palette: Option<&'static [Color]>,
+    enabled: bool,
+}
+
+impl LayerConfig {
+    /// Creates an enabled full screen configuration, which blends with the pixel alpha.
+    pub fn new(format: PixelFormat) -> LayerConfig {
+        LayerConfig {
+            format,
+            window: Rect::screen(),
+            constant_alpha: 255,
+            blending: (
+                BlendingFactor::PixelAlphaTimesConstantAlpha,
+                BlendingFactor::PixelAlphaTimesConstantAlpha,
+            ),
+            palette: None,
+            enabled: true,
+        }
+    }
+
+    /// Sets the area of the display that the layer covers.
+    pub fn with_window(mut self, window: Rect) -> LayerConfig {
+        self.window = window;
+        self
+    }
+
+    /// Sets the alpha value that is multiplied with all pixels.
+    pub fn with_constant_alpha(mut self, alpha: u8) -> LayerConfig {
+        self.constant_alpha = alpha;
+        self
+    }
+
+    /// Sets the blending factors for the layer and for the layers below it.
+    ///
+    /// The factor of the layers below is subtracted from one by the LTDC.
+    pub fn with_blending(mut self, factor_1: BlendingFactor, factor_2: BlendingFactor) -> Self {
+        self.blending = (factor_1, factor_2);
+        self
+    }
+
+    /// Loads the palette into the color lookup table of the layer.
+    ///
+    /// Only used by the luminance formats, see [`PixelFormat::has_luminance`].
+    pub fn with_palette(mut self, palette: &'static [Color]) -> LayerConfig {
+        self.palette = Some(palette);
+        self
+    }
+
+    /// Creates the layer hidden, it is shown with [`LayerHandle::set_enabled`].
+    pub fn disabled(mut self) -> LayerConfig {
+        self.enabled = false;
+        self
+    }
+
+    /// Returns the pixel format.
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Returns the window of the layer.
+    pub fn window(&self) -> Rect {
+        self.window
+    }
+
+    /// Returns the constant alpha value.
+    pub fn constant_alpha(&self) -> u8 {
+        self.constant_alpha
+    }
+
+    /// Returns whether the layer is shown.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn palette(&self) -> Option<&'static [Color]> {
+        if self.format.has_luminance() {
+            self.palette
+        } else {
+            None
+        }
+    }
+}
+
+/// A framebuffer in any [`PixelFormat`] with the size of the window of its layer.
+///
+/// Coordinates are relative to the top left corner of the window. Pixels outside of the window
+/// are ignored.
+pub struct ConfiguredFramebuffer {
+    base_addr: usize,
+    format: PixelFormat,
+    width: usize,
+    height: usize,
+    palette: Option<&'static [Color]>,
+}
+
+impl ConfiguredFramebuffer {
+    /// Returns the pixel format.
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    fn pixel_addr(&self, x: usize, y: usize) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(self.base_addr + (y * self.width + x) * self.format.bytes_per_pixel())
+        } else {
+            None
+        }
+    }
+
+    fn write(&mut self, addr: usize, value: u32) {
+        unsafe {
+            match self.format.bytes_per_pixel() {
+                4 => ptr::write_volatile(addr as *mut u32, value),
+                3 => {
+                    // RGB888 pixels are not aligned, the blue byte comes first
+                    ptr::write_volatile(addr as *mut u8, value as u8);
+                    ptr::write_volatile((addr + 1) as *mut u8, (value >> 8) as u8);
+                    ptr::write_volatile((addr + 2) as *mut u8, (value >> 16) as u8);
+                }
+                2 => ptr::write_volatile(addr as *mut u16, value as u16),
+                _ => ptr::write_volatile(addr as *mut u8, value as u8),
+            }
+        }
+    }
+
+    fn read(&self, addr: usize) -> u32 {
+        unsafe { read_value(addr, self.format) }
+    }
+}
+
+/// Reads the value of a pixel in the passed format from memory.
+///
+/// The caller ensures that `addr` points to a pixel of a framebuffer.
+pub(super) unsafe fn read_value(addr: usize, format: PixelFormat) -> u32 {
+    match format.bytes_per_pixel() {
+        4 => ptr::read_volatile(addr as *const u32),
+        3 => {
+            u32::from(ptr::read_volatile(addr as *const u8))
+                | u32::from(ptr::read_volatile((addr + 1) as *const u8)) << 8
+                | u32::from(ptr::read_volatile((addr + 2) as *const u8)) << 16
+        }
+        2 => u32::from(ptr::read_volatile(addr as *const u16)),
+        _ => u32::from(ptr::read_volatile(addr as *const u8)),
+    }
+}
+
+impl Framebuffer for ConfiguredFramebuffer {
+    fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+        if let Some(addr) = self.pixel_addr(x, y) {
+            let value = self.format.encode(color, self.palette);
+            self.write(addr, value);
+        }
+    }
+
+    fn read_pixel(&self, x: usize, y: usize) -> Option<Color> {
+        let addr = self.pixel_addr(x, y)?;
+        Some(self.format.decode(self.read(addr), self.palette))
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        let size = Rect::new(0, 0, self.width, self.height);
+        let rect = match rect.intersection(&size) {
+            Some(rect) => rect,
+            None => return,
+        };
+        let value = self.format.encode(color, self.palette);
+        for y in rect.y..rect.bottom() {
+            for x in rect.x..rect.right() {
+                let addr = self.base_addr + (y * self.width + x) * self.format.bytes_per_pixel();
+                self.write(addr, value);
+            }
+        }
+    }
+}
+
+/// The exclusive handle of a configured layer.
+///
+/// It dereferences to the [`Layer`] of the window, so all drawing methods of layers are
+/// available. Changes to the configuration take effect at the next vertical blanking.
+pub struct LayerHandle {
+    ltdc_layer: LtdcLayer,
+    config: LayerConfig,
+    layer: Layer<ConfiguredFramebuffer>,
+}
+
+impl LayerHandle {
+    /// Returns the LTDC layer that this handle controls.
+    pub fn ltdc_layer(&self) -> LtdcLayer {
+        self.ltdc_layer
+    }
+
+    /// Returns the current configuration.
+    pub fn config(&self) -> &LayerConfig {
+        &self.config
+    }
+
+    /// Shows or hides the layer.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.config.enabled = enabled;
+        with_layer_registers!(ltdc(), self.ltdc_layer, |regs| {
+            regs.cr.modify(|_, w| w.len().bit(enabled));
+        });
+        reload();
+    }
+
+    /// Sets the alpha value that is multiplied with all pixels.
+    pub fn set_constant_alpha(&mut self, alpha: u8) {
+        self.config.constant_alpha = alpha;
+        with_layer_registers!(ltdc(), self.ltdc_layer, |regs| {
+            regs.cacr.modify(|_, w| unsafe { w.consta().bits(alpha) });
+        });
+        reload();
+    }
+
+    /// Sets the blending factors, see [`LayerConfig::with_blending`].
+    pub fn set_blending(&mut self, factor_1: BlendingFactor, factor_2: BlendingFactor) {
+        self.config.blending = (factor_1, factor_2);
+        let (bf1, bf2) = blending_register_values(self.config.blending);
+        with_layer_registers!(ltdc(), self.ltdc_layer, |regs| {
+            regs.bfcr.modify(|_, w| unsafe {
+                w.bf1().bits(bf1);
+                w.bf2().bits(bf2)
+            });
+        });
+        reload();
+    }
+
+    /// Moves the top left corner of the window, the size of the window is kept.
+    pub fn move_window(&mut self, position: Point) -> Result<(), LayerError> {
+        let window = self.config.window;
+        let moved = Rect::new(position.x, position.y, window.width, window.height);
+        if !Rect::screen().contains_rect(&moved) {
+            return Err(LayerError::InvalidWindow);
+        }
+        self.config.window = moved;
+        write_window(self.ltdc_layer, moved);
+        reload();
+        Ok(())
+    }
+}
+
+impl Deref for LayerHandle {
+    type Target = Layer<ConfiguredFramebuffer>;
+
+    fn deref(&self) -> &Layer<ConfiguredFramebuffer> {
+        &self.layer
+    }
+}
+
+impl DerefMut for LayerHandle {
+    fn deref_mut(&mut self) -> &mut Layer<ConfiguredFramebuffer> {
+        &mut self.layer
+    }
+}
+
+/// Programs the registers of the layer and creates the handle.
+///
+/// The caller ensures that there is no other handle for the layer.
+pub(super) fn configure(
+    ltdc: &LTDC,
+    ltdc_layer: LtdcLayer,
+    config: LayerConfig,
+) -> Result<LayerHandle, LayerError> {
+    let window = config.window;
+    if window.is_empty() || !Rect::screen().contains_rect(&window) {
+        return Err(LayerError::InvalidWindow);
+    }
+    let (base_addr, length) = match ltdc_layer {
+        LtdcLayer::Layer1 => (LAYER_1_START, LAYER_1_LENGTH),
+        LtdcLayer::Layer2 => (LAYER_2_START, LAYER_2_LENGTH),
+    };
+    let line_length = window.width * config.format.bytes_per_pixel();
+    if line_length * window.height > length {
+        return Err(LayerError::FramebufferTooLarge);
+    }
+
+    let (bf1, bf2) = blending_register_values(config.blending);
+    write_window(ltdc_layer, window);
+    with_layer_registers!(ltdc, ltdc_layer, |regs| {
+        regs.pfcr
+            .modify(|_, w| unsafe { w.pf().bits(config.format.register_value()) });
+        regs.cacr
+            .modify(|_, w| unsafe { w.consta().bits(config.constant_alpha) });
+        regs.bfcr.modify(|_, w| unsafe {
+            w.bf1().bits(bf1);
+            w.bf2().bits(bf2)
+        });
+        regs.cfbar
+            .modify(|_, w| unsafe { w.cfbadd().bits(base_addr as u32) });
+        regs.cfblr.modify(|_, w| unsafe {
+            w.cfbp().bits(line_length as u16); // pitch
+            w.cfbll().bits(line_length as u16 + 3) // line_length
+        });
+        regs.cfblnr
+            .modify(|_, w| unsafe { w.cfblnbr().bits(window.height as u16) });
+    });
+
+    let palette = config.palette();
+    match palette {
+        // writing the lookup table disables the layer, it is enabled again below
+        Some(palette) => super::write_clut(ltdc, ltdc_layer, palette),
+        None => with_layer_registers!(ltdc, ltdc_layer, |regs| {
+            regs.cr.modify(|_, w| w.cluten().clear_bit());
+        }),
+    }
+    with_layer_registers!(ltdc, ltdc_layer, |regs| {
+        regs.cr.modify(|_, w| w.len().bit(config.enabled));
+    });
+    ltdc.srcr.write(|w| w.imr().set_bit());
+
+    let framebuffer = ConfiguredFramebuffer {
+        base_addr,
+        format: config.format,
+        width: window.width,
+        height: window.height,
+        palette,
+    };
+    Ok(LayerHandle {
+        ltdc_layer,
+        config,
+        layer: Layer::new(framebuffer),
+    })
+}
+
+fn blending_register_values(blending: (BlendingFactor, BlendingFactor)) -> (u8, u8) {
+    let bf1 = match blending.0 {
+        BlendingFactor::ConstantAlpha => 0b100,
+        BlendingFactor::PixelAlphaTimesConstantAlpha => 0b110,
+    };
+    let bf2 = match blending.1 {
+        BlendingFactor::ConstantAlpha => 0b101,
+        BlendingFactor::PixelAlphaTimesConstantAlpha => 0b111,
+    };
+    (bf1, bf2)
+}
+
+/// Reads the registers of the layer, e.g. for a screenshot.
+///
+/// `palette` is used as the color lookup table if the table is enabled, because the LTDC
+/// can't read it back.
+pub(super) fn read_state(
+    ltdc: &LTDC,
+    ltdc_layer: LtdcLayer,
+    palette: Option<Vec<Color>>,
+) -> LayerState {
+    with_layer_registers!(ltdc, ltdc_layer, |regs| {
+        let cr = regs.cr.read();
+        let (whpcr, wvpcr) = (regs.whpcr.read(), regs.wvpcr.read());
+        let (left, right) = (whpcr.whstpos().bits(), whpcr.whsppos().bits());
+        let (top, bottom) = (wvpcr.wvstpos().bits(), wvpcr.wvsppos().bits());
+        let window = Rect::new(
+            usize::from(left.saturating_sub(H_OFFSET)),
+            usize::from(top.saturating_sub(V_OFFSET)),
+            usize::from((right + 1).saturating_sub(left)),
+            usize::from((bottom + 1).saturating_sub(top)),
+        );
+        let bfcr = regs.bfcr.read();
+        let factor = |value: u8| {
+            // the second factor uses odd values for the same meaning
+            if value & !1 == 0b100 {
+                BlendingFactor::ConstantAlpha
+            } else {
+                BlendingFactor::PixelAlphaTimesConstantAlpha
+            }
+        };
+        let dccr = regs.dccr.read();
+        let format = PixelFormat::from_register_value(regs.pfcr.read().pf().bits());
+        LayerState {
+            enabled: cr.len().bit_is_set(),
+            format,
+            base_addr: regs.cfbar.read().cfbadd().bits() as usize,
+            pitch: usize::from(regs.cfblr.read().cfbp().bits()),
+            window,
+            constant_alpha: regs.cacr.read().consta().bits(),
+            blending: (factor(bfcr.bf1().bits()), factor(bfcr.bf2().bits())),
+            default_color: Color::rgba(
+                dccr.dcred().bits(),
+                dccr.dcgreen().bits(),
+                dccr.dcblue().bits(),
+                dccr.dcalpha().bits(),
+            ),
+            palette: palette.filter(|_| cr.cluten().bit_is_set() && format.has_luminance()),
+        }
+    })
+}
+
+fn write_window(ltdc_layer: LtdcLayer, window: Rect) {
+    let (left, top) = (window.x as u16 + H_OFFSET, window.y as u16 + V_OFFSET);
+    let (right, bottom) = (
+        left + window.width as u16 - 1,
+        top + window.height as u16 - 1,
+    );
+    with_layer_registers!(ltdc(), ltdc_layer, |regs| {
+        regs.whpcr.modify(|_, w| unsafe {
+            w.whstpos().bits(left); // window_horizontal_start_position
+            w.whsppos().bits(right) // window_horizontal_stop_position
+        });
+        regs.wvpcr.modify(|_, w| unsafe {
+            w.wvstpos().bits(top); // window_vertical_start_position
+            w.wvsppos().bits(bottom) // window_vertical_stop_position
+        });
+    });
+}
+
+/// Returns the LTDC registers.
+///
+/// Handles only modify the registers of their own layer and request a reload, the owner of the
+/// LTDC does not touch these registers after handing out the handle.
+fn ltdc() -> &'static LTDC {
+    unsafe { &*LTDC::ptr() }
+}
+
+/// Applies the changed registers at the next vertical blanking.
+fn reload() {
+    ltdc().srcr.modify(|_, w| w.vbr().set_bit());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PixelFormat;
+    use crate::lcd::Color;
+
+    const FORMATS: [PixelFormat; 8] = [
+        PixelFormat::Argb8888,
+        PixelFormat::Rgb888,
+        PixelFormat::Rgb565,
+        PixelFormat::Argb1555,
+        PixelFormat::Argb4444,
+        PixelFormat::L8,
+        PixelFormat::Al44,
+        PixelFormat::Al88,
+    ];
+
+    #[test]
+    fn encoded_colors_round_trip() {
+        let colors = [
+            Color::rgb(255, 255, 255),
+            Color::rgba(0, 0, 0, 0),
+            Color::rgb(0, 0, 0),
+        ];
+        for &format in FORMATS.iter() {
+            for &color in colors.iter() {
+                let has_alpha = format != PixelFormat::Rgb888
+                    && format != PixelFormat::Rgb565
+                    && format != PixelFormat::L8;
+                let expected = if has_alpha {
+                    color
+                } else {
+                    Color {
+                        alpha: 255,
+                        ..color
+                    }
+                };
+                let decoded = format.decode(format.encode(color, None), None);
+                assert_eq!(decoded, expected, "{:?}", format);
+            }
+        }
+    }
+
+    #[test]
+    fn luminance_formats_use_the_palette() {
+        let palette = [
+            Color::rgb(0, 0, 0),
+            Color::rgb(200, 0, 0),
+            Color::rgb(0, 0, 200),
+        ];
+        let color = Color::rgba(180, 20, 10, 128);
+        let value = PixelFormat::Al88.encode(color, Some(&palette));
+        assert_eq!(value, 128 << 8 | 1);
+        assert_eq!(
+            PixelFormat::Al88.decode(value, Some(&palette)),
+            Color::rgba(200, 0, 0, 128)
+        );
+        let value = PixelFormat::Al44.encode(color, Some(&palette));
+        assert_eq!(value, 0x80 | 1);
+    }
+}
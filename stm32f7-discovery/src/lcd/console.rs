@@ -0,0 +1,577 @@
+//! A text console with a cursor, ANSI colors and a scrollback buffer.
+//!
+//! The [`Console`] keeps the text of the last [`SCROLLBACK_LINES`] lines and draws the visible
+//! part with the 8x8 font. When the cursor leaves the last row, the text moves up by one row
+//! instead of starting again at the top of the layer. Older lines can be brought back with
+//! [`Console::scroll_view`].
+//!
+//! The console understands the following escape sequences:
+//!
+//! - `ESC[…m` sets the colors: `0` resets them, `1` selects the bright variant of the text
+//!   color, `30`–`37` and `90`–`97` set the text color, `40`–`47` and `100`–`107` the
+//!   background, and `39` and `49` restore the default colors.
+//! - `ESC[row;columnH` moves the cursor, the rows and columns are counted from 1. `ESC[nA`,
+//!   `ESC[nB`, `ESC[nC` and `ESC[nD` move it by `n` cells up, down, right and left.
+//! - `ESC[J` clears the screen after the cursor and `ESC[2J` the whole screen. `ESC[K` clears
+//!   the rest of the line and `ESC[2K` the whole line.
+//!
+//! Other sequences are ignored.
+
+use super::{font, Color, Framebuffer, Layer, Rect, HEIGHT, WIDTH};
+use core::fmt;
+
+/// The number of characters in a row of the console.
+pub const COLUMNS: usize = WIDTH / 8;
+/// The number of rows that are visible at the same time.
+pub const ROWS: usize = HEIGHT / 8;
+/// The number of lines that are kept, including the visible rows.
+pub const SCROLLBACK_LINES: usize = 100;
+
+/// The 16 ANSI colors: black, red, green, yellow, blue, magenta, cyan and white, followed by
+/// their bright variants.
+///
+/// Load this palette into the color lookup table of a CLUT layer (see
+/// [`Lcd::layer_2_clut`](super::Lcd::layer_2_clut)) to show the colors exactly.
+pub const PALETTE: [Color; 16] = [
+    rgb(0x00, 0x00, 0x00),
+    rgb(0xaa, 0x00, 0x00),
+    rgb(0x00, 0xaa, 0x00),
+    rgb(0xaa, 0x55, 0x00),
+    rgb(0x00, 0x00, 0xaa),
+    rgb(0xaa, 0x00, 0xaa),
+    rgb(0x00, 0xaa, 0xaa),
+    rgb(0xaa, 0xaa, 0xaa),
+    rgb(0x55, 0x55, 0x55),
+    rgb(0xff, 0x55, 0x55),
+    rgb(0x55, 0xff, 0x55),
+    rgb(0xff, 0xff, 0x55),
+    rgb(0x55, 0x55, 0xff),
+    rgb(0xff, 0x55, 0xff),
+    rgb(0x55, 0xff, 0xff),
+    rgb(0xff, 0xff, 0xff),
+];
+
+/// The text color after a reset, bright white like the [`TextWriter`](super::TextWriter).
+const DEFAULT_FOREGROUND: u8 = 15;
+/// The background value of cells without a background color, which stay transparent.
+const TRANSPARENT: u8 = 0xff;
+/// The character that is stored for characters outside of Latin-1, drawn as
+/// [`REPLACEMENT_GLYPH`](font::REPLACEMENT_GLYPH).
+const REPLACEMENT: u8 = 0x1a;
+/// The number of parameters of an escape sequence that are kept, the rest is ignored.
+const MAX_PARAMETERS: usize = 4;
+
+const fn rgb(red: u8, green: u8, blue: u8) -> Color {
+    Color {
+        red,
+        green,
+        blue,
+        alpha: 255,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cell {
+    character: u8,
+    foreground: u8,
+    background: u8,
+}
+
+impl Cell {
+    const BLANK: Cell = Cell {
+        character: b' ',
+        foreground: DEFAULT_FOREGROUND,
+        background: TRANSPARENT,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    None,
+    /// An escape character was written.
+    Started,
+    /// The parameters of a control sequence (`ESC[`) are read.
+    Control {
+        parameters: [u16; MAX_PARAMETERS],
+        count: usize,
+    },
+}
+
+/// A text console on a layer.
+///
+/// The console implements [`fmt::Write`], so it can be used with the `write!` macro. The cursor
+/// is shown as a line below the cell where the next character is written.
+pub struct Console<T: Framebuffer> {
+    layer: Layer<T>,
+    /// A ring buffer with the lines of the console, the visible rows are the last [`ROWS`]
+    /// lines.
+    lines: [[Cell; COLUMNS]; SCROLLBACK_LINES],
+    /// The index of the oldest line in `lines`.
+    first_line: usize,
+    /// The number of lines in use, at least [`ROWS`].
+    line_count: usize,
+    /// The number of lines that the view is scrolled back.
+    view_offset: usize,
+    row: usize,
+    /// The column of the next character. It is [`COLUMNS`] after the last column was written,
+    /// the line is then wrapped before the next character.
+    column: usize,
+    foreground: u8,
+    background: u8,
+    bold: bool,
+    escape: Escape,
+    /// The cell where the cursor was drawn.
+    drawn_cursor: Option<(usize, usize)>,
+}
+
+impl<T: Framebuffer> Console<T> {
+    /// Creates an empty console on the passed layer and clears the layer.
+    pub fn new(layer: Layer<T>) -> Console<T> {
+        let mut console = Console {
+            layer,
+            lines: [[Cell::BLANK; COLUMNS]; SCROLLBACK_LINES],
+            first_line: 0,
+            line_count: ROWS,
+            view_offset: 0,
+            row: 0,
+            column: 0,
+            foreground: DEFAULT_FOREGROUND,
+            background: TRANSPARENT,
+            bold: false,
+            escape: Escape::None,
+            drawn_cursor: None,
+        };
+        console.redraw();
+        console.draw_cursor();
+        console
+    }
+
+    /// Returns the layer that the console draws to.
+    pub fn layer(&self) -> &Layer<T> {
+        &self.layer
+    }
+
+    /// Returns the number of lines that the view is scrolled back from the newest lines.
+    pub fn view_offset(&self) -> usize {
+        self.view_offset
+    }
+
+    /// Scrolls the view back by `lines` lines, or forward for a negative number.
+    ///
+    /// The view stops at the oldest line of the scrollback buffer and at the newest line.
+    /// While the view is scrolled back, new text is only added to the buffer and the cursor is
+    /// hidden.
+    pub fn scroll_view(&mut self, lines: isize) {
+        let max_offset = self.line_count - ROWS;
+        let offset = if lines < 0 {
+            self.view_offset.saturating_sub(-lines as usize)
+        } else {
+            (self.view_offset + lines as usize).min(max_offset)
+        };
+        if offset != self.view_offset {
+            self.view_offset = offset;
+            self.drawn_cursor = None;
+            self.redraw();
+            self.draw_cursor();
+        }
+    }
+
+    /// Scrolls the view to the newest lines.
+    pub fn scroll_to_end(&mut self) {
+        self.scroll_view(-(self.view_offset as isize));
+    }
+
+    /// Draws all visible rows again.
+    pub fn redraw(&mut self) {
+        for row in 0..ROWS {
+            self.draw_row(row);
+        }
+    }
+
+    /// Returns the line of the ring buffer with the passed index, counted from the oldest line.
+    fn line_index(&self, index: usize) -> usize {
+        (self.first_line + index) % SCROLLBACK_LINES
+    }
+
+    /// Returns the line of the ring buffer that is shown in the visible `row` of the screen.
+    fn screen_line(&self, row: usize) -> usize {
+        self.line_index(self.line_count - ROWS + row)
+    }
+
+    fn shows_newest_lines(&self) -> bool {
+        self.view_offset == 0
+    }
+
+    fn draw_row(&mut self, row: usize) {
+        let line = self.line_index(self.line_count - ROWS - self.view_offset + row);
+        for column in 0..COLUMNS {
+            let cell = self.lines[line][column];
+            self.draw_cell(row, column, cell);
+        }
+    }
+
+    fn draw_cell(&mut self, row: usize, column: usize, cell: Cell) {
+        let glyph = if cell.character == REPLACEMENT {
+            font::REPLACEMENT_GLYPH
+        } else {
+            font::glyph(cell.character as char)
+        };
+        let foreground = PALETTE[cell.foreground as usize];
+        let background = match cell.background {
+            TRANSPARENT => Color::from_argb8888(0),
+            index => PALETTE[index as usize],
+        };
+        let (x, y) = (column * 8, row * 8);
+        for (dy, bits) in glyph.iter().enumerate() {
+            for dx in 0..8 {
+                let color = if bits & (1 << dx) == 0 {
+                    background
+                } else {
+                    foreground
+                };
+                self.layer.framebuffer.set_pixel(x + dx, y + dy, color);
+            }
+        }
+        self.layer.mark_dirty(Rect::new(x, y, 8, 8));
+    }
+
+    fn set_cell(&mut self, row: usize, column: usize, cell: Cell) {
+        let line = self.screen_line(row);
+        self.lines[line][column] = cell;
+        if self.shows_newest_lines() {
+            self.draw_cell(row, column, cell);
+        }
+    }
+
+    /// Draws the cursor, if the newest lines are visible.
+    fn draw_cursor(&mut self) {
+        if !self.shows_newest_lines() {
+            return;
+        }
+        let (row, column) = (self.row, self.column.min(COLUMNS - 1));
+        let color = PALETTE[DEFAULT_FOREGROUND as usize];
+        let line = Rect::new(column * 8, row * 8 + 7, 8, 1);
+        self.layer.fill_rect(line, color);
+        self.drawn_cursor = Some((row, column));
+    }
+
+    /// Removes the cursor by drawing the cell below it again.
+    fn hide_cursor(&mut self) {
+        if let Some((row, column)) = self.drawn_cursor.take() {
+            if self.shows_newest_lines() {
+                let cell = self.lines[self.screen_line(row)][column];
+                self.draw_cell(row, column, cell);
+            }
+        }
+    }
+
+    /// Adds a line at the bottom and moves the text up by one row.
+    fn scroll_up(&mut self) {
+        if self.line_count < SCROLLBACK_LINES {
+            self.line_count += 1;
+        } else {
+            self.first_line = (self.first_line + 1) % SCROLLBACK_LINES;
+        }
+        let last = self.screen_line(ROWS - 1);
+        self.lines[last] = [Cell::BLANK; COLUMNS];
+        if self.shows_newest_lines() {
+            self.redraw();
+        } else if self.view_offset < self.line_count - ROWS {
+            // keep the old lines in place while they are viewed
+            self.view_offset += 1;
+        } else {
+            // the oldest line was dropped, the view moves with the buffer
+            self.redraw();
+        }
+    }
+
+    fn newline(&mut self) {
+        self.column = 0;
+        if self.row + 1 < ROWS {
+            self.row += 1;
+        } else {
+            self.scroll_up();
+        }
+    }
+
+    fn put_char(&mut self, c: char) {
+        if self.column == COLUMNS {
+            self.newline();
+        }
+        let character = if (c as u32) < 0x100 {
+            c as u8
+        } else {
+            REPLACEMENT
+        };
+        let foreground = if self.bold && self.foreground < 8 {
+            self.foreground + 8
+        } else {
+            self.foreground
+        };
+        let cell = Cell {
+            character,
+            foreground,
+            background: self.background,
+        };
+        let (row, column) = (self.row, self.column);
+        self.set_cell(row, column, cell);
+        self.column += 1;
+    }
+
+    /// Clears the cells of `row` from `start` to the end of the row.
+    fn clear_row(&mut self, row: usize, start: usize) {
+        let blank = Cell {
+            background: self.background,
+            ..Cell::BLANK
+        };
+        for column in start..COLUMNS {
+            self.set_cell(row, column, blank);
+        }
+    }
+
+    fn write_char(&mut self, c: char) {
+        match self.escape {
+            Escape::None => {}
+            Escape::Started => {
+                self.escape = if c == '[' {
+                    Escape::Control {
+                        parameters: [0; MAX_PARAMETERS],
+                        count: 0,
+                    }
+                } else {
+                    Escape::None
+                };
+                return;
+            }
+            Escape::Control {
+                mut parameters,
+                mut count,
+            } => {
+                match c {
+                    '0'..='9' => {
+                        let digit = c as u16 - '0' as u16;
+                        if count < MAX_PARAMETERS {
+                            parameters[count] =
+                                parameters[count].saturating_mul(10).saturating_add(digit);
+                        }
+                        self.escape = Escape::Control { parameters, count };
+                    }
+                    ';' => {
+                        count += 1;
+                        self.escape = Escape::Control { parameters, count };
+                    }
+                    '@'..='~' => {
+                        self.escape = Escape::None;
+                        let count = (count + 1).min(MAX_PARAMETERS);
+                        self.control_sequence(c, &parameters[..count]);
+                    }
+                    _ => self.escape = Escape::None,
+                }
+                return;
+            }
+        }
+        match c {
+            '\x1b' => self.escape = Escape::Started,
+            '\n' => self.newline(),
+            '\r' => self.column = 0,
+            '\t' => {
+                let next_stop = (self.column / 8 + 1) * 8;
+                while self.column < next_stop.min(COLUMNS) {
+                    self.put_char(' ');
+                }
+            }
+            '\x08' => self.column = self.column.min(COLUMNS - 1).saturating_sub(1),
+            c if c.is_control() => {}
+            c => self.put_char(c),
+        }
+    }
+
+    /// Executes the control sequence `ESC[parameters final`. Missing parameters are 0.
+    fn control_sequence(&mut self, final_byte: char, parameters: &[u16]) {
+        // cursor movements treat a missing or zero count as 1
+        let count = (parameters[0] as usize).max(1);
+        match final_byte {
+            'm' => {
+                for &parameter in parameters {
+                    self.select_graphic_rendition(parameter);
+                }
+            }
+            'H' | 'f' => {
+                let row = parameters[0] as usize;
+                let column = parameters.get(1).cloned().unwrap_or(0) as usize;
+                self.row = row.max(1).min(ROWS) - 1;
+                self.column = column.max(1).min(COLUMNS) - 1;
+            }
+            'A' => self.row = self.row.saturating_sub(count),
+            'B' => self.row = (self.row + count).min(ROWS - 1),
+            'C' => self.column = (self.column + count).min(COLUMNS - 1),
+            'D' => self.column = self.column.min(COLUMNS - 1).saturating_sub(count),
+            'J' => {
+                let (first_row, start) = match parameters[0] {
+                    2 => (0, 0),
+                    _ => (self.row, self.column.min(COLUMNS)),
+                };
+                self.clear_row(first_row, start);
+                for row in first_row + 1..ROWS {
+                    self.clear_row(row, 0);
+                }
+            }
+            'K' => {
+                let row = self.row;
+                match parameters[0] {
+                    2 => self.clear_row(row, 0),
+                    _ => self.clear_row(row, self.column.min(COLUMNS)),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn select_graphic_rendition(&mut self, parameter: u16) {
+        match parameter {
+            0 => {
+                self.foreground = DEFAULT_FOREGROUND;
+                self.background = TRANSPARENT;
+                self.bold = false;
+            }
+            1 => self.bold = true,
+            22 => self.bold = false,
+            30..=37 => self.foreground = (parameter - 30) as u8,
+            39 => self.foreground = DEFAULT_FOREGROUND,
+            40..=47 => self.background = (parameter - 40) as u8,
+            49 => self.background = TRANSPARENT,
+            90..=97 => self.foreground = (parameter - 90 + 8) as u8,
+            100..=107 => self.background = (parameter - 100 + 8) as u8,
+            _ => {}
+        }
+    }
+}
+
+impl<T: Framebuffer> fmt::Write for Console<T> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.hide_cursor();
+        for c in s.chars() {
+            self.write_char(c);
+        }
+        self.draw_cursor();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lcd::MemoryFramebuffer;
+    use alloc::string::{String, ToString};
+    use core::fmt::Write;
+
+    fn console() -> Console<MemoryFramebuffer> {
+        Console::new(Layer::new(MemoryFramebuffer::new()))
+    }
+
+    /// Returns the text of a visible row, without the spaces at the end.
+    fn row_text(console: &Console<MemoryFramebuffer>, row: usize) -> String {
+        let line = console.line_index(console.line_count - ROWS - console.view_offset + row);
+        let text: String = console.lines[line]
+            .iter()
+            .map(|cell| cell.character as char)
+            .collect();
+        text.trim_end().to_string()
+    }
+
+    /// Returns whether the glyph of the cell contains a pixel of `color`.
+    fn cell_has_color(
+        console: &Console<MemoryFramebuffer>,
+        row: usize,
+        column: usize,
+        color: Color,
+    ) -> bool {
+        let framebuffer = console.layer().framebuffer();
+        (0..8).any(|y| (0..8).any(|x| framebuffer.pixel(column * 8 + x, row * 8 + y) == color))
+    }
+
+    #[test]
+    fn wraps_long_lines() {
+        let mut console = console();
+        let line: String = (0..COLUMNS + 3)
+            .map(|i| (b'a' + (i % 26) as u8) as char)
+            .collect();
+        write!(console, "{}\nnext", line).unwrap();
+        assert_eq!(row_text(&console, 0).len(), COLUMNS);
+        assert_eq!(row_text(&console, 1), "ijk");
+        assert_eq!(row_text(&console, 2), "next");
+        assert_eq!((console.row, console.column), (2, 4));
+    }
+
+    #[test]
+    fn moves_text_up_at_the_bottom() {
+        let mut console = console();
+        for i in 0..ROWS + 2 {
+            writeln!(console, "line {}", i).unwrap();
+        }
+        // the last row holds the cursor after the final newline
+        assert_eq!(row_text(&console, 0), "line 3");
+        assert_eq!(row_text(&console, ROWS - 2), format!("line {}", ROWS + 1));
+        assert_eq!(row_text(&console, ROWS - 1), "");
+        assert!(cell_has_color(
+            &console,
+            0,
+            0,
+            PALETTE[DEFAULT_FOREGROUND as usize]
+        ));
+    }
+
+    #[test]
+    fn scrolls_the_view_through_the_scrollback() {
+        let mut console = console();
+        for i in 0..SCROLLBACK_LINES + 10 {
+            writeln!(console, "line {}", i).unwrap();
+        }
+        console.scroll_view(5);
+        assert_eq!(console.view_offset(), 5);
+        assert_eq!(
+            row_text(&console, ROWS - 1),
+            format!("line {}", SCROLLBACK_LINES + 5)
+        );
+
+        // new text does not move the viewed lines
+        writeln!(console, "new").unwrap();
+        assert_eq!(console.view_offset(), 6);
+        assert_eq!(
+            row_text(&console, ROWS - 1),
+            format!("line {}", SCROLLBACK_LINES + 5)
+        );
+
+        // the view stops at the oldest line that is kept
+        console.scroll_view(1000);
+        assert_eq!(console.view_offset(), SCROLLBACK_LINES - ROWS);
+        assert_eq!(row_text(&console, 0), "line 12");
+
+        console.scroll_to_end();
+        assert_eq!(console.view_offset(), 0);
+        assert_eq!(row_text(&console, ROWS - 2), "new");
+    }
+
+    #[test]
+    fn applies_ansi_colors() {
+        let mut console = console();
+        write!(console, "\x1b[31mA\x1b[1;44mA\x1b[0mA").unwrap();
+        assert!(cell_has_color(&console, 0, 0, PALETTE[1]));
+        assert!(cell_has_color(&console, 0, 1, PALETTE[9]));
+        assert!(cell_has_color(&console, 0, 1, PALETTE[4]));
+        assert!(cell_has_color(&console, 0, 2, PALETTE[15]));
+        assert!(!cell_has_color(&console, 0, 2, PALETTE[4]));
+        assert_eq!(row_text(&console, 0), "AAA");
+    }
+
+    #[test]
+    fn moves_the_cursor_and_clears() {
+        let mut console = console();
+        write!(console, "first\nsecond\x1b[1;3HX\x1b[K").unwrap();
+        assert_eq!(row_text(&console, 0), "fiX");
+        assert_eq!(row_text(&console, 1), "second");
+        write!(console, "\x1b[2J").unwrap();
+        assert_eq!(row_text(&console, 0), "");
+        assert_eq!(row_text(&console, 1), "");
+    }
+}
@@ -0,0 +1,123 @@
+//! Tracking of the display regions that changed since the last update.
+
+use super::Rect;
+use arrayvec::ArrayVec;
+use core::slice;
+
+/// The maximal number of separate regions.
+///
+/// If more regions become dirty, the two regions whose union wastes the least area are merged.
+pub const MAX_REGIONS: usize = 8;
+
+/// A set of rectangles that cover all pixels that were changed.
+///
+/// Adjacent and overlapping rectangles are merged, so drawing many small primitives next to
+/// each other results in a single region.
+#[derive(Debug, Clone, Default)]
+pub struct DirtyRegions {
+    rects: ArrayVec<[Rect; MAX_REGIONS]>,
+}
+
+impl DirtyRegions {
+    /// Creates an empty set of regions.
+    pub fn new() -> DirtyRegions {
+        DirtyRegions::default()
+    }
+
+    /// Marks the pixels of the rectangle as changed.
+    pub fn add(&mut self, rect: Rect) {
+        if rect.is_empty() || self.rects.iter().any(|r| r.contains_rect(&rect)) {
+            return;
+        }
+        let mut rect = rect;
+        let mut i = 0;
+        while i < self.rects.len() {
+            if area(&self.rects[i].union(&rect)) <= area(&self.rects[i]) + area(&rect) {
+                rect = rect.union(&self.rects[i]);
+                self.rects.swap_remove(i);
+                // the grown rectangle might be mergeable with a region checked before
+                i = 0;
+            } else {
+                i += 1;
+            }
+        }
+        if self.rects.is_full() {
+            let (i, _) = self
+                .rects
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, r)| area(&r.union(&rect)) - area(r))
+                .expect("no regions although full");
+            rect = rect.union(&self.rects[i]);
+            self.rects.swap_remove(i);
+        }
+        self.rects.push(rect);
+    }
+
+    /// Returns `true` if no pixels changed.
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    /// Returns the regions.
+    pub fn iter(&self) -> slice::Iter<Rect> {
+        self.rects.iter()
+    }
+
+    /// Returns the smallest rectangle that contains all regions.
+    pub fn bounds(&self) -> Rect {
+        self.rects
+            .iter()
+            .fold(Rect::new(0, 0, 0, 0), |bounds, rect| bounds.union(rect))
+    }
+
+    /// Removes all regions.
+    pub fn clear(&mut self) {
+        self.rects.clear();
+    }
+}
+
+fn area(rect: &Rect) -> usize {
+    rect.width * rect.height
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DirtyRegions, MAX_REGIONS};
+    use crate::lcd::Rect;
+
+    #[test]
+    fn adjacent_pixels_are_merged() {
+        let mut dirty = DirtyRegions::new();
+        for y in 10..20 {
+            for x in 5..15 {
+                dirty.add(Rect::new(x, y, 1, 1));
+            }
+        }
+        assert_eq!(dirty.iter().count(), 1);
+        assert_eq!(dirty.bounds(), Rect::new(5, 10, 10, 10));
+    }
+
+    #[test]
+    fn distant_rects_are_kept_apart() {
+        let mut dirty = DirtyRegions::new();
+        dirty.add(Rect::new(0, 0, 25, 25));
+        dirty.add(Rect::new(200, 100, 25, 25));
+        dirty.add(Rect::new(5, 5, 10, 10));
+        assert_eq!(dirty.iter().count(), 2);
+        assert_eq!(dirty.bounds(), Rect::new(0, 0, 225, 125));
+    }
+
+    #[test]
+    fn number_of_regions_is_bounded() {
+        let mut dirty = DirtyRegions::new();
+        for i in 0..3 * MAX_REGIONS {
+            dirty.add(Rect::new(i * 20, (i % 2) * 100, 5, 5));
+        }
+        assert_eq!(dirty.iter().count(), MAX_REGIONS);
+        for i in 0..3 * MAX_REGIONS {
+            let rect = Rect::new(i * 20, (i % 2) * 100, 5, 5);
+            assert!(dirty.iter().any(|r| r.contains_rect(&rect)));
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! Hardware accelerated fills and copies with the Chrom-ART accelerator (DMA2D).
+//!
+//! The accelerator is optional. After [`enable`] is called, rectangle fills and image copies on
+//! ARGB8888 layers are performed by the DMA2D instead of the CPU. The AL88 format is not
+//! supported as output format by the DMA2D, so layer 2 always uses the software path.
+
+use super::{Rect, WIDTH};
+use spin::Mutex;
+use stm32f7::stm32f7x6::DMA2D;
+
+static ACCELERATOR: Mutex<Option<DMA2D>> = Mutex::new(None);
+
+const MODE_MEMORY_TO_MEMORY: u32 = 0b00 << 16;
+const MODE_REGISTER_TO_MEMORY: u32 = 0b11 << 16;
+const CR_START: u32 = 1 << 0;
+const ISR_TRANSFER_ERROR: u32 = 1 << 0;
+const ISR_CONFIGURATION_ERROR: u32 = 1 << 5;
+const COLOR_MODE_ARGB8888: u32 = 0b000;
+
+/// Uses the passed DMA2D for all following fills and copies on ARGB8888 layers.
+///
+/// The DMA2D clock is enabled by [`init`](super::init).
+pub fn enable(dma2d: DMA2D) {
+    *ACCELERATOR.lock() = Some(dma2d);
+}
+
+/// Stops using the DMA2D and returns it.
+pub fn disable() -> Option<DMA2D> {
+    ACCELERATOR.lock().take()
+}
+
+/// Returns whether the DMA2D is used.
+pub fn is_enabled() -> bool {
+    ACCELERATOR.lock().is_some()
+}
+
+/// Fills the rectangle of the ARGB8888 framebuffer at `base_addr` with the passed color.
+///
+/// Returns `false` if the DMA2D is not enabled or the transfer failed.
+pub(super) fn fill_argb8888(base_addr: usize, rect: Rect, argb: u32) -> bool {
+    let mut accelerator = ACCELERATOR.lock();
+    let dma2d = match accelerator.as_mut() {
+        Some(dma2d) => dma2d,
+        None => return false,
+    };
+    let output_addr = base_addr + (rect.y * WIDTH + rect.x) * 4;
+    unsafe {
+        dma2d.opfccr.write(|w| w.bits(COLOR_MODE_ARGB8888));
+        dma2d.ocolr.write(|w| w.bits(argb));
+        dma2d.omar.write(|w| w.bits(output_addr as u32));
+        dma2d.oor.write(|w| w.bits((WIDTH - rect.width) as u32));
+        dma2d.nlr.write(|w| w.bits(lines(rect)));
+        dma2d.cr.write(|w| w.bits(MODE_REGISTER_TO_MEMORY | CR_START));
+    }
+    wait_for_transfer(dma2d)
+}
+
+/// Copies ARGB8888 pixels to the rectangle of the ARGB8888 framebuffer at `base_addr`.
+///
+/// `pixels` must contain `rect.height` rows of `stride` pixels each. Returns `false` if the
+/// DMA2D is not enabled or the transfer failed.
+pub(super) fn copy_argb8888(base_addr: usize, rect: Rect, pixels: &[u32], stride: usize) -> bool {
+    let mut accelerator = ACCELERATOR.lock();
+    let dma2d = match accelerator.as_mut() {
+        Some(dma2d) => dma2d,
+        None => return false,
+    };
+    let output_addr = base_addr + (rect.y * WIDTH + rect.x) * 4;
+    unsafe {
+        dma2d.fgpfccr.write(|w| w.bits(COLOR_MODE_ARGB8888));
+        dma2d.fgmar.write(|w| w.bits(pixels.as_ptr() as u32));
+        dma2d.fgor.write(|w| w.bits((stride - rect.width) as u32));
+        dma2d.opfccr.write(|w| w.bits(COLOR_MODE_ARGB8888));
+        dma2d.omar.write(|w| w.bits(output_addr as u32));
+        dma2d.oor.write(|w| w.bits((WIDTH - rect.width) as u32));
+        dma2d.nlr.write(|w| w.bits(lines(rect)));
+        dma2d.cr.write(|w| w.bits(MODE_MEMORY_TO_MEMORY | CR_START));
+    }
+    wait_for_transfer(dma2d)
+}
+
+/// Returns the value of the number of lines register: pixels per line in bits 29:16 and the
+/// number of lines in bits 15:0.
+fn lines(rect: Rect) -> u32 {
+    (rect.width as u32) << 16 | rect.height as u32
+}
+
+fn wait_for_transfer(dma2d: &mut DMA2D) -> bool {
+    while dma2d.cr.read().bits() & CR_START != 0 {}
+    let errors = dma2d.isr.read().bits() & (ISR_TRANSFER_ERROR | ISR_CONFIGURATION_ERROR);
+    // clear all interrupt flags
+    dma2d.ifcr.write(|w| unsafe { w.bits(0x3f) });
+    errors == 0
+}
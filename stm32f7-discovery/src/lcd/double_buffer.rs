@@ -0,0 +1,219 @@
+//! Double buffered layers with tear-free page flipping.
+//!
+//! A [`DoubleBuffer`] owns two framebuffers of a layer. Drawing always goes to the back buffer,
+//! while the LTDC scans out the front buffer. [`DoubleBuffer::present`] makes the back buffer
+//! visible during the vertical blanking period, so that a half drawn frame is never shown.
+//!
+//! The switch happens in the LTDC line interrupt if it was enabled with
+//! [`Lcd::enable_line_interrupt`](super::Lcd::enable_line_interrupt) and
+//! [`handle_line_interrupt`] is registered for the `LTDC` interrupt in the
+//! [`InterruptTable`](crate::interrupts::InterruptTable). Otherwise the controller reloads the
+//! address on its own at the next vertical blanking and `present` polls until this happened.
+
+use super::{
+    dma2d, Framebuffer, FramebufferAl88, FramebufferAl88Clut, FramebufferArgb8888, Layer, Rect,
+    HEIGHT, WIDTH,
+};
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+use core::slice;
+use core::sync::atomic::{AtomicBool, Ordering};
+use stm32f7::stm32f7x6::LTDC;
+
+/// The first line after the active display area, which is the start of the vertical blanking.
+pub(super) const BLANKING_LINE: u16 = HEIGHT as u16 + 10 + 2;
+
+static LINE_INTERRUPT_ENABLED: AtomicBool = AtomicBool::new(false);
+static RELOAD_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+pub(super) fn set_line_interrupt_enabled(enabled: bool) {
+    LINE_INTERRUPT_ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// The interrupt handler for the LTDC line interrupt.
+///
+/// Reloads the shadow registers of the controller if a page flip was requested. The line
+/// interrupt is triggered at the start of the vertical blanking, so the new framebuffer
+/// addresses take effect before the next frame is scanned out.
+pub fn handle_line_interrupt() {
+    // the interrupt only touches the interrupt clear and shadow reload registers, which are
+    // never modified by the owner of the LTDC outside of the initialization
+    let ltdc = unsafe { &*LTDC::ptr() };
+    ltdc.icr.write(|w| w.clif().set_bit());
+    if RELOAD_REQUESTED.load(Ordering::SeqCst) {
+        ltdc.srcr.write(|w| w.imr().set_bit());
+        RELOAD_REQUESTED.store(false, Ordering::SeqCst);
+    }
+}
+
+/// The LTDC layer that shows a framebuffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LtdcLayer {
+    /// Layer 1, which is drawn above the background color.
+    Layer1,
+    /// Layer 2, which is drawn above layer 1.
+    Layer2,
+}
+
+/// Framebuffer formats that can be double buffered.
+///
+/// This trait is not exported, so it can't be implemented outside of this crate.
+pub trait Buffered: Framebuffer {
+    /// The layer that shows framebuffers of this format.
+    const LAYER: LtdcLayer;
+
+    /// Returns the start address of the framebuffer.
+    fn base_addr(&self) -> usize;
+
+    /// Creates a framebuffer of the same kind at the passed address.
+    fn at(&self, base_addr: usize) -> Self;
+
+    /// Copies the rectangle from the framebuffer at `from` to the framebuffer at `to`.
+    fn copy_buffer(from: usize, to: usize, rect: Rect);
+}
+
+impl Buffered for FramebufferArgb8888 {
+    const LAYER: LtdcLayer = LtdcLayer::Layer1;
+
+    fn base_addr(&self) -> usize {
+        self.base_addr
+    }
+
+    fn at(&self, base_addr: usize) -> Self {
+        FramebufferArgb8888::new(base_addr)
+    }
+
+    fn copy_buffer(from: usize, to: usize, rect: Rect) {
+        let pixels = unsafe { slice::from_raw_parts(from as *const u32, WIDTH * HEIGHT) };
+        let offset = rect.y * WIDTH + rect.x;
+        if !dma2d::copy_argb8888(to, rect, &pixels[offset..], WIDTH) {
+            copy_rows(from, to, rect, 4);
+        }
+    }
+}
+
+impl Buffered for FramebufferAl88 {
+    const LAYER: LtdcLayer = LtdcLayer::Layer2;
+
+    fn base_addr(&self) -> usize {
+        self.base_addr
+    }
+
+    fn at(&self, base_addr: usize) -> Self {
+        FramebufferAl88::new(base_addr)
+    }
+
+    fn copy_buffer(from: usize, to: usize, rect: Rect) {
+        copy_rows(from, to, rect, 2);
+    }
+}
+
+impl Buffered for FramebufferAl88Clut {
+    const LAYER: LtdcLayer = LtdcLayer::Layer2;
+
+    fn base_addr(&self) -> usize {
+        self.base_addr
+    }
+
+    fn at(&self, base_addr: usize) -> Self {
+        FramebufferAl88Clut::new(base_addr, self.palette)
+    }
+
+    fn copy_buffer(from: usize, to: usize, rect: Rect) {
+        copy_rows(from, to, rect, 2);
+    }
+}
+
+fn copy_rows(from: usize, to: usize, rect: Rect, octets_per_pixel: usize) {
+    for y in rect.y..rect.bottom() {
+        let offset = (y * WIDTH + rect.x) * octets_per_pixel;
+        unsafe {
+            ptr::copy_nonoverlapping(
+                (from + offset) as *const u8,
+                (to + offset) as *mut u8,
+                rect.width * octets_per_pixel,
+            )
+        };
+    }
+}
+
+/// A layer with a front buffer that is shown and a back buffer that is drawn to.
+///
+/// It dereferences to the [`Layer`] of the back buffer, so all drawing methods of layers are
+/// available. Nothing drawn is visible until [`present`](DoubleBuffer::present) is called.
+pub struct DoubleBuffer<T> {
+    layer: Layer<T>,
+    front: usize,
+    back: usize,
+}
+
+impl<T: Buffered> DoubleBuffer<T> {
+    /// Creates a double buffer that shows the `front` framebuffer and uses `back` as address of
+    /// the second buffer.
+    ///
+    /// The back buffer starts with a copy of the front buffer.
+    pub(super) fn new(front: T, back: usize) -> DoubleBuffer<T> {
+        let front_addr = front.base_addr();
+        T::copy_buffer(front_addr, back, Rect::screen());
+        DoubleBuffer {
+            layer: Layer::new(front.at(back)),
+            front: front_addr,
+            back,
+        }
+    }
+
+    /// Returns whether the back buffer was drawn to since the last `present`.
+    pub fn is_modified(&self) -> bool {
+        !self.layer.dirty_regions().is_empty()
+    }
+
+    /// Shows the back buffer, starting with the next frame.
+    ///
+    /// Waits until the switch happened and copies the regions that changed to the new back
+    /// buffer, so drawing continues on top of the visible frame. Does nothing if nothing was
+    /// drawn since the last call.
+    pub fn present(&mut self) {
+        let dirty = self.layer.take_dirty_regions();
+        if dirty.is_empty() {
+            return;
+        }
+        // the controller only reads the shadow register, which is updated on reload
+        let ltdc = unsafe { &*LTDC::ptr() };
+        match T::LAYER {
+            LtdcLayer::Layer1 => ltdc
+                .l1cfbar
+                .modify(|_, w| unsafe { w.cfbadd().bits(self.back as u32) }),
+            LtdcLayer::Layer2 => ltdc
+                .l2cfbar
+                .modify(|_, w| unsafe { w.cfbadd().bits(self.back as u32) }),
+        }
+        if LINE_INTERRUPT_ENABLED.load(Ordering::SeqCst) {
+            RELOAD_REQUESTED.store(true, Ordering::SeqCst);
+            while RELOAD_REQUESTED.load(Ordering::SeqCst) {}
+        } else {
+            ltdc.srcr.modify(|_, w| w.vbr().set_bit());
+            while ltdc.srcr.read().vbr().bit_is_set() {}
+        }
+
+        // the new back buffer only misses the changes of the presented frame
+        core::mem::swap(&mut self.front, &mut self.back);
+        for rect in dirty.iter() {
+            T::copy_buffer(self.front, self.back, *rect);
+        }
+        self.layer = Layer::new(self.layer.framebuffer.at(self.back));
+    }
+}
+
+impl<T> Deref for DoubleBuffer<T> {
+    type Target = Layer<T>;
+
+    fn deref(&self) -> &Layer<T> {
+        &self.layer
+    }
+}
+
+impl<T> DerefMut for DoubleBuffer<T> {
+    fn deref_mut(&mut self) -> &mut Layer<T> {
+        &mut self.layer
+    }
+}
@@ -0,0 +1,274 @@
+//! Drawing primitives for layers.
+//!
+//! All primitives are clipped to the display. Rectangle fills and image copies are forwarded to
+//! [`Framebuffer::fill_rect`] and [`Framebuffer::copy_rect`], so that framebuffers can provide
+//! faster implementations than writing single pixels.
+
+use super::{Color, Framebuffer, Layer, Point, Rect, HEIGHT, WIDTH};
+
+/// An image of ARGB8888 pixels, stored row by row.
+#[derive(Debug, Clone, Copy)]
+pub struct Image<'a> {
+    width: usize,
+    height: usize,
+    pixels: &'a [u32],
+}
+
+impl<'a> Image<'a> {
+    /// Creates an image from `width * height` ARGB8888 pixels.
+    pub fn new(width: usize, height: usize, pixels: &'a [u32]) -> Image<'a> {
+        assert_eq!(pixels.len(), width * height);
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Returns the width of the image in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the image in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the pixels of the image, row by row.
+    pub fn pixels(&self) -> &'a [u32] {
+        self.pixels
+    }
+}
+
+fn screen() -> Rect {
+    Rect::new(0, 0, WIDTH, HEIGHT)
+}
+
+impl<T: Framebuffer> Layer<T> {
+    /// Fills the rectangle with the passed color.
+    pub fn fill_rect(&mut self, rect: Rect, color: Color) {
+        if let Some(rect) = rect.intersection(&screen()) {
+            self.dirty.add(rect);
+            self.framebuffer.fill_rect(rect, color);
+        }
+    }
+
+    /// Draws the one pixel wide outline of the rectangle.
+    pub fn draw_rect(&mut self, rect: Rect, color: Color) {
+        if rect.is_empty() {
+            return;
+        }
+        self.hline(rect.x, rect.y, rect.width, color);
+        self.hline(rect.x, rect.bottom() - 1, rect.width, color);
+        self.vline(rect.x, rect.y, rect.height, color);
+        self.vline(rect.right() - 1, rect.y, rect.height, color);
+    }
+
+    /// Draws a horizontal line of `len` pixels, starting at (`x`, `y`) to the right.
+    pub fn hline(&mut self, x: usize, y: usize, len: usize, color: Color) {
+        self.fill_rect(Rect::new(x, y, len, 1), color);
+    }
+
+    /// Draws a vertical line of `len` pixels, starting at (`x`, `y`) downwards.
+    pub fn vline(&mut self, x: usize, y: usize, len: usize, color: Color) {
+        self.fill_rect(Rect::new(x, y, 1, len), color);
+    }
+
+    /// Draws a line from `from` to `to`, including both end points.
+    pub fn draw_line(&mut self, from: Point, to: Point, color: Color) {
+        if from.y == to.y {
+            let x = from.x.min(to.x);
+            return self.hline(x, from.y, from.x.max(to.x) - x + 1, color);
+        }
+        if from.x == to.x {
+            let y = from.y.min(to.y);
+            return self.vline(from.x, y, from.y.max(to.y) - y + 1, color);
+        }
+
+        // Bresenham's algorithm
+        let (mut x, mut y) = (from.x as isize, from.y as isize);
+        let (x1, y1) = (to.x as isize, to.y as isize);
+        let dx = (x1 - x).abs();
+        let dy = -(y1 - y).abs();
+        let step_x = if x < x1 { 1 } else { -1 };
+        let step_y = if y < y1 { 1 } else { -1 };
+        let mut error = dx + dy;
+        loop {
+            self.set_pixel_clipped(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if e2 <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// Draws the one pixel wide outline of a circle.
+    pub fn draw_circle(&mut self, center: Point, radius: usize, color: Color) {
+        // midpoint circle algorithm, which computes one octant and mirrors it
+        let (cx, cy) = (center.x as isize, center.y as isize);
+        let mut x = radius as isize;
+        let mut y = 0;
+        let mut error = 1 - x;
+        while x >= y {
+            for &(px, py) in [
+                (x, y),
+                (y, x),
+                (-y, x),
+                (-x, y),
+                (-x, -y),
+                (-y, -x),
+                (y, -x),
+                (x, -y),
+            ]
+            .iter()
+            {
+                self.set_pixel_clipped(cx + px, cy + py, color);
+            }
+            y += 1;
+            if error < 0 {
+                error += 2 * y + 1;
+            } else {
+                x -= 1;
+                error += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// Copies the image to the layer, with its top left corner at `position`.
+    ///
+    /// The alpha channel of the image is copied as well, it is not blended with the current
+    /// content of the layer.
+    pub fn blit(&mut self, position: Point, image: &Image) {
+        let target = Rect::new(position.x, position.y, image.width, image.height);
+        let visible = match target.intersection(&screen()) {
+            Some(visible) => visible,
+            None => return,
+        };
+        let offset = (visible.y - position.y) * image.width + (visible.x - position.x);
+        self.dirty.add(visible);
+        self.framebuffer
+            .copy_rect(visible, &image.pixels[offset..], image.width);
+    }
+
+    fn set_pixel_clipped(&mut self, x: isize, y: isize, color: Color) {
+        if x >= 0 && y >= 0 && (x as usize) < WIDTH && (y as usize) < HEIGHT {
+            self.dirty.add(Rect::new(x as usize, y as usize, 1, 1));
+            self.framebuffer.set_pixel(x as usize, y as usize, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Image;
+    use crate::lcd::{Color, Layer, MemoryFramebuffer, Point, Rect, HEIGHT, WIDTH};
+    use alloc::vec::Vec;
+
+    fn memory_layer() -> Layer<MemoryFramebuffer> {
+        Layer::new(MemoryFramebuffer::new())
+    }
+
+    fn set_pixels(layer: &Layer<MemoryFramebuffer>, color: Color) -> Vec<(usize, usize)> {
+        let mut pixels = Vec::new();
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                if layer.framebuffer().pixel(x, y) == color {
+                    pixels.push((x, y));
+                }
+            }
+        }
+        pixels
+    }
+
+    const RED: Color = Color {
+        red: 255,
+        green: 0,
+        blue: 0,
+        alpha: 255,
+    };
+
+    #[test]
+    fn fill_rect_is_clipped() {
+        let mut layer = memory_layer();
+        layer.fill_rect(Rect::new(WIDTH - 2, HEIGHT - 3, 10, 10), RED);
+        let pixels = set_pixels(&layer, RED);
+        assert_eq!(pixels.len(), 2 * 3);
+        assert!(pixels.contains(&(WIDTH - 1, HEIGHT - 1)));
+        assert!(pixels.contains(&(WIDTH - 2, HEIGHT - 3)));
+    }
+
+    #[test]
+    fn drawing_marks_regions_dirty() {
+        let mut layer = memory_layer();
+        let filled = Rect::new(WIDTH - 2, HEIGHT - 3, 2, 3);
+        let outline = Rect::new(10, 20, 5, 4);
+        layer.fill_rect(Rect::new(WIDTH - 2, HEIGHT - 3, 10, 10), RED);
+        layer.draw_rect(outline, RED);
+        let dirty = layer.take_dirty_regions();
+        assert!(dirty.iter().any(|r| *r == filled));
+        for rect in dirty.iter().filter(|r| **r != filled) {
+            assert!(outline.contains_rect(rect));
+        }
+        assert_eq!(dirty.bounds(), outline.union(&filled));
+        assert!(layer.dirty_regions().is_empty());
+    }
+
+    #[test]
+    fn draw_rect_draws_outline() {
+        let mut layer = memory_layer();
+        layer.draw_rect(Rect::new(10, 20, 5, 4), RED);
+        let pixels = set_pixels(&layer, RED);
+        assert_eq!(pixels.len(), 2 * 5 + 2 * 2);
+        assert!(pixels.contains(&(14, 23)));
+        assert!(!pixels.contains(&(12, 21)));
+    }
+
+    #[test]
+    fn draw_line_includes_end_points() {
+        let mut layer = memory_layer();
+        layer.draw_line(Point::new(3, 2), Point::new(13, 7), RED);
+        let pixels = set_pixels(&layer, RED);
+        assert_eq!(pixels.len(), 11);
+        assert!(pixels.contains(&(3, 2)));
+        assert!(pixels.contains(&(13, 7)));
+
+        let mut layer = memory_layer();
+        layer.draw_line(Point::new(20, 9), Point::new(20, 5), RED);
+        assert_eq!(set_pixels(&layer, RED).len(), 5);
+    }
+
+    #[test]
+    fn draw_circle_is_symmetric() {
+        let mut layer = memory_layer();
+        layer.draw_circle(Point::new(50, 50), 10, RED);
+        let pixels = set_pixels(&layer, RED);
+        for &(x, y) in pixels.iter() {
+            assert!(pixels.contains(&(100 - x, y)));
+            assert!(pixels.contains(&(x, 100 - y)));
+        }
+        assert!(pixels.contains(&(60, 50)));
+        assert!(pixels.contains(&(50, 40)));
+        assert!(!pixels.contains(&(50, 50)));
+    }
+
+    #[test]
+    fn blit_copies_visible_part() {
+        let pixels: Vec<u32> = (0..12).map(|i| 0xff00_0000 | i).collect();
+        let image = Image::new(4, 3, &pixels);
+        let mut layer = memory_layer();
+        layer.blit(Point::new(WIDTH - 2, HEIGHT - 1), &image);
+        let framebuffer = layer.framebuffer();
+        assert_eq!(framebuffer.pixel(WIDTH - 2, HEIGHT - 1), Color::from_argb8888(0xff00_0000));
+        assert_eq!(framebuffer.pixel(WIDTH - 1, HEIGHT - 1), Color::from_argb8888(0xff00_0001));
+        assert_eq!(framebuffer.pixel(WIDTH - 3, HEIGHT - 1), Color::from_argb8888(0));
+    }
+}
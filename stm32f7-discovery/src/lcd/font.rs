@@ -1,77 +1,231 @@
-use alloc::Vec;
+//! Fonts that render anti-aliased text at different sizes.
+//!
+//! A [`Font`] produces a coverage value between 0 and 255 for every pixel of a glyph.
+//! [`Layer::draw_text`] blends the text color with this coverage over the current content of the
+//! layer, so text can be drawn on top of images on both layers.
+//!
+//! [`Font8x8`] scales the pre-rasterized glyphs of the `font8x8` crate and smooths the edges.
 
-pub struct FontRenderer<'a> {
-    font: Font<'a>,
-    height: f32,
+use super::{Color, Framebuffer, Layer, Point, Rect};
+use font8x8::{self, UnicodeFonts};
+
+/// The glyph that is drawn for characters that `font8x8` has no glyph for, an empty box.
+pub const REPLACEMENT_GLYPH: [u8; 8] = [0x7e, 0x42, 0x42, 0x42, 0x42, 0x42, 0x7e, 0x00];
+
+/// Returns the 8x8 glyph of `c`, with the pixels of row `y` in the bits of byte `y`.
+///
+/// Looks up the basic (ASCII), Latin-1, Greek, box drawing and block glyphs of `font8x8` and
+/// returns [`REPLACEMENT_GLYPH`] for all other characters.
+pub fn glyph(c: char) -> [u8; 8] {
+    font8x8::BASIC_FONTS
+        .get(c)
+        .or_else(|| font8x8::LATIN_FONTS.get(c))
+        .or_else(|| font8x8::GREEK_FONTS.get(c))
+        .or_else(|| font8x8::BOX_FONTS.get(c))
+        .or_else(|| font8x8::BLOCK_FONTS.get(c))
+        .unwrap_or(REPLACEMENT_GLYPH)
+}
+
+/// A font that renders glyphs as coverage values.
+pub trait Font {
+    /// Returns the height of a line of text in pixels.
+    fn height(&self) -> usize;
+
+    /// Returns the horizontal distance from the start of `c` to the start of the next character.
+    fn advance(&self, c: char) -> usize;
+
+    /// Renders the glyph of `c`.
+    ///
+    /// `draw` is called with the coordinates relative to the top left corner of the line and the
+    /// coverage of the pixel, where 255 means that the pixel is completely covered. Pixels with a
+    /// coverage of 0 may be skipped.
+    fn draw_glyph<F: FnMut(usize, usize, u8)>(&self, c: char, draw: F);
+
+    /// Returns the width of `text` in pixels, e.g. for centering it.
+    fn text_width(&self, text: &str) -> usize {
+        text.chars().map(|c| self.advance(c)).sum()
+    }
+}
+
+/// The 8x8 pixel font of the [`TextWriter`](super::TextWriter), scaled by an integer factor.
+///
+/// The scaled glyphs are interpolated bilinearly and the edges are sharpened again, which gives
+/// rounded diagonals instead of large steps. A scale of 1 renders the glyphs unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Font8x8 {
+    scale: usize,
 }
 
-impl<'a> FontRenderer<'a> {
-    pub fn new(font_data: &[u8], font_height: f32) -> FontRenderer {
-        let collection = FontCollection::from_bytes(font_data);
-        // only succeeds if collection consists of one font
-        let font = collection.into_font().unwrap();
-        FontRenderer {
-            font,
-            height: font_height,
+impl Font8x8 {
+    /// Creates the font with glyphs of `8 * scale` pixels. A scale of 0 is treated as 1.
+    pub fn new(scale: usize) -> Font8x8 {
+        Font8x8 {
+            scale: scale.max(1),
         }
     }
 
-    pub fn font_height(&self) -> f32 {
-        self.height
+    /// Maps the center of the output pixel `i` to the glyph, in 8.8 fixed point.
+    fn source_position(&self, i: usize) -> i32 {
+        ((2 * i + 1) * 256 / (2 * self.scale)) as i32 - 128
     }
+}
 
-    pub fn layout(&self, s: &str) -> Vec<PositionedGlyph> {
-        let scale = Scale {
-            x: self.height,
-            y: self.height,
-        };
+impl Font for Font8x8 {
+    fn height(&self) -> usize {
+        8 * self.scale
+    }
 
-        // The origin of a line of text is at the baseline (roughly where non-descending letters
-        // sit). We don't want to clip the text, so we shift it down with an offset when laying
-        // it out. v_metrics.ascent is the distance between the baseline and the highest edge of
-        // any glyph in the font. That's enough to guarantee that there's no clipping.
-        let v_metrics = self.font.v_metrics(scale);
-        let offset = point(0.0, v_metrics.ascent);
+    fn advance(&self, _c: char) -> usize {
+        8 * self.scale
+    }
 
-        self.font.layout(s, scale, offset).collect()
+    fn draw_glyph<F: FnMut(usize, usize, u8)>(&self, c: char, mut draw: F) {
+        let glyph = glyph(c);
+        let bit = |x: i32, y: i32| -> i32 {
+            if x < 0 || y < 0 || x >= 8 || y >= 8 || glyph[y as usize] & (1 << x) == 0 {
+                0
+            } else {
+                255
+            }
+        };
+        let size = 8 * self.scale;
+        for y in 0..size {
+            let sy = self.source_position(y);
+            let (y0, fy) = (sy >> 8, sy & 0xff);
+            for x in 0..size {
+                let sx = self.source_position(x);
+                let (x0, fx) = (sx >> 8, sx & 0xff);
+                let top = bit(x0, y0) * (256 - fx) + bit(x0 + 1, y0) * fx;
+                let bottom = bit(x0, y0 + 1) * (256 - fx) + bit(x0 + 1, y0 + 1) * fx;
+                let value = (top * (256 - fy) + bottom * fy) >> 16;
+                // the interpolation blurs the edges over a whole source pixel, so only the
+                // middle of the ramp is kept
+                let coverage = ((value - 64) * 2).max(0).min(255);
+                if coverage > 0 {
+                    draw(x, y, coverage as u8);
+                }
+            }
+        }
     }
+}
+
+impl<T: Framebuffer> Layer<T> {
+    /// Draws a line of text with its top left corner at `position` and returns its width.
+    ///
+    /// The coverage of the glyphs is multiplied with the alpha value of `color` and blended over
+    /// the current content of the layer. Framebuffers that can't be read are treated as
+    /// transparent. The text is clipped to the display.
+    pub fn draw_text<F: Font>(
+        &mut self,
+        position: Point,
+        text: &str,
+        font: &F,
+        color: Color,
+    ) -> usize {
+        self.draw_text_clipped(position, text, font, color, Rect::screen())
+    }
+
+    /// Like [`draw_text`](Layer::draw_text), but only pixels inside of `clip` are drawn.
+    pub(super) fn draw_text_clipped<F: Font>(
+        &mut self,
+        position: Point,
+        text: &str,
+        font: &F,
+        color: Color,
+        clip: Rect,
+    ) -> usize {
+        let width = font.text_width(text);
+        let target = Rect::new(position.x, position.y, width, font.height());
+        let clip = match clip
+            .intersection(&Rect::screen())
+            .and_then(|clip| clip.intersection(&target))
+        {
+            Some(clip) => clip,
+            None => return width,
+        };
+        self.dirty.add(clip);
 
-    pub fn render<F>(&self, s: &str, mut draw_pixel: F) -> usize
-    where
-        F: FnMut(usize, usize, f32),
-    {
-        let glyphs = self.layout(s);
-        let pixel_height = self.height.ceil() as usize;
-
-        // Find the most visually pleasing width to display
-        let width = glyphs
-            .iter()
-            .rev()
-            .map(|g| g.position().x as f32 + g.unpositioned().h_metrics().advance_width)
-            .next()
-            .unwrap_or(0.0)
-            .ceil() as usize;
-
-        for g in glyphs {
-            if let Some(bb) = g.pixel_bounding_box() {
-                g.draw(|x, y, mut v| {
-                    assert!(v >= 0.0 - 1.0e-5);
-                    assert!(v <= 1.0 + 1.0e-5);
-                    if v < 0.0 {
-                        v = 0.0;
-                    }
-                    if v > 1.0 {
-                        v = 1.0;
-                    }
-                    let x = x as i32 + bb.min.x;
-                    let y = y as i32 + bb.min.y;
-                    // There's still a possibility that the glyph clips the boundaries of the bitmap
-                    if x >= 0 && x < width as i32 && y >= 0 && y < pixel_height as i32 {
-                        draw_pixel(x as usize, y as usize, v);
-                    }
-                });
+        let framebuffer = &mut self.framebuffer;
+        let mut x_pos = position.x;
+        for c in text.chars() {
+            if x_pos >= clip.right() {
+                break;
             }
+            font.draw_glyph(c, |x, y, coverage| {
+                let point = Point::new(x_pos + x, position.y + y);
+                if !clip.contains(point) {
+                    return;
+                }
+                let alpha = u32::from(coverage) * u32::from(color.alpha) / 255;
+                let foreground = Color {
+                    alpha: alpha as u8,
+                    ..color
+                };
+                let background = framebuffer
+                    .read_pixel(point.x, point.y)
+                    .unwrap_or(Color::rgba(0, 0, 0, 0));
+                framebuffer.set_pixel(point.x, point.y, foreground.blend_over(background));
+            });
+            x_pos += font.advance(c);
         }
-        return width;
+        width
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glyph, Font, Font8x8, REPLACEMENT_GLYPH};
+    use crate::lcd::{Color, Layer, MemoryFramebuffer, Point, HEIGHT, WIDTH};
+    use alloc::vec::Vec;
+
+    fn render(font: &Font8x8, c: char) -> Vec<u8> {
+        let size = font.height();
+        let mut pixels = alloc::vec![0; size * size];
+        font.draw_glyph(c, |x, y, coverage| pixels[y * size + x] = coverage);
+        pixels
+    }
+
+    #[test]
+    fn scale_one_matches_the_bitmap() {
+        let pixels = render(&Font8x8::new(1), 'A');
+        // the first row of 'A' in font8x8 is 0x0C
+        let row: Vec<u8> = pixels[..8].to_vec();
+        assert_eq!(row, [0, 0, 255, 255, 0, 0, 0, 0]);
+        assert!(pixels.iter().all(|&c| c == 0 || c == 255));
+    }
+
+    #[test]
+    fn glyphs_outside_of_ascii() {
+        assert_ne!(glyph('ä'), REPLACEMENT_GLYPH);
+        assert_ne!(glyph('°'), REPLACEMENT_GLYPH);
+        assert_ne!(glyph('λ'), REPLACEMENT_GLYPH);
+        assert_ne!(glyph('┼'), REPLACEMENT_GLYPH);
+        assert_eq!(glyph('\u{4e2d}'), REPLACEMENT_GLYPH);
+    }
+
+    #[test]
+    fn scaled_glyphs_are_anti_aliased() {
+        let font = Font8x8::new(4);
+        assert_eq!(font.height(), 32);
+        assert_eq!(font.text_width("Win"), 96);
+        let pixels = render(&font, 'A');
+        assert!(pixels.iter().any(|&c| c == 255));
+        assert!(pixels.iter().any(|&c| c > 0 && c < 255));
+        // the inside of the left stroke of the top row is solid
+        assert_eq!(pixels[2 * 32 + 12], 255);
+    }
+
+    #[test]
+    fn draw_text_blends_and_clips() {
+        let mut layer = Layer::new(MemoryFramebuffer::new());
+        let font = Font8x8::new(1);
+        let background = Color::rgb(0, 0, 255);
+        layer.fill_rect(crate::lcd::Rect::screen(), background);
+        let half_red = Color::rgba(255, 0, 0, 128);
+        let width = layer.draw_text(Point::new(WIDTH - 12, HEIGHT - 8), "AA", &font, half_red);
+        assert_eq!(width, 16);
+        let framebuffer = layer.framebuffer();
+        assert_eq!(framebuffer.pixel(WIDTH - 10, HEIGHT - 8), Color::rgb(128, 0, 127));
+        assert_eq!(framebuffer.pixel(WIDTH - 12, HEIGHT - 8), background);
     }
 }
@@ -0,0 +1,171 @@
+//! Points and rectangles in display coordinates.
+
+/// A point on the display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Point {
+    /// The horizontal coordinate, increasing to the right.
+    pub x: usize,
+    /// The vertical coordinate, increasing downwards.
+    pub y: usize,
+}
+
+impl Point {
+    /// Creates a new point.
+    pub const fn new(x: usize, y: usize) -> Point {
+        Point { x, y }
+    }
+}
+
+/// An axis-aligned rectangle on the display.
+///
+/// The rectangle includes the pixels from `x` to `x + width - 1` and from `y` to
+/// `y + height - 1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    /// The left edge.
+    pub x: usize,
+    /// The top edge.
+    pub y: usize,
+    /// The width in pixels.
+    pub width: usize,
+    /// The height in pixels.
+    pub height: usize,
+}
+
+impl Rect {
+    /// Creates a new rectangle with the top left corner at (`x`, `y`).
+    pub const fn new(x: usize, y: usize, width: usize, height: usize) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Creates the rectangle that spans from `top_left` to `bottom_right` (exclusive).
+    pub fn from_corners(top_left: Point, bottom_right: Point) -> Rect {
+        Rect::new(
+            top_left.x,
+            top_left.y,
+            bottom_right.x.saturating_sub(top_left.x),
+            bottom_right.y.saturating_sub(top_left.y),
+        )
+    }
+
+    /// The rectangle that covers the whole display.
+    pub const fn screen() -> Rect {
+        Rect::new(0, 0, super::WIDTH, super::HEIGHT)
+    }
+
+    /// The x coordinate right of the rectangle.
+    pub fn right(&self) -> usize {
+        self.x + self.width
+    }
+
+    /// The y coordinate below the rectangle.
+    pub fn bottom(&self) -> usize {
+        self.y + self.height
+    }
+
+    /// Returns `true` if the rectangle contains no pixels.
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Returns `true` if the point lies inside the rectangle.
+    pub fn contains(&self, point: Point) -> bool {
+        point.x >= self.x && point.x < self.right() && point.y >= self.y && point.y < self.bottom()
+    }
+
+    /// Returns `true` if the other rectangle lies completely inside this rectangle.
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom()
+    }
+
+    /// Returns the overlapping part of both rectangles, if any.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        if x < right && y < bottom {
+            Some(Rect::new(x, y, right - x, bottom - y))
+        } else {
+            None
+        }
+    }
+
+    /// Returns `true` if both rectangles overlap.
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    /// Returns the smallest rectangle that contains both rectangles.
+    pub fn union(&self, other: &Rect) -> Rect {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect::new(
+            x,
+            y,
+            self.right().max(other.right()) - x,
+            self.bottom().max(other.bottom()) - y,
+        )
+    }
+
+    /// Shrinks the rectangle by `amount` pixels on each side.
+    pub fn inset(&self, amount: usize) -> Rect {
+        let width = self.width.saturating_sub(2 * amount);
+        let height = self.height.saturating_sub(2 * amount);
+        Rect::new(self.x + amount, self.y + amount, width, height)
+    }
+
+    /// Splits the rectangle into a left part of `width` pixels and the remaining right part.
+    pub fn split_left(&self, width: usize) -> (Rect, Rect) {
+        let width = width.min(self.width);
+        (
+            Rect::new(self.x, self.y, width, self.height),
+            Rect::new(self.x + width, self.y, self.width - width, self.height),
+        )
+    }
+
+    /// Splits the rectangle into a top part of `height` pixels and the remaining bottom part.
+    pub fn split_top(&self, height: usize) -> (Rect, Rect) {
+        let height = height.min(self.height);
+        (
+            Rect::new(self.x, self.y, self.width, height),
+            Rect::new(self.x, self.y + height, self.width, self.height - height),
+        )
+    }
+
+    /// Returns `rows` rectangles of equal height stacked from top to bottom, separated by
+    /// `spacing` pixels.
+    pub fn rows(&self, rows: usize, spacing: usize) -> impl Iterator<Item = Rect> {
+        let total_spacing = spacing * rows.saturating_sub(1);
+        let height = self.height.saturating_sub(total_spacing) / rows.max(1);
+        let area = *self;
+        (0..rows).map(move |i| {
+            Rect::new(area.x, area.y + i * (height + spacing), area.width, height)
+        })
+    }
+
+    /// Returns `columns` rectangles of equal width placed from left to right, separated by
+    /// `spacing` pixels.
+    pub fn columns(&self, columns: usize, spacing: usize) -> impl Iterator<Item = Rect> {
+        let total_spacing = spacing * columns.saturating_sub(1);
+        let width = self.width.saturating_sub(total_spacing) / columns.max(1);
+        let area = *self;
+        (0..columns).map(move |i| {
+            Rect::new(area.x + i * (width + spacing), area.y, width, area.height)
+        })
+    }
+}
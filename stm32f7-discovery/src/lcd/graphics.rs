@@ -0,0 +1,82 @@
+//! Support for drawing to layers with the [`embedded-graphics`] crate.
+//!
+//! Layers implement [`Drawing`], so the shapes, fonts and images of `embedded-graphics` can be
+//! drawn to them directly. Layer 1 uses [`PixelColorU32`] as color type, whose value is written
+//! as ARGB8888 color. Layer 2 only stores an alpha value per pixel, so it uses [`PixelColorU8`],
+//! whose value is written as the alpha of the pixel. With a color lookup table
+//! ([`FramebufferAl88Clut`]), layer 2 uses ARGB8888 colors as well.
+//!
+//! This module is only available with the `embedded-graphics` feature. It targets the 0.4
+//! releases of `embedded-graphics`, because newer releases need a newer compiler than the pinned
+//! nightly. The 0.4 releases have no `DrawTarget` with `fill_solid` and `fill_contiguous`, so
+//! every shape reaches the layer pixel by pixel and is not filled with the DMA2D. Large filled
+//! rectangles should be drawn with [`Layer::fill_rect`] instead.
+//!
+//! [`embedded-graphics`]: https://docs.rs/embedded-graphics/0.4
+
+use super::{
+    Color, Framebuffer, FramebufferAl88, FramebufferAl88Clut, FramebufferArgb8888, Layer, Rect,
+    HEIGHT, WIDTH,
+};
+use embedded_graphics::drawable::Pixel;
+use embedded_graphics::pixelcolor::{PixelColor, PixelColorU32, PixelColorU8};
+use embedded_graphics::Drawing;
+
+/// A framebuffer that can be used as an `embedded-graphics` drawing target.
+pub trait GraphicsFramebuffer: Framebuffer {
+    /// The `embedded-graphics` color type of the framebuffer.
+    type Color: PixelColor + Clone;
+
+    /// Converts the `embedded-graphics` color to the color that is written to the framebuffer.
+    fn convert(color: Self::Color) -> Color;
+}
+
+impl GraphicsFramebuffer for FramebufferArgb8888 {
+    type Color = PixelColorU32;
+
+    fn convert(color: PixelColorU32) -> Color {
+        Color::from_argb8888(color.into_inner())
+    }
+}
+
+impl GraphicsFramebuffer for FramebufferAl88 {
+    type Color = PixelColorU8;
+
+    fn convert(color: PixelColorU8) -> Color {
+        Color::rgba(255, 255, 255, color.into_inner())
+    }
+}
+
+impl GraphicsFramebuffer for FramebufferAl88Clut {
+    type Color = PixelColorU32;
+
+    fn convert(color: PixelColorU32) -> Color {
+        Color::from_argb8888(color.into_inner())
+    }
+}
+
+impl<T: GraphicsFramebuffer> Drawing<T::Color> for Layer<T> {
+    fn draw<I>(&mut self, item_pixels: I)
+    where
+        I: Iterator<Item = Pixel<T::Color>>,
+    {
+        // the drawn pixels are marked dirty as one bounding rectangle after the loop
+        let mut bounds: Option<(usize, usize, usize, usize)> = None;
+        for Pixel(coord, color) in item_pixels {
+            let (x, y) = (coord[0] as usize, coord[1] as usize);
+            if x < WIDTH && y < HEIGHT {
+                self.framebuffer.set_pixel(x, y, T::convert(color));
+                bounds = Some(match bounds {
+                    Some((left, top, right, bottom)) => {
+                        (left.min(x), top.min(y), right.max(x), bottom.max(y))
+                    }
+                    None => (x, y, x, y),
+                });
+            }
+        }
+        if let Some((left, top, right, bottom)) = bounds {
+            self.dirty
+                .add(Rect::new(left, top, right - left + 1, bottom - top + 1));
+        }
+    }
+}
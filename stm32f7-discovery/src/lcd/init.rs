@@ -3,6 +3,9 @@ use stm32f7::stm32f7x6::{LTDC, RCC};
 
 /// Initializes the LCD controller.
 ///
+/// Both layers are set up full screen, layer 1 in the ARGB8888 and layer 2 in the AL88 format.
+/// [`Lcd::configure_layer`] changes this configuration.
+///
 /// The SDRAM must be initialized before this function is called. See the
 /// [`init_sdram`] function for more information.
 ///
@@ -0,0 +1,56 @@
+//! A framebuffer in main memory.
+
+use super::{Color, Framebuffer, Rect, HEIGHT, WIDTH};
+use alloc::vec::Vec;
+
+/// A framebuffer that stores the colors of all pixels in a vector.
+///
+/// It has the size of the display and is useful for rendering off-screen and for testing
+/// drawing code without the hardware.
+pub struct MemoryFramebuffer {
+    pixels: Vec<Color>,
+}
+
+impl MemoryFramebuffer {
+    /// Creates a framebuffer with all pixels set to transparent black.
+    pub fn new() -> MemoryFramebuffer {
+        MemoryFramebuffer {
+            pixels: vec![Color::from_argb8888(0); WIDTH * HEIGHT],
+        }
+    }
+
+    /// Returns the color of the pixel at the specified coordinates.
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * WIDTH + x]
+    }
+
+    /// Returns all pixels, row by row.
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+}
+
+impl Default for MemoryFramebuffer {
+    fn default() -> MemoryFramebuffer {
+        MemoryFramebuffer::new()
+    }
+}
+
+impl Framebuffer for MemoryFramebuffer {
+    fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+        self.pixels[y * WIDTH + x] = color;
+    }
+
+    fn read_pixel(&self, x: usize, y: usize) -> Option<Color> {
+        Some(self.pixel(x, y))
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        for y in rect.y..rect.bottom() {
+            let row = y * WIDTH;
+            for pixel in &mut self.pixels[row + rect.x..row + rect.right()] {
+                *pixel = color;
+            }
+        }
+    }
+}
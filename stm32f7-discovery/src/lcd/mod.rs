@@ -4,13 +4,47 @@
 //! with an uniform color.
 
 pub use self::color::Color;
+pub use self::config::{
+    BlendingFactor, ConfiguredFramebuffer, LayerConfig, LayerError, LayerHandle, PixelFormat,
+};
+pub use self::dirty::DirtyRegions;
+pub use self::double_buffer::{handle_line_interrupt, DoubleBuffer, LtdcLayer};
+pub use self::draw::Image;
+pub use self::geometry::{Point, Rect};
+#[cfg(feature = "embedded-graphics")]
+pub use self::graphics::GraphicsFramebuffer;
 pub use self::init::init;
-
-use core::{fmt, ptr};
+pub use self::memory::MemoryFramebuffer;
+pub use self::screenshot::{DisplayState, LayerState, Screenshot};
+pub use self::stdout::init as init_stdout;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::{fmt, ptr, slice};
+use spin::Mutex;
 use stm32f7::stm32f7x6::LTDC;
 
+pub mod bmp;
 mod color;
+mod config;
+pub mod console;
+mod dirty;
+pub mod dma2d;
+mod double_buffer;
+mod draw;
+pub mod font;
+mod geometry;
+#[cfg(feature = "embedded-graphics")]
+mod graphics;
 mod init;
+mod memory;
+pub mod qoi;
+pub mod screenshot;
+pub mod sprite;
+#[macro_use]
+pub mod stdout;
+pub mod text;
+pub mod ui;
 
 /// The height of the display in pixels.
 pub const HEIGHT: usize = 272;
@@ -26,18 +60,32 @@ pub const LAYER_2_OCTETS_PER_PIXEL: usize = 2;
 /// The length of the layer 1 buffer in bytes.
 pub const LAYER_2_LENGTH: usize = HEIGHT * WIDTH * LAYER_2_OCTETS_PER_PIXEL;
 
+/// The number of entries of the color lookup table of a layer.
+pub const CLUT_SIZE: usize = 256;
+
 /// Start address of the SDRAM where the framebuffers live.
 pub const SDRAM_START: usize = 0xC000_0000;
 /// Start address of the layer 1 framebuffer.
 pub const LAYER_1_START: usize = SDRAM_START;
 /// Start address of the layer 2 framebuffer.
 pub const LAYER_2_START: usize = SDRAM_START + LAYER_1_LENGTH;
+/// Start address of the layer 1 back buffer, which is used by [`Lcd::layer_1_double_buffered`].
+pub const LAYER_1_BACK_START: usize = LAYER_2_START + LAYER_2_LENGTH;
+/// Start address of the layer 2 back buffer, which is used by [`Lcd::layer_2_double_buffered`].
+pub const LAYER_2_BACK_START: usize = LAYER_1_BACK_START + LAYER_1_LENGTH;
+/// Start address of the buffer that is returned by [`Lcd::screenshot`].
+pub const SCREENSHOT_START: usize = LAYER_2_BACK_START + LAYER_2_LENGTH;
+
+/// The palettes that were written to the color lookup tables of the layers, because the tables
+/// can't be read back. Used for screenshots.
+static CLUTS: Mutex<[Option<[Color; CLUT_SIZE]>; 2]> = Mutex::new([None, None]);
 
 /// Represents the LCD and provides methods to access both layers.
 pub struct Lcd<'a> {
     controller: &'a mut LTDC,
     layer_1_in_use: bool,
     layer_2_in_use: bool,
+    screenshot_in_use: bool,
 }
 
 impl<'a> Lcd<'a> {
@@ -46,6 +94,7 @@ impl<'a> Lcd<'a> {
             controller: ltdc,
             layer_1_in_use: false,
             layer_2_in_use: false,
+            screenshot_in_use: false,
         }
     }
 
@@ -57,32 +106,261 @@ impl<'a> Lcd<'a> {
     }
 
     /// Returns a reference to layer 1.
+    ///
+    /// Returns `None` if layer 1 was already handed out.
     pub fn layer_1(&mut self) -> Option<Layer<FramebufferArgb8888>> {
-        if self.layer_1_in_use {
-            None
+        if self.claim(LtdcLayer::Layer1) {
+            Some(Layer::new(FramebufferArgb8888::new(LAYER_1_START)))
         } else {
-            Some(Layer {
-                framebuffer: FramebufferArgb8888::new(LAYER_1_START),
-            })
+            None
         }
     }
 
     /// Returns a reference to layer 2.
+    ///
+    /// Returns `None` if layer 2 was already handed out.
     pub fn layer_2(&mut self) -> Option<Layer<FramebufferAl88>> {
-        if self.layer_2_in_use {
+        if self.claim(LtdcLayer::Layer2) {
+            Some(Layer::new(FramebufferAl88::new(LAYER_2_START)))
+        } else {
+            None
+        }
+    }
+
+    /// Returns layer 1 with a back buffer at [`LAYER_1_BACK_START`].
+    pub fn layer_1_double_buffered(&mut self) -> Option<DoubleBuffer<FramebufferArgb8888>> {
+        if self.claim(LtdcLayer::Layer1) {
+            let front = FramebufferArgb8888::new(LAYER_1_START);
+            Some(DoubleBuffer::new(front, LAYER_1_BACK_START))
+        } else {
+            None
+        }
+    }
+
+    /// Returns layer 2 with a back buffer at [`LAYER_2_BACK_START`].
+    pub fn layer_2_double_buffered(&mut self) -> Option<DoubleBuffer<FramebufferAl88>> {
+        if self.claim(LtdcLayer::Layer2) {
+            let front = FramebufferAl88::new(LAYER_2_START);
+            Some(DoubleBuffer::new(front, LAYER_2_BACK_START))
+        } else {
+            None
+        }
+    }
+
+    /// Returns layer 2 with its color lookup table loaded with `palette`.
+    ///
+    /// Colors drawn to the layer are mapped to the nearest palette color, see
+    /// [`FramebufferAl88Clut`].
+    pub fn layer_2_clut(
+        &mut self,
+        palette: &'static [Color],
+    ) -> Option<Layer<FramebufferAl88Clut>> {
+        if self.claim(LtdcLayer::Layer2) {
+            self.set_clut(LtdcLayer::Layer2, palette);
+            Some(Layer::new(FramebufferAl88Clut::new(LAYER_2_START, palette)))
+        } else {
             None
+        }
+    }
+
+    /// Returns layer 2 with a back buffer at [`LAYER_2_BACK_START`] and its color lookup table
+    /// loaded with `palette`.
+    pub fn layer_2_clut_double_buffered(
+        &mut self,
+        palette: &'static [Color],
+    ) -> Option<DoubleBuffer<FramebufferAl88Clut>> {
+        if self.claim(LtdcLayer::Layer2) {
+            self.set_clut(LtdcLayer::Layer2, palette);
+            let front = FramebufferAl88Clut::new(LAYER_2_START, palette);
+            Some(DoubleBuffer::new(front, LAYER_2_BACK_START))
         } else {
-            Some(Layer {
-                framebuffer: FramebufferAl88::new(LAYER_2_START),
-            })
+            None
         }
     }
+
+    /// Configures the pixel format, window and blending of a layer and returns its handle.
+    ///
+    /// The framebuffer starts at [`LAYER_1_START`] or [`LAYER_2_START`] and must fit into
+    /// [`LAYER_1_LENGTH`] or [`LAYER_2_LENGTH`] bytes. Fails if the layer was already handed
+    /// out, by this method or by one of the `layer_*` methods.
+    pub fn configure_layer(
+        &mut self,
+        layer: LtdcLayer,
+        config: LayerConfig,
+    ) -> Result<LayerHandle, LayerError> {
+        if self.is_in_use(layer) {
+            return Err(LayerError::InUse);
+        }
+        let handle = config::configure(self.controller, layer, config)?;
+        self.claim(layer);
+        Ok(handle)
+    }
+
+    fn is_in_use(&self, layer: LtdcLayer) -> bool {
+        match layer {
+            LtdcLayer::Layer1 => self.layer_1_in_use,
+            LtdcLayer::Layer2 => self.layer_2_in_use,
+        }
+    }
+
+    /// Marks the layer as handed out, returns `false` if it already was.
+    fn claim(&mut self, layer: LtdcLayer) -> bool {
+        let in_use = match layer {
+            LtdcLayer::Layer1 => &mut self.layer_1_in_use,
+            LtdcLayer::Layer2 => &mut self.layer_2_in_use,
+        };
+        !core::mem::replace(in_use, true)
+    }
+
+    /// Loads `palette` into the color lookup table (CLUT) of the layer and enables the table.
+    ///
+    /// With an enabled CLUT, the 8 bit luminance values of the L8, AL44 and AL88 formats are
+    /// indices into the palette. The alpha values of the palette colors are ignored. The table
+    /// can only be written while the layer is disabled, so the layer is hidden for one frame.
+    ///
+    /// Panics if the palette has more than [`CLUT_SIZE`] entries.
+    pub fn set_clut(&mut self, layer: LtdcLayer, palette: &[Color]) {
+        write_clut(self.controller, layer, palette);
+    }
+
+    /// Returns the state of the background and both layers, see [`DisplayState::current`].
+    pub fn state(&self) -> DisplayState {
+        DisplayState::current()
+    }
+
+    /// Returns a screenshot buffer in the SDRAM at [`SCREENSHOT_START`].
+    ///
+    /// Returns `None` if the buffer was already handed out.
+    pub fn screenshot(&mut self) -> Option<Screenshot<'static>> {
+        if core::mem::replace(&mut self.screenshot_in_use, true) {
+            return None;
+        }
+        let buffer = unsafe {
+            slice::from_raw_parts_mut(SCREENSHOT_START as *mut u8, screenshot::BUFFER_LENGTH)
+        };
+        Some(Screenshot::new(buffer))
+    }
+
+    /// Triggers the LTDC line interrupt at the start of each vertical blanking period.
+    ///
+    /// [`handle_line_interrupt`] must be registered for the `LTDC` interrupt before, otherwise
+    /// [`DoubleBuffer::present`] waits forever.
+    pub fn enable_line_interrupt(&mut self) {
+        self.controller
+            .lipcr
+            .modify(|_, w| unsafe { w.lipos().bits(double_buffer::BLANKING_LINE) });
+        self.controller.ier.modify(|_, w| w.lie().set_bit());
+        double_buffer::set_line_interrupt_enabled(true);
+    }
+
+    /// Disables the LTDC line interrupt, page flips are then synchronized by polling.
+    pub fn disable_line_interrupt(&mut self) {
+        self.controller.ier.modify(|_, w| w.lie().clear_bit());
+        double_buffer::set_line_interrupt_enabled(false);
+    }
+}
+
+/// Writes the color lookup table of the layer, see [`Lcd::set_clut`].
+fn write_clut(ltdc: &LTDC, layer: LtdcLayer, palette: &[Color]) {
+    assert!(palette.len() <= CLUT_SIZE, "palette too large");
+    match layer {
+        LtdcLayer::Layer1 => ltdc.l1cr.modify(|_, w| w.len().clear_bit()),
+        LtdcLayer::Layer2 => ltdc.l2cr.modify(|_, w| w.len().clear_bit()),
+    }
+    ltdc.srcr.write(|w| w.imr().set_bit());
+
+    let mut clut = [Color::rgb(0, 0, 0); CLUT_SIZE];
+    clut[..palette.len()].copy_from_slice(palette);
+    CLUTS.lock()[layer_index(layer)] = Some(clut);
+    for (index, color) in palette.iter().enumerate() {
+        match layer {
+            LtdcLayer::Layer1 => ltdc.l1clutwr.write(|w| unsafe {
+                w.clutadd().bits(index as u8);
+                w.red().bits(color.red);
+                w.green().bits(color.green);
+                w.blue().bits(color.blue)
+            }),
+            LtdcLayer::Layer2 => ltdc.l2clutwr.write(|w| unsafe {
+                w.clutadd().bits(index as u8);
+                w.red().bits(color.red);
+                w.green().bits(color.green);
+                w.blue().bits(color.blue)
+            }),
+        }
+    }
+
+    match layer {
+        LtdcLayer::Layer1 => ltdc.l1cr.modify(|_, w| w.cluten().set_bit().len().set_bit()),
+        LtdcLayer::Layer2 => ltdc.l2cr.modify(|_, w| w.cluten().set_bit().len().set_bit()),
+    }
+    ltdc.srcr.write(|w| w.imr().set_bit());
+}
+
+/// Returns the palette that was written to the color lookup table of the layer.
+fn clut(layer: LtdcLayer) -> Option<Vec<Color>> {
+    CLUTS.lock()[layer_index(layer)]
+        .as_ref()
+        .map(|clut| clut.to_vec())
+}
+
+fn layer_index(layer: LtdcLayer) -> usize {
+    match layer {
+        LtdcLayer::Layer1 => 0,
+        LtdcLayer::Layer2 => 1,
+    }
 }
 
 /// Represents a buffer of pixels.
 pub trait Framebuffer {
     /// Set the pixel at the specified coordinates to the specified color.
     fn set_pixel(&mut self, x: usize, y: usize, color: Color);
+
+    /// Returns the color of the pixel at the specified coordinates.
+    ///
+    /// Framebuffers that can't be read return `None`, drawing code then assumes a transparent
+    /// pixel.
+    fn read_pixel(&self, _x: usize, _y: usize) -> Option<Color> {
+        None
+    }
+
+    /// Fills the rectangle, which lies completely inside the display, with the passed color.
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        for y in rect.y..rect.bottom() {
+            for x in rect.x..rect.right() {
+                self.set_pixel(x, y, color);
+            }
+        }
+    }
+
+    /// Copies ARGB8888 pixels to the rectangle, which lies completely inside the display.
+    ///
+    /// `pixels` contains the rows of the source image, each `stride` pixels long.
+    fn copy_rect(&mut self, rect: Rect, pixels: &[u32], stride: usize) {
+        for y in 0..rect.height {
+            let row = &pixels[y * stride..y * stride + rect.width];
+            for (x, argb) in row.iter().enumerate() {
+                self.set_pixel(rect.x + x, rect.y + y, Color::from_argb8888(*argb));
+            }
+        }
+    }
+}
+
+impl<F: Framebuffer + ?Sized> Framebuffer for Box<F> {
+    fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+        (**self).set_pixel(x, y, color)
+    }
+
+    fn read_pixel(&self, x: usize, y: usize) -> Option<Color> {
+        (**self).read_pixel(x, y)
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        (**self).fill_rect(rect, color)
+    }
+
+    fn copy_rect(&mut self, rect: Rect, pixels: &[u32], stride: usize) {
+        (**self).copy_rect(rect, pixels, stride)
+    }
 }
 
 /// A framebuffer in the ARGB8888 format.
@@ -98,12 +376,48 @@ impl FramebufferArgb8888 {
     }
 }
 
+impl FramebufferArgb8888 {
+    fn row_ptr(&self, x: usize, y: usize) -> *mut u32 {
+        (self.base_addr + (y * WIDTH + x) * LAYER_1_OCTETS_PER_PIXEL) as *mut u32
+    }
+}
+
 impl Framebuffer for FramebufferArgb8888 {
     fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
         let pixel = y * WIDTH + x;
         let pixel_ptr = (self.base_addr + pixel * LAYER_1_OCTETS_PER_PIXEL) as *mut u32;
         unsafe { ptr::write_volatile(pixel_ptr, color.to_argb8888()) };
     }
+
+    fn read_pixel(&self, x: usize, y: usize) -> Option<Color> {
+        let argb = unsafe { ptr::read_volatile(self.row_ptr(x, y)) };
+        Some(Color::from_argb8888(argb))
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        let argb = color.to_argb8888();
+        if dma2d::fill_argb8888(self.base_addr, rect, argb) {
+            return;
+        }
+        for y in rect.y..rect.bottom() {
+            let row = self.row_ptr(rect.x, y);
+            for x in 0..rect.width {
+                unsafe { ptr::write_volatile(row.add(x), argb) };
+            }
+        }
+    }
+
+    fn copy_rect(&mut self, rect: Rect, pixels: &[u32], stride: usize) {
+        if dma2d::copy_argb8888(self.base_addr, rect, pixels, stride) {
+            return;
+        }
+        for y in 0..rect.height {
+            let row = self.row_ptr(rect.x, rect.y + y);
+            for (x, argb) in pixels[y * stride..y * stride + rect.width].iter().enumerate() {
+                unsafe { ptr::write_volatile(row.add(x), *argb) };
+            }
+        }
+    }
 }
 
 /// A framebuffer in the AL88 format.
@@ -126,11 +440,119 @@ impl Framebuffer for FramebufferAl88 {
         let pixel_ptr = (self.base_addr + pixel * LAYER_2_OCTETS_PER_PIXEL) as *mut u16;
         unsafe { ptr::write_volatile(pixel_ptr, (color.alpha as u16) << 8 | 0xff) };
     }
+
+    fn read_pixel(&self, x: usize, y: usize) -> Option<Color> {
+        let pixel = y * WIDTH + x;
+        let pixel_ptr = (self.base_addr + pixel * LAYER_2_OCTETS_PER_PIXEL) as *const u16;
+        let value = unsafe { ptr::read_volatile(pixel_ptr) };
+        Some(Color::rgba(255, 255, 255, (value >> 8) as u8))
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        let value = (color.alpha as u16) << 8 | 0xff;
+        for y in rect.y..rect.bottom() {
+            let row = (self.base_addr + (y * WIDTH + rect.x) * LAYER_2_OCTETS_PER_PIXEL) as *mut u16;
+            for x in 0..rect.width {
+                unsafe { ptr::write_volatile(row.add(x), value) };
+            }
+        }
+    }
+}
+
+/// A framebuffer in the AL88 format with a color lookup table.
+///
+/// The luminance byte of every pixel is an index into the palette that was loaded with
+/// [`Lcd::set_clut`]. Drawn colors are replaced by the nearest palette color, their alpha value
+/// is kept.
+pub struct FramebufferAl88Clut {
+    base_addr: usize,
+    palette: &'static [Color],
+}
+
+impl FramebufferAl88Clut {
+    fn new(base_addr: usize, palette: &'static [Color]) -> Self {
+        Self { base_addr, palette }
+    }
+
+    fn value(&self, color: Color) -> u16 {
+        (color.alpha as u16) << 8 | color.nearest_index(self.palette) as u16
+    }
+
+    fn pixel_ptr(&self, x: usize, y: usize) -> *mut u16 {
+        (self.base_addr + (y * WIDTH + x) * LAYER_2_OCTETS_PER_PIXEL) as *mut u16
+    }
+}
+
+impl Framebuffer for FramebufferAl88Clut {
+    fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+        let value = self.value(color);
+        unsafe { ptr::write_volatile(self.pixel_ptr(x, y), value) };
+    }
+
+    fn read_pixel(&self, x: usize, y: usize) -> Option<Color> {
+        let value = unsafe { ptr::read_volatile(self.pixel_ptr(x, y)) };
+        let color = self
+            .palette
+            .get((value & 0xff) as usize)
+            .cloned()
+            .unwrap_or(Color::rgb(0, 0, 0));
+        Some(Color {
+            alpha: (value >> 8) as u8,
+            ..color
+        })
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        let value = self.value(color);
+        for y in rect.y..rect.bottom() {
+            let row = self.pixel_ptr(rect.x, y);
+            for x in 0..rect.width {
+                unsafe { ptr::write_volatile(row.add(x), value) };
+            }
+        }
+    }
 }
 
 /// Represents a layer of the LCD controller.
+///
+/// The layer keeps track of the regions that were drawn to, see [`Layer::dirty_regions`].
 pub struct Layer<T> {
     framebuffer: T,
+    dirty: DirtyRegions,
+}
+
+impl<T> Layer<T> {
+    /// Creates a layer that draws to the passed framebuffer.
+    ///
+    /// The layers of the display are returned by [`Lcd::layer_1`] and [`Lcd::layer_2`], this
+    /// function is useful for drawing to a [`MemoryFramebuffer`].
+    pub fn new(framebuffer: T) -> Layer<T> {
+        Layer {
+            framebuffer,
+            dirty: DirtyRegions::new(),
+        }
+    }
+
+    /// Returns the framebuffer of the layer.
+    pub fn framebuffer(&self) -> &T {
+        &self.framebuffer
+    }
+
+    /// Returns the regions that were drawn to since the last call of
+    /// [`take_dirty_regions`](Layer::take_dirty_regions).
+    pub fn dirty_regions(&self) -> &DirtyRegions {
+        &self.dirty
+    }
+
+    /// Returns the regions that were drawn to and starts tracking anew.
+    pub fn take_dirty_regions(&mut self) -> DirtyRegions {
+        core::mem::replace(&mut self.dirty, DirtyRegions::new())
+    }
+
+    /// Marks the rectangle as changed, for drawing that bypasses the methods of the layer.
+    pub fn mark_dirty(&mut self, rect: Rect) {
+        self.dirty.add(rect);
+    }
 }
 
 impl<T: Framebuffer> Layer<T> {
@@ -143,6 +565,7 @@ impl<T: Framebuffer> Layer<T> {
         ];
 
         // horizontal stripes
+        self.dirty.add(Rect::screen());
         for i in 0..HEIGHT {
             for j in 0..WIDTH {
                 self.framebuffer.set_pixel(
@@ -163,6 +586,7 @@ impl<T: Framebuffer> Layer<T> {
         ];
 
         // vertical stripes
+        self.dirty.add(Rect::screen());
         for i in 0..HEIGHT {
             for j in 0..WIDTH {
                 self.framebuffer.set_pixel(
@@ -178,11 +602,9 @@ impl<T: Framebuffer> Layer<T> {
     ///
     /// This method sets each pixel to transparent or black, depending on the framebuffer format.
     pub fn clear(&mut self) {
-        for i in 0..HEIGHT {
-            for j in 0..WIDTH {
-                self.framebuffer.set_pixel(j, i, Color::from_argb8888(0));
-            }
-        }
+        self.dirty.add(Rect::screen());
+        self.framebuffer
+            .fill_rect(Rect::screen(), Color::from_argb8888(0));
     }
 
     /// Sets the pixel at the specified coordinates to white.
@@ -195,6 +617,7 @@ impl<T: Framebuffer> Layer<T> {
         assert!(x < WIDTH);
         assert!(y < HEIGHT);
 
+        self.dirty.add(Rect::new(x, y, 1, 1));
         self.framebuffer.set_pixel(x, y, color);
     }
 
@@ -300,40 +723,33 @@ impl<'a, T: Framebuffer> TextWriter<'a, T> {
         self.x_pos = 0;
         if self.y_pos >= HEIGHT {
             self.y_pos = 0;
-            self.layer.clear();
         }
+        // only the line that is written next is cleared, the rest of the layer is kept
+        let line = Rect::new(0, self.y_pos, WIDTH, 8);
+        self.layer.fill_rect(line, Color::rgba(0, 0, 0, 0));
     }
 }
 
 impl<'a, T: Framebuffer> fmt::Write for TextWriter<'a, T> {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        use font8x8::{self, UnicodeFonts};
-
         for c in s.chars() {
             if c == '\n' {
                 self.newline();
                 continue;
             }
-            match c {
-                ' '..='~' => {
-                    let rendered = font8x8::BASIC_FONTS
-                        .get(c)
-                        .expect("character not found in basic font");
-                    for (y, byte) in rendered.iter().enumerate() {
-                        for (x, bit) in (0..8).enumerate() {
-                            let alpha = if *byte & (1 << bit) == 0 { 0 } else { 255 };
-                            let color = Color {
-                                red: 255,
-                                green: 255,
-                                blue: 255,
-                                alpha,
-                            };
-                            self.layer
-                                .print_point_color_at(self.x_pos + x, self.y_pos + y, color);
-                        }
-                    }
+            let rendered = font::glyph(c);
+            for (y, byte) in rendered.iter().enumerate() {
+                for (x, bit) in (0..8).enumerate() {
+                    let alpha = if *byte & (1 << bit) == 0 { 0 } else { 255 };
+                    let color = Color {
+                        red: 255,
+                        green: 255,
+                        blue: 255,
+                        alpha,
+                    };
+                    self.layer
+                        .print_point_color_at(self.x_pos + x, self.y_pos + y, color);
                 }
-                _ => panic!("unprintable character"),
             }
             self.x_pos += 8;
             if self.x_pos >= WIDTH {
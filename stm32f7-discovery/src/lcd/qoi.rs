@@ -0,0 +1,318 @@
+//! Streaming decoder for images in the [QOI format].
+//!
+//! QOI compresses images losslessly with runs, a small color cache and differences to the
+//! previous pixel. The pixels are decoded one after another, so an image can be drawn straight
+//! into a framebuffer without decoding it into an intermediate buffer first.
+//!
+//! [QOI format]: https://qoiformat.org/qoi-specification.pdf
+
+use super::{Color, Framebuffer, Layer, Point, Rect};
+use byteorder::{BigEndian, ByteOrder};
+
+const MAGIC: &[u8; 4] = b"qoif";
+const HEADER_SIZE: usize = 14;
+const END_MARKER: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+/// Images with a larger side are rejected, which also prevents overflows in size computations.
+const MAX_SIDE: usize = 1 << 15;
+
+const OP_INDEX: u8 = 0b00;
+const OP_DIFF: u8 = 0b01;
+const OP_LUMA: u8 = 0b10;
+const OP_RUN: u8 = 0b11;
+const OP_RGB: u8 = 0xfe;
+const OP_RGBA: u8 = 0xff;
+
+/// Errors that can happen while parsing a QOI image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The data is shorter than the header and the end marker.
+    Truncated,
+    /// The data does not start with `qoif`.
+    InvalidMagic,
+    /// The width or height is zero or too large.
+    InvalidDimensions,
+    /// The number of channels is neither 3 nor 4.
+    InvalidChannels(u8),
+    /// The data does not end with the end marker.
+    MissingEndMarker,
+}
+
+/// A QOI image, which borrows the encoded data.
+#[derive(Debug, Clone, Copy)]
+pub struct Qoi<'a> {
+    chunks: &'a [u8],
+    width: usize,
+    height: usize,
+}
+
+impl<'a> Qoi<'a> {
+    /// Parses the header of the image and checks the end marker.
+    ///
+    /// Errors in the compressed pixel data are only detected while decoding, see
+    /// [`pixels`](Qoi::pixels).
+    pub fn parse(data: &'a [u8]) -> Result<Qoi<'a>, Error> {
+        if data.len() < HEADER_SIZE + END_MARKER.len() {
+            return Err(Error::Truncated);
+        }
+        if &data[0..4] != MAGIC {
+            return Err(Error::InvalidMagic);
+        }
+        let width = BigEndian::read_u32(&data[4..8]) as usize;
+        let height = BigEndian::read_u32(&data[8..12]) as usize;
+        if width == 0 || height == 0 || width > MAX_SIDE || height > MAX_SIDE {
+            return Err(Error::InvalidDimensions);
+        }
+        let channels = data[12];
+        if channels != 3 && channels != 4 {
+            return Err(Error::InvalidChannels(channels));
+        }
+        let chunks_end = data.len() - END_MARKER.len();
+        if data[chunks_end..] != END_MARKER {
+            return Err(Error::MissingEndMarker);
+        }
+        Ok(Qoi {
+            chunks: &data[HEADER_SIZE..chunks_end],
+            width,
+            height,
+        })
+    }
+
+    /// Returns the width of the image in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the image in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns an iterator that decodes the pixels row by row.
+    ///
+    /// The iterator ends early if the compressed data is incomplete.
+    pub fn pixels(&self) -> Pixels<'a> {
+        Pixels {
+            chunks: self.chunks,
+            position: 0,
+            remaining: self.width * self.height,
+            previous: Color::rgba(0, 0, 0, 255),
+            index: [Color::rgba(0, 0, 0, 0); 64],
+            run: 0,
+        }
+    }
+}
+
+/// An iterator over the decoded pixels of a [`Qoi`] image.
+pub struct Pixels<'a> {
+    chunks: &'a [u8],
+    position: usize,
+    remaining: usize,
+    previous: Color,
+    index: [Color; 64],
+    run: usize,
+}
+
+impl<'a> Pixels<'a> {
+    fn next_byte(&mut self) -> Option<u8> {
+        let byte = self.chunks.get(self.position).cloned();
+        self.position += 1;
+        byte
+    }
+
+    fn decode_chunk(&mut self) -> Option<Color> {
+        let tag = self.next_byte()?;
+        let previous = self.previous;
+        let color = match tag {
+            OP_RGB => Color::rgba(
+                self.next_byte()?,
+                self.next_byte()?,
+                self.next_byte()?,
+                previous.alpha,
+            ),
+            OP_RGBA => Color::rgba(
+                self.next_byte()?,
+                self.next_byte()?,
+                self.next_byte()?,
+                self.next_byte()?,
+            ),
+            _ => match tag >> 6 {
+                OP_INDEX => self.index[(tag & 0x3f) as usize],
+                OP_DIFF => Color::rgba(
+                    previous.red.wrapping_add((tag >> 4) & 0x3).wrapping_sub(2),
+                    previous.green.wrapping_add((tag >> 2) & 0x3).wrapping_sub(2),
+                    previous.blue.wrapping_add(tag & 0x3).wrapping_sub(2),
+                    previous.alpha,
+                ),
+                OP_LUMA => {
+                    let byte = self.next_byte()?;
+                    let green = (tag & 0x3f).wrapping_sub(32);
+                    let red = green.wrapping_add(byte >> 4).wrapping_sub(8);
+                    let blue = green.wrapping_add(byte & 0xf).wrapping_sub(8);
+                    Color::rgba(
+                        previous.red.wrapping_add(red),
+                        previous.green.wrapping_add(green),
+                        previous.blue.wrapping_add(blue),
+                        previous.alpha,
+                    )
+                }
+                _ => {
+                    // OP_RUN: the run length is stored with a bias of -1, this pixel included
+                    self.run = (tag & 0x3f) as usize;
+                    previous
+                }
+            },
+        };
+        self.index[hash(color)] = color;
+        Some(color)
+    }
+}
+
+impl<'a> Iterator for Pixels<'a> {
+    type Item = Color;
+
+    fn next(&mut self) -> Option<Color> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let color = if self.run > 0 {
+            self.run -= 1;
+            self.previous
+        } else {
+            match self.decode_chunk() {
+                Some(color) => color,
+                None => {
+                    self.remaining = 0;
+                    return None;
+                }
+            }
+        };
+        self.previous = color;
+        self.remaining -= 1;
+        Some(color)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+fn hash(color: Color) -> usize {
+    (usize::from(color.red) * 3
+        + usize::from(color.green) * 5
+        + usize::from(color.blue) * 7
+        + usize::from(color.alpha) * 11)
+        % 64
+}
+
+impl<T: Framebuffer> Layer<T> {
+    /// Decodes the image directly to the layer, with its top left corner at `position`.
+    ///
+    /// The image is clipped to the display. Like [`blit`](Layer::blit), the alpha channel of
+    /// the image is copied and not blended.
+    pub fn draw_qoi(&mut self, position: Point, image: &Qoi) {
+        let target = Rect::new(position.x, position.y, image.width(), image.height());
+        let visible = match target.intersection(&Rect::screen()) {
+            Some(visible) => visible,
+            None => return,
+        };
+        self.dirty.add(visible);
+        let mut pixels = image.pixels();
+        for y in target.y..visible.bottom() {
+            for x in target.x..target.right() {
+                // pixels outside of the display have to be decoded anyway
+                let color = match pixels.next() {
+                    Some(color) => color,
+                    None => return,
+                };
+                if visible.contains(Point::new(x, y)) {
+                    self.framebuffer.set_pixel(x, y, color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, Qoi, END_MARKER};
+    use crate::lcd::{Color, Layer, MemoryFramebuffer, Point, HEIGHT, WIDTH};
+    use alloc::vec::Vec;
+
+    fn qoi(width: u32, height: u32, chunks: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"qoif");
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&[4, 0]);
+        data.extend_from_slice(chunks);
+        data.extend_from_slice(&END_MARKER);
+        data
+    }
+
+    #[test]
+    fn decodes_all_chunk_types() {
+        let chunks = [
+            0xfe, 100, 50, 200, // RGB
+            0b01_11_10_01, // DIFF: red +1, green +0, blue -1
+            0b10_100001, 0x9a, // LUMA: green +1, red +1 +1, blue +1 +2
+            0xc1, // RUN of two pixels
+            0xff, 1, 2, 3, 4, // RGBA
+            super::hash(Color::rgba(100, 50, 200, 255)) as u8, // INDEX
+        ];
+        let data = qoi(7, 1, &chunks);
+        let image = Qoi::parse(&data).unwrap();
+        let pixels: Vec<Color> = image.pixels().collect();
+        assert_eq!(
+            pixels,
+            [
+                Color::rgba(100, 50, 200, 255),
+                Color::rgba(101, 50, 199, 255),
+                Color::rgba(103, 51, 202, 255),
+                Color::rgba(103, 51, 202, 255),
+                Color::rgba(103, 51, 202, 255),
+                Color::rgba(1, 2, 3, 4),
+                Color::rgba(100, 50, 200, 255),
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_data_ends_early() {
+        // the second chunk misses its blue value
+        let data = qoi(4, 1, &[0xfe, 1, 2, 3, 0xfe, 1, 2]);
+        let image = Qoi::parse(&data).unwrap();
+        assert_eq!(image.pixels().count(), 1);
+    }
+
+    #[test]
+    fn invalid_headers_are_rejected() {
+        let data = qoi(1, 1, &[0xc0]);
+        assert!(Qoi::parse(&data).is_ok());
+        assert_eq!(Qoi::parse(&data[..10]).unwrap_err(), Error::Truncated);
+        assert_eq!(
+            Qoi::parse(&data[..data.len() - 1]).unwrap_err(),
+            Error::MissingEndMarker
+        );
+        let mut wrong = data.clone();
+        wrong[0] = b'x';
+        assert_eq!(Qoi::parse(&wrong).unwrap_err(), Error::InvalidMagic);
+        wrong = data.clone();
+        wrong[12] = 2;
+        assert_eq!(Qoi::parse(&wrong).unwrap_err(), Error::InvalidChannels(2));
+        let data = qoi(0, 1, &[]);
+        assert_eq!(Qoi::parse(&data).unwrap_err(), Error::InvalidDimensions);
+    }
+
+    #[test]
+    fn draw_qoi_is_clipped() {
+        // a 2x2 image with a red and a blue row
+        let data = qoi(2, 2, &[0xfe, 255, 0, 0, 0xc0, 0xfe, 0, 0, 255, 0xc0]);
+        let image = Qoi::parse(&data).unwrap();
+        let mut layer = Layer::new(MemoryFramebuffer::new());
+        layer.draw_qoi(Point::new(WIDTH - 1, HEIGHT - 2), &image);
+        let framebuffer = layer.framebuffer();
+        assert_eq!(framebuffer.pixel(WIDTH - 1, HEIGHT - 2), Color::rgb(255, 0, 0));
+        assert_eq!(framebuffer.pixel(WIDTH - 1, HEIGHT - 1), Color::rgb(0, 0, 255));
+        assert_eq!(framebuffer.pixel(WIDTH - 2, HEIGHT - 1), Color::from_argb8888(0));
+    }
+}
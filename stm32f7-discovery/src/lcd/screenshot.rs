@@ -0,0 +1,406 @@
+//! Screenshots of the composed display.
+//!
+//! [`DisplayState::current`] reads the background color and the configuration of both layers
+//! from the LTDC, including the addresses of the buffers that are currently shown.
+//! [`DisplayState::pixel`] blends the layers like the LTDC does, so the result is exactly what is
+//! on the screen. [`Screenshot::capture`] stores the composed image, which can then be written
+//! out slowly while the display continues to change.
+//!
+//! Screenshots are exported as uncompressed 24 bit BMP files. [`ScreenshotServer`] serves the
+//! file over TCP, e.g. `nc 192.168.42.1 5556 > screen.bmp`.
+
+use super::config::{self, BlendingFactor, PixelFormat};
+use super::{Color, LtdcLayer, Point, Rect, HEIGHT, WIDTH};
+use alloc::vec::Vec;
+use byteorder::{ByteOrder, LittleEndian};
+use smoltcp::socket::{SocketHandle, SocketSet, TcpSocket, TcpSocketBuffer};
+use stm32f7::stm32f7x6::LTDC;
+
+/// The default TCP port of the [`ScreenshotServer`].
+pub const SCREENSHOT_PORT: u16 = 5556;
+/// The number of bytes of the buffer of a [`Screenshot`].
+pub const BUFFER_LENGTH: usize = ROW_LENGTH * HEIGHT;
+
+const ROW_LENGTH: usize = WIDTH * 3;
+const FILE_HEADER_SIZE: usize = 14;
+const INFO_HEADER_SIZE: usize = 40;
+const HEADER_SIZE: usize = FILE_HEADER_SIZE + INFO_HEADER_SIZE;
+/// 96 DPI, which image viewers expect for screen content.
+const PIXELS_PER_METER: u32 = 3780;
+
+/// The state of a layer as the LTDC uses it, read from its registers.
+#[derive(Debug, Clone)]
+pub struct LayerState {
+    pub(super) enabled: bool,
+    pub(super) format: PixelFormat,
+    pub(super) base_addr: usize,
+    pub(super) pitch: usize,
+    pub(super) window: Rect,
+    pub(super) constant_alpha: u8,
+    pub(super) blending: (BlendingFactor, BlendingFactor),
+    pub(super) default_color: Color,
+    pub(super) palette: Option<Vec<Color>>,
+}
+
+impl LayerState {
+    /// Returns whether the layer is shown.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Returns the pixel format.
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Returns the address of the framebuffer that is shown.
+    ///
+    /// For double buffered layers, this is the address of the current front buffer.
+    pub fn base_addr(&self) -> usize {
+        self.base_addr
+    }
+
+    /// Returns the area of the display that the layer covers.
+    pub fn window(&self) -> Rect {
+        self.window
+    }
+
+    /// Returns the constant alpha value.
+    pub fn constant_alpha(&self) -> u8 {
+        self.constant_alpha
+    }
+
+    /// Returns the color of the layer at a position of the display, before blending.
+    ///
+    /// Outside of the window and for disabled layers, this is the default color of the layer,
+    /// which is transparent after [`init`](super::init).
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        let window = self.window;
+        if !self.enabled || !window.contains(Point::new(x, y)) {
+            return self.default_color;
+        }
+        let offset = (y - window.y) * self.pitch + (x - window.x) * self.format.bytes_per_pixel();
+        // the address and the window were read from the LTDC, which reads the same memory
+        let value = unsafe { config::read_value(self.base_addr + offset, self.format) };
+        self.format
+            .decode(value, self.palette.as_ref().map(|palette| &palette[..]))
+    }
+
+    /// Blends the pixel of the layer over the color of the layers below, like the LTDC.
+    pub fn blend(&self, x: usize, y: usize, below: Color) -> Color {
+        let color = self.pixel(x, y);
+        let constant_alpha = u32::from(self.constant_alpha);
+        let pixel_alpha = constant_alpha * u32::from(color.alpha) / 255;
+        let factor = |factor| match factor {
+            BlendingFactor::ConstantAlpha => constant_alpha,
+            BlendingFactor::PixelAlphaTimesConstantAlpha => pixel_alpha,
+        };
+        let (factor_1, factor_2) = (factor(self.blending.0), 255 - factor(self.blending.1));
+        let mix = |value: u8, below: u8| {
+            let mixed = (u32::from(value) * factor_1 + u32::from(below) * factor_2) / 255;
+            mixed.min(255) as u8
+        };
+        Color::rgb(
+            mix(color.red, below.red),
+            mix(color.green, below.green),
+            mix(color.blue, below.blue),
+        )
+    }
+}
+
+/// The background color and the state of both layers.
+#[derive(Debug, Clone)]
+pub struct DisplayState {
+    background: Color,
+    layers: [LayerState; 2],
+}
+
+impl DisplayState {
+    /// Reads the current state from the LTDC registers.
+    ///
+    /// The color lookup tables can't be read back, so the palettes that were last written with
+    /// [`Lcd::set_clut`](super::Lcd::set_clut) are used.
+    pub fn current() -> DisplayState {
+        // only reads the registers, which are written by the owner of the LTDC
+        let ltdc = unsafe { &*LTDC::ptr() };
+        let background = ltdc.bccr.read().bc().bits() & 0x00ff_ffff;
+        let layer = |layer| config::read_state(ltdc, layer, super::clut(layer));
+        DisplayState {
+            background: Color::from_rgb888(background),
+            layers: [layer(LtdcLayer::Layer1), layer(LtdcLayer::Layer2)],
+        }
+    }
+
+    /// Returns the background color.
+    pub fn background(&self) -> Color {
+        self.background
+    }
+
+    /// Returns the state of a layer.
+    pub fn layer(&self, layer: LtdcLayer) -> &LayerState {
+        match layer {
+            LtdcLayer::Layer1 => &self.layers[0],
+            LtdcLayer::Layer2 => &self.layers[1],
+        }
+    }
+
+    /// Returns the color of a pixel on the screen, with both layers blended over the background.
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        self.layers
+            .iter()
+            .fold(self.background, |below, layer| layer.blend(x, y, below))
+    }
+}
+
+/// A captured image of the display.
+///
+/// The pixels are stored in the layout of a bottom-up BMP file, so the file is produced without
+/// any conversion.
+pub struct Screenshot<'a> {
+    pixels: &'a mut [u8],
+}
+
+impl<'a> Screenshot<'a> {
+    /// Creates a screenshot that stores its pixels in `buffer`.
+    ///
+    /// Panics if the buffer is shorter than [`BUFFER_LENGTH`]. A screenshot with a buffer in the
+    /// SDRAM is returned by [`Lcd::screenshot`](super::Lcd::screenshot).
+    pub fn new(buffer: &'a mut [u8]) -> Screenshot<'a> {
+        assert!(buffer.len() >= BUFFER_LENGTH, "screenshot buffer too small");
+        Screenshot {
+            pixels: &mut buffer[..BUFFER_LENGTH],
+        }
+    }
+
+    /// Composes the display and replaces the stored image.
+    pub fn capture(&mut self, state: &DisplayState) {
+        for y in 0..HEIGHT {
+            let row = row_offset(y);
+            for x in 0..WIDTH {
+                let color = state.pixel(x, y);
+                let pixel = &mut self.pixels[row + 3 * x..row + 3 * x + 3];
+                pixel.copy_from_slice(&[color.blue, color.green, color.red]);
+            }
+        }
+    }
+
+    /// Returns the color of a pixel of the stored image.
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        let offset = row_offset(y) + 3 * x;
+        let pixel = &self.pixels[offset..offset + 3];
+        Color::rgb(pixel[2], pixel[1], pixel[0])
+    }
+
+    /// Returns the size of the BMP file in bytes.
+    pub fn bmp_len(&self) -> usize {
+        HEADER_SIZE + BUFFER_LENGTH
+    }
+
+    /// Copies the part of the BMP file that starts at `offset` into `buffer` and returns the
+    /// number of copied bytes, which is 0 at the end of the file.
+    ///
+    /// The file is never stored as a whole, it would not fit into the heap.
+    pub fn read_bmp(&self, offset: usize, buffer: &mut [u8]) -> usize {
+        let mut copied = 0;
+        if offset < HEADER_SIZE {
+            let header = bmp_header();
+            copied = (HEADER_SIZE - offset).min(buffer.len());
+            buffer[..copied].copy_from_slice(&header[offset..offset + copied]);
+        }
+        let start = (offset + copied)
+            .saturating_sub(HEADER_SIZE)
+            .min(BUFFER_LENGTH);
+        let count = (BUFFER_LENGTH - start).min(buffer.len() - copied);
+        buffer[copied..copied + count].copy_from_slice(&self.pixels[start..start + count]);
+        copied + count
+    }
+}
+
+/// Returns the offset of a row in the pixel data, the last row of the display comes first.
+fn row_offset(y: usize) -> usize {
+    (HEIGHT - 1 - y) * ROW_LENGTH
+}
+
+fn bmp_header() -> [u8; HEADER_SIZE] {
+    let mut header = [0; HEADER_SIZE];
+    header[0..2].copy_from_slice(b"BM");
+    LittleEndian::write_u32(&mut header[2..6], (HEADER_SIZE + BUFFER_LENGTH) as u32);
+    // bytes 6..10 are reserved
+    LittleEndian::write_u32(&mut header[10..14], HEADER_SIZE as u32);
+    let info = &mut header[FILE_HEADER_SIZE..];
+    LittleEndian::write_u32(&mut info[0..4], INFO_HEADER_SIZE as u32);
+    LittleEndian::write_u32(&mut info[4..8], WIDTH as u32);
+    // a positive height means that the rows are stored bottom-up
+    LittleEndian::write_u32(&mut info[8..12], HEIGHT as u32);
+    // one color plane with 24 bits per pixel, bytes 16..20 are 0 for no compression
+    LittleEndian::write_u16(&mut info[12..14], 1);
+    LittleEndian::write_u16(&mut info[14..16], 24);
+    LittleEndian::write_u32(&mut info[20..24], BUFFER_LENGTH as u32);
+    LittleEndian::write_u32(&mut info[24..28], PIXELS_PER_METER);
+    LittleEndian::write_u32(&mut info[28..32], PIXELS_PER_METER);
+    // bytes 32..40: no palette
+    header
+}
+
+/// A TCP server that sends a screenshot to every client that connects.
+pub struct ScreenshotServer {
+    handle: SocketHandle,
+    port: u16,
+    screenshot: Screenshot<'static>,
+    captured: bool,
+    sent: Option<usize>,
+}
+
+impl ScreenshotServer {
+    /// Adds a listening TCP socket on `port` to the socket set.
+    pub fn new(
+        sockets: &mut SocketSet<'_, '_, 'static>,
+        port: u16,
+        screenshot: Screenshot<'static>,
+    ) -> ScreenshotServer {
+        let rx_buffer = TcpSocketBuffer::new(vec![0; 64]);
+        let tx_buffer = TcpSocketBuffer::new(vec![0; crate::ethernet::MTU]);
+        let mut socket = TcpSocket::new(rx_buffer, tx_buffer);
+        socket.listen(port).unwrap();
+        ScreenshotServer {
+            handle: sockets.add(socket),
+            port,
+            screenshot,
+            captured: false,
+            sent: None,
+        }
+    }
+
+    /// Captures the display now and keeps the image for the next client.
+    ///
+    /// Without this call, the display is captured when a client connects.
+    pub fn capture(&mut self) {
+        self.screenshot.capture(&DisplayState::current());
+        self.captured = true;
+    }
+
+    /// Sends the next part of the BMP file if a client is connected.
+    ///
+    /// Should be called after each poll of the interface. The connection is closed after the
+    /// whole file was sent.
+    pub fn poll(&mut self, sockets: &mut SocketSet) {
+        let mut socket = sockets.get::<TcpSocket>(self.handle);
+        if !socket.is_open() {
+            self.sent = None;
+            socket.listen(self.port).unwrap();
+            return;
+        }
+        if !socket.may_send() {
+            if self.sent.is_some() {
+                // the client went away before the download finished
+                self.sent = None;
+                socket.close();
+            }
+            return;
+        }
+        let offset = match self.sent {
+            Some(sent) => sent,
+            None => {
+                if !self.captured {
+                    self.screenshot.capture(&DisplayState::current());
+                }
+                self.captured = false;
+                0
+            }
+        };
+        let screenshot = &self.screenshot;
+        let sent = socket
+            .send(|buffer| {
+                let copied = screenshot.read_bmp(offset, buffer);
+                (copied, copied)
+            })
+            .unwrap_or(0);
+        self.sent = Some(offset + sent);
+        if offset + sent >= screenshot.bmp_len() {
+            socket.close();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DisplayState, LayerState, Screenshot, BUFFER_LENGTH};
+    use crate::lcd::bmp::Bmp;
+    use crate::lcd::config::{BlendingFactor, PixelFormat};
+    use crate::lcd::{Color, Rect};
+    use alloc::vec::Vec;
+
+    fn layer(pixels: &[u32], window: Rect) -> LayerState {
+        LayerState {
+            enabled: true,
+            format: PixelFormat::Argb8888,
+            base_addr: pixels.as_ptr() as usize,
+            pitch: window.width * 4,
+            window,
+            constant_alpha: 255,
+            blending: (
+                BlendingFactor::PixelAlphaTimesConstantAlpha,
+                BlendingFactor::PixelAlphaTimesConstantAlpha,
+            ),
+            default_color: Color::rgba(0, 0, 0, 0),
+            palette: None,
+        }
+    }
+
+    #[test]
+    fn blends_layers_like_the_ltdc() {
+        let half_red = Color::rgba(255, 0, 0, 128).to_argb8888();
+        let layer_1_pixels = [half_red; 4];
+        let layer_2_pixels = [0x00ff_ffff, 0xff00_ff00];
+        let mut layer_2 = layer(&layer_2_pixels, Rect::new(1, 1, 2, 1));
+        layer_2.constant_alpha = 128;
+        let state = DisplayState {
+            background: Color::rgb(0, 0, 255),
+            layers: [layer(&layer_1_pixels, Rect::new(0, 0, 2, 2)), layer_2],
+        };
+
+        assert_eq!(state.pixel(0, 0), Color::rgb(128, 0, 127));
+        // the transparent pixel of layer 2 and the outside of both windows
+        assert_eq!(state.pixel(1, 1), Color::rgb(128, 0, 127));
+        assert_eq!(state.pixel(3, 3), Color::rgb(0, 0, 255));
+        // the constant alpha is multiplied with the opaque green pixel
+        assert_eq!(state.pixel(2, 1), Color::rgb(0, 128, 127));
+
+        let mut disabled = state.clone();
+        disabled.layers[0].enabled = false;
+        assert_eq!(disabled.pixel(0, 0), Color::rgb(0, 0, 255));
+    }
+
+    #[test]
+    fn bmp_file_can_be_read_in_parts() {
+        let pixels = [Color::rgb(10, 20, 30).to_argb8888(); 4];
+        let state = DisplayState {
+            background: Color::rgb(1, 2, 3),
+            layers: [
+                layer(&pixels, Rect::new(478, 0, 2, 2)),
+                layer(&pixels, Rect::new(0, 270, 2, 2)),
+            ],
+        };
+        let mut buffer = alloc::vec![0; BUFFER_LENGTH];
+        let mut screenshot = Screenshot::new(&mut buffer);
+        screenshot.capture(&state);
+
+        let mut file = Vec::new();
+        let mut chunk = [0; 1000];
+        loop {
+            let copied = screenshot.read_bmp(file.len(), &mut chunk);
+            if copied == 0 {
+                break;
+            }
+            file.extend_from_slice(&chunk[..copied]);
+        }
+        assert_eq!(file.len(), screenshot.bmp_len());
+
+        let bmp = Bmp::parse(&file).unwrap();
+        assert_eq!((bmp.width(), bmp.height()), (480, 272));
+        assert_eq!(bmp.pixel(479, 1), Color::rgb(10, 20, 30));
+        assert_eq!(bmp.pixel(0, 271), Color::rgb(10, 20, 30));
+        assert_eq!(bmp.pixel(100, 100), Color::rgb(1, 2, 3));
+        assert_eq!(screenshot.pixel(0, 271), Color::rgb(10, 20, 30));
+    }
+}
@@ -0,0 +1,391 @@
+//! Sprites and frame based animations.
+//!
+//! An [`Animation`] is a sequence of equally sized frames that are shown for a fixed time each.
+//! The frames are either stored as ARGB8888 images ([`Frames`]) or computed on the fly
+//! ([`Generated`]), which needs no memory for the pixels.
+//!
+//! An [`Animator`] plays animations on a layer. When a playback starts, it saves the pixels under
+//! the sprite, blends every frame over these pixels and restores them when the playback ended.
+//! So sprites can be shown on top of images without destroying them, and the other layer is not
+//! touched at all.
+//!
+//! The animator is driven by timestamps in milliseconds, usually from
+//! [`system_clock::ms`](crate::system_clock::ms). [`Animator::update`] never blocks, so it can be
+//! called in the loops that wait for input or for the network.
+//!
+//! ```
+//! let mut animator = Animator::new();
+//! let splash = animator.add(Generated::new(21, 21, 6, 80, |frame, x, y| ring(frame, x, y)));
+//! animator.play(splash, Point::new(52, 77), system_clock::ms());
+//! while animator.update(&mut layer_1, system_clock::ms()) {
+//!     // handle input
+//! }
+//! ```
+
+use super::{Color, Framebuffer, Image, Layer, Point, Rect};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// A sequence of frames of the same size.
+pub trait Animation {
+    /// Returns the width of the frames in pixels.
+    fn width(&self) -> usize;
+
+    /// Returns the height of the frames in pixels.
+    fn height(&self) -> usize;
+
+    /// Returns the number of frames.
+    fn frame_count(&self) -> usize;
+
+    /// Returns how long each frame is shown, in milliseconds.
+    fn frame_duration(&self) -> usize;
+
+    /// Returns the color of a pixel of a frame. Transparent pixels show the content below.
+    fn pixel(&self, frame: usize, x: usize, y: usize) -> Color;
+
+    /// Returns the length of the animation in milliseconds.
+    fn duration(&self) -> usize {
+        self.frame_count() * self.frame_duration()
+    }
+}
+
+/// An animation whose frames are stored as ARGB8888 images, one after another.
+#[derive(Debug, Clone, Copy)]
+pub struct Frames<'a> {
+    width: usize,
+    height: usize,
+    frame_duration: usize,
+    pixels: &'a [u32],
+}
+
+impl<'a> Frames<'a> {
+    /// Creates an animation from the pixels of all frames, row by row.
+    ///
+    /// The number of frames is derived from the length of `pixels`, which must be a multiple of
+    /// `width * height`.
+    pub fn new(width: usize, height: usize, frame_duration: usize, pixels: &'a [u32]) -> Self {
+        assert!(width * height > 0 && pixels.len() % (width * height) == 0);
+        Frames {
+            width,
+            height,
+            frame_duration,
+            pixels,
+        }
+    }
+
+    /// Returns a frame as image, e.g. for drawing it with [`Layer::draw_sprite`].
+    pub fn frame(&self, frame: usize) -> Image<'a> {
+        let size = self.width * self.height;
+        Image::new(
+            self.width,
+            self.height,
+            &self.pixels[frame * size..(frame + 1) * size],
+        )
+    }
+}
+
+impl<'a> Animation for Frames<'a> {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn frame_count(&self) -> usize {
+        self.pixels.len() / (self.width * self.height)
+    }
+
+    fn frame_duration(&self) -> usize {
+        self.frame_duration
+    }
+
+    fn pixel(&self, frame: usize, x: usize, y: usize) -> Color {
+        let size = self.width * self.height;
+        Color::from_argb8888(self.pixels[frame * size + y * self.width + x])
+    }
+}
+
+/// An animation whose pixels are computed by a function of the frame and the coordinates.
+pub struct Generated<F> {
+    width: usize,
+    height: usize,
+    frame_count: usize,
+    frame_duration: usize,
+    pixel: F,
+}
+
+impl<F: Fn(usize, usize, usize) -> Color> Generated<F> {
+    /// Creates an animation that calls `pixel(frame, x, y)` for every pixel of a frame.
+    pub fn new(
+        width: usize,
+        height: usize,
+        frame_count: usize,
+        frame_duration: usize,
+        pixel: F,
+    ) -> Self {
+        Generated {
+            width,
+            height,
+            frame_count,
+            frame_duration,
+            pixel,
+        }
+    }
+}
+
+impl<F: Fn(usize, usize, usize) -> Color> Animation for Generated<F> {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    fn frame_duration(&self) -> usize {
+        self.frame_duration
+    }
+
+    fn pixel(&self, frame: usize, x: usize, y: usize) -> Color {
+        (self.pixel)(frame, x, y)
+    }
+}
+
+/// Identifies an animation of an [`Animator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnimationId(usize);
+
+struct Playback {
+    animation: AnimationId,
+    position: Point,
+    start: usize,
+    shown: Option<usize>,
+    // the pixels under the visible part of the sprite, saved when the first frame is drawn
+    background: Vec<Color>,
+}
+
+/// Plays animations on a layer, see the module documentation.
+#[derive(Default)]
+pub struct Animator {
+    animations: Vec<Box<dyn Animation>>,
+    playing: Vec<Playback>,
+}
+
+impl Animator {
+    /// Creates an animator without animations.
+    pub fn new() -> Animator {
+        Animator::default()
+    }
+
+    /// Adds an animation and returns its id, which is used to play it.
+    pub fn add<A: Animation + 'static>(&mut self, animation: A) -> AnimationId {
+        self.animations.push(Box::new(animation));
+        AnimationId(self.animations.len() - 1)
+    }
+
+    /// Returns the animation with the passed id.
+    pub fn animation(&self, id: AnimationId) -> &dyn Animation {
+        &*self.animations[id.0]
+    }
+
+    /// Plays the animation with its top left corner at `position`, starting at the time `start`.
+    ///
+    /// The start may lie in the future, which allows to play animations one after another. The
+    /// same animation can be played several times at once. Playbacks that overlap must end in
+    /// the reverse order of their start, otherwise a restored background covers the other
+    /// sprite.
+    pub fn play(&mut self, id: AnimationId, position: Point, start: usize) {
+        self.playing.push(Playback {
+            animation: id,
+            position,
+            start,
+            shown: None,
+            background: Vec::new(),
+        });
+    }
+
+    /// Returns whether an animation is playing or waiting for its start.
+    pub fn is_running(&self) -> bool {
+        !self.playing.is_empty()
+    }
+
+    /// Draws the current frames of all playbacks that reached a new frame at time `now` and
+    /// removes the playbacks that ended.
+    ///
+    /// Returns whether animations are still running.
+    pub fn update<T: Framebuffer>(&mut self, layer: &mut Layer<T>, now: usize) -> bool {
+        let animations = &self.animations;
+        let mut i = 0;
+        while i < self.playing.len() {
+            let playback = &mut self.playing[i];
+            if now < playback.start {
+                i += 1;
+                continue;
+            }
+            let animation = &*animations[playback.animation.0];
+            let frame = (now - playback.start) / animation.frame_duration().max(1);
+            if frame >= animation.frame_count() {
+                playback.restore(animation, layer);
+                self.playing.remove(i);
+                continue;
+            }
+            if playback.shown != Some(frame) {
+                playback.draw(animation, frame, layer);
+            }
+            i += 1;
+        }
+        self.is_running()
+    }
+
+    /// Stops all playbacks and restores the content of the layer below the sprites.
+    pub fn stop_all<T: Framebuffer>(&mut self, layer: &mut Layer<T>) {
+        // restored in reverse order, so overlapping sprites leave the original content
+        for playback in self.playing.drain(..).rev() {
+            playback.restore(&*self.animations[playback.animation.0], layer);
+        }
+    }
+}
+
+impl Playback {
+    /// Returns the part of the sprite that is on the display.
+    fn area(&self, animation: &dyn Animation) -> Option<Rect> {
+        let (x, y) = (self.position.x, self.position.y);
+        Rect::new(x, y, animation.width(), animation.height()).intersection(&Rect::screen())
+    }
+
+    fn draw<T: Framebuffer>(
+        &mut self,
+        animation: &dyn Animation,
+        frame: usize,
+        layer: &mut Layer<T>,
+    ) {
+        let area = match self.area(animation) {
+            Some(area) => area,
+            None => return,
+        };
+        if self.shown.is_none() {
+            self.background = points(area)
+                .map(|p| {
+                    let background = layer.framebuffer.read_pixel(p.x, p.y);
+                    background.unwrap_or(Color::rgba(0, 0, 0, 0))
+                })
+                .collect();
+        }
+        for (point, background) in points(area).zip(self.background.iter()) {
+            let (x, y) = (point.x - self.position.x, point.y - self.position.y);
+            let color = animation.pixel(frame, x, y).blend_over(*background);
+            layer.framebuffer.set_pixel(point.x, point.y, color);
+        }
+        layer.mark_dirty(area);
+        self.shown = Some(frame);
+    }
+
+    fn restore<T: Framebuffer>(&self, animation: &dyn Animation, layer: &mut Layer<T>) {
+        let area = match self.area(animation) {
+            Some(area) if self.shown.is_some() => area,
+            _ => return,
+        };
+        for (point, background) in points(area).zip(self.background.iter()) {
+            layer.framebuffer.set_pixel(point.x, point.y, *background);
+        }
+        layer.mark_dirty(area);
+    }
+}
+
+/// Returns the points of the rectangle, row by row.
+fn points(area: Rect) -> impl Iterator<Item = Point> {
+    (area.y..area.bottom()).flat_map(move |y| (area.x..area.right()).map(move |x| Point::new(x, y)))
+}
+
+impl<T: Framebuffer> Layer<T> {
+    /// Blends the image over the content of the layer, with its top left corner at `position`.
+    ///
+    /// Unlike [`blit`](Layer::blit), transparent pixels show the content below.
+    /// Framebuffers that can't be read are treated as transparent. The sprite is clipped to the
+    /// display.
+    pub fn draw_sprite(&mut self, position: Point, image: &Image) {
+        let target = Rect::new(position.x, position.y, image.width(), image.height());
+        let area = match target.intersection(&Rect::screen()) {
+            Some(area) => area,
+            None => return,
+        };
+        for point in points(area) {
+            let (x, y) = (point.x - position.x, point.y - position.y);
+            let color = Color::from_argb8888(image.pixels()[y * image.width() + x]);
+            let background = self
+                .framebuffer
+                .read_pixel(point.x, point.y)
+                .unwrap_or(Color::rgba(0, 0, 0, 0));
+            self.framebuffer
+                .set_pixel(point.x, point.y, color.blend_over(background));
+        }
+        self.dirty.add(area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Animator, Generated};
+    use crate::lcd::{Color, Layer, MemoryFramebuffer, Point, Rect};
+
+    #[test]
+    fn plays_frames_and_restores_the_background() {
+        let background = Color::rgb(0, 0, 255);
+        let red = Color::rgb(255, 0, 0);
+        let half_red = Color::rgba(255, 0, 0, 128);
+        let mut layer = Layer::new(MemoryFramebuffer::new());
+        layer.fill_rect(Rect::screen(), background);
+        layer.take_dirty_regions();
+
+        let mut animator = Animator::new();
+        let colors = [red, half_red];
+        let blink = animator.add(Generated::new(4, 4, 2, 100, move |frame, _, _| {
+            colors[frame]
+        }));
+        animator.play(blink, Point::new(10, 20), 1000);
+
+        assert!(animator.update(&mut layer, 999));
+        assert!(layer.dirty_regions().is_empty());
+        assert!(animator.update(&mut layer, 1000));
+        assert_eq!(layer.framebuffer().pixel(13, 23), red);
+        assert_eq!(layer.framebuffer().pixel(14, 23), background);
+        assert_eq!(layer.dirty_regions().bounds(), Rect::new(10, 20, 4, 4));
+
+        // the second frame is blended over the saved background, not over the first frame
+        assert!(animator.update(&mut layer, 1150));
+        assert_eq!(layer.framebuffer().pixel(10, 20), Color::rgb(128, 0, 127));
+
+        assert!(!animator.update(&mut layer, 1200));
+        assert_eq!(layer.framebuffer().pixel(10, 20), background);
+    }
+
+    #[test]
+    fn stop_restores_and_clips_to_the_display() {
+        let background = Color::rgb(0, 255, 0);
+        let mut layer = Layer::new(MemoryFramebuffer::new());
+        layer.fill_rect(Rect::screen(), background);
+
+        let mut animator = Animator::new();
+        let white = animator.add(Generated::new(8, 8, 1, 100, |_, _, _| {
+            Color::rgb(255, 255, 255)
+        }));
+        animator.play(white, Point::new(476, 268), 0);
+        animator.play(white, Point::new(0, 0), 50);
+        animator.update(&mut layer, 0);
+        assert_eq!(
+            layer.framebuffer().pixel(479, 271),
+            Color::rgb(255, 255, 255)
+        );
+
+        animator.stop_all(&mut layer);
+        assert!(!animator.is_running());
+        assert_eq!(layer.framebuffer().pixel(479, 271), background);
+        assert_eq!(layer.framebuffer().pixel(0, 0), background);
+    }
+}
@@ -0,0 +1,97 @@
+//! The global LCD console that the [`print!`] and [`println!`] macros write to.
+//!
+//! The console is set up with [`init_stdout`](super::init_stdout). Printing is safe in interrupt
+//! handlers and exceptions, because the console is only accessed with interrupts disabled. The
+//! interrupts stay disabled while the text is drawn, so large outputs delay them noticeably.
+//! Text that is printed before the console is initialized, or while an interrupted print holds
+//! the console (e.g. in the panic handler after a fault in a print), is discarded.
+//!
+//! The scrollback buffer of the [`Console`] is viewed by swiping, see [`handle_gesture`].
+
+use super::console::{Console, ROWS};
+use super::{Framebuffer, Layer};
+use crate::touch::{Gesture, SwipeDirection};
+use alloc::boxed::Box;
+use core::fmt::{self, Write};
+use cortex_m::interrupt;
+use spin::Mutex;
+
+static STDOUT: Mutex<Option<Console<Box<dyn Framebuffer + Send>>>> = Mutex::new(None);
+
+/// Makes the passed layer the console of the [`print!`] and [`println!`] macros and clears it.
+///
+/// The framebuffer of the layer is moved to the heap, so the allocator must be initialized
+/// first. Use a layer with the [`console::PALETTE`](super::console::PALETTE) in its color lookup
+/// table to show the ANSI colors.
+pub fn init<T: Framebuffer + Send + 'static>(layer: Layer<T>) {
+    let layer = Layer {
+        framebuffer: Box::new(layer.framebuffer) as Box<dyn Framebuffer + Send>,
+        dirty: layer.dirty,
+    };
+    // clearing the layer takes a while, so it's done before interrupts are disabled
+    let console = Console::new(layer);
+    interrupt::free(|_| *STDOUT.lock() = Some(console));
+}
+
+/// Returns whether the console was initialized.
+pub fn is_initialized() -> bool {
+    // the console is only locked by a print that was interrupted, so it exists
+    interrupt::free(|_| STDOUT.try_lock().map_or(true, |stdout| stdout.is_some()))
+}
+
+/// Writes to the console.
+///
+/// Normally this function is not called directly, but through the [`print!`] and [`println!`]
+/// macros.
+pub fn print(args: fmt::Arguments) {
+    with_console(|console| {
+        let _ = console.write_fmt(args);
+    });
+}
+
+/// Scrolls the view back by `lines` lines, or forward for a negative number, see
+/// [`Console::scroll_view`].
+pub fn scroll_view(lines: isize) {
+    with_console(|console| console.scroll_view(lines));
+}
+
+/// Scrolls the view through the scrollback buffer with a gesture.
+///
+/// Swiping down shows older lines and swiping up newer lines, half a screen at a time. A two
+/// finger tap returns to the newest lines. Returns whether the gesture was used.
+pub fn handle_gesture(gesture: &Gesture) -> bool {
+    let page = (ROWS / 2) as isize;
+    match *gesture {
+        Gesture::Swipe(SwipeDirection::Down) => scroll_view(page),
+        Gesture::Swipe(SwipeDirection::Up) => scroll_view(-page),
+        Gesture::TwoFingerTap { .. } => with_console(|console| console.scroll_to_end()),
+        _ => return false,
+    }
+    true
+}
+
+fn with_console<F: FnOnce(&mut Console<Box<dyn Framebuffer + Send>>)>(f: F) {
+    interrupt::free(|_| {
+        if let Some(mut stdout) = STDOUT.try_lock() {
+            if let Some(ref mut console) = *stdout {
+                f(console);
+            }
+        }
+    });
+}
+
+/// Prints to the LCD console, see [`lcd::stdout`](crate::lcd::stdout).
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => {
+        $crate::lcd::stdout::print(format_args!($($arg)*))
+    };
+}
+
+/// Prints to the LCD console, with a newline.
+#[macro_export]
+macro_rules! println {
+    () => { $crate::print!("\n") };
+    ($fmt:expr) => { $crate::print!(concat!($fmt, "\n")) };
+    ($fmt:expr, $($arg:tt)*) => { $crate::print!(concat!($fmt, "\n"), $($arg)*) };
+}
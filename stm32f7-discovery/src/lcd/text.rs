@@ -0,0 +1,286 @@
+//! Text boxes with word wrapping and alignment.
+//!
+//! A [`TextBox`] describes a rectangle on a layer, the font and the alignment of the text in it.
+//! [`Layer::draw_text_box`] clears the rectangle and draws the text wrapped at spaces, words that
+//! are wider than the box are broken between characters. Text that does not fit is cut off at the
+//! last visible line, which ends with an ellipsis.
+//!
+//! ```
+//! let message = TextBox::new(Rect::new(300, 156, 180, 32), Font8x8::new(1))
+//!     .with_alignment(HorizontalAlignment::Center, VerticalAlignment::Middle);
+//! layer.draw_text_box(&message, "sunk ship of length 3");
+//! layer.clear_text_box(&message);
+//! ```
+
+use super::font::Font;
+use super::{Color, Framebuffer, Layer, Point, Rect};
+
+const ELLIPSIS: &str = "...";
+
+/// The horizontal alignment of the lines in a [`TextBox`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HorizontalAlignment {
+    /// Lines start at the left edge.
+    Left,
+    /// Lines are centered.
+    Center,
+    /// Lines end at the right edge.
+    Right,
+}
+
+/// The vertical alignment of the block of lines in a [`TextBox`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerticalAlignment {
+    /// The first line starts at the top edge.
+    Top,
+    /// The lines are centered.
+    Middle,
+    /// The last line ends at the bottom edge.
+    Bottom,
+}
+
+/// A rectangle that text is laid out in.
+#[derive(Debug, Clone, Copy)]
+pub struct TextBox<F> {
+    bounds: Rect,
+    font: F,
+    color: Color,
+    background: Color,
+    horizontal: HorizontalAlignment,
+    vertical: VerticalAlignment,
+    line_spacing: usize,
+}
+
+impl<F: Font> TextBox<F> {
+    /// Creates a text box with white, top left aligned text on a transparent background.
+    pub fn new(bounds: Rect, font: F) -> TextBox<F> {
+        TextBox {
+            bounds,
+            font,
+            color: Color::rgb(255, 255, 255),
+            background: Color::rgba(0, 0, 0, 0),
+            horizontal: HorizontalAlignment::Left,
+            vertical: VerticalAlignment::Top,
+            line_spacing: 0,
+        }
+    }
+
+    /// Sets the text and background colors.
+    pub fn with_colors(mut self, color: Color, background: Color) -> TextBox<F> {
+        self.color = color;
+        self.background = background;
+        self
+    }
+
+    /// Sets the alignment of the text.
+    pub fn with_alignment(
+        mut self,
+        horizontal: HorizontalAlignment,
+        vertical: VerticalAlignment,
+    ) -> TextBox<F> {
+        self.horizontal = horizontal;
+        self.vertical = vertical;
+        self
+    }
+
+    /// Adds `spacing` pixels between two lines.
+    pub fn with_line_spacing(mut self, spacing: usize) -> TextBox<F> {
+        self.line_spacing = spacing;
+        self
+    }
+
+    /// Returns the rectangle of the text box.
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    /// Returns the number of lines that fit into the text box.
+    pub fn max_lines(&self) -> usize {
+        (self.bounds.height + self.line_spacing) / (self.font.height() + self.line_spacing)
+    }
+
+    /// Returns the lines of the wrapped text, without the limit of [`max_lines`].
+    ///
+    /// [`max_lines`]: TextBox::max_lines
+    pub fn lines<'t>(&'t self, text: &'t str) -> Lines<'t, F> {
+        Lines {
+            text,
+            font: &self.font,
+            width: self.bounds.width,
+        }
+    }
+
+    fn line_x(&self, width: usize) -> usize {
+        let free = self.bounds.width.saturating_sub(width);
+        match self.horizontal {
+            HorizontalAlignment::Left => self.bounds.x,
+            HorizontalAlignment::Center => self.bounds.x + free / 2,
+            HorizontalAlignment::Right => self.bounds.x + free,
+        }
+    }
+}
+
+/// An iterator over the wrapped lines of a text, see [`TextBox::lines`].
+pub struct Lines<'t, F> {
+    text: &'t str,
+    font: &'t F,
+    width: usize,
+}
+
+impl<'t, F: Font> Iterator for Lines<'t, F> {
+    type Item = &'t str;
+
+    fn next(&mut self) -> Option<&'t str> {
+        if self.text.is_empty() {
+            return None;
+        }
+        let (line, rest) = wrap(self.text, self.font, self.width);
+        self.text = rest;
+        Some(line)
+    }
+}
+
+/// Splits off the first line of `text` that fits into `width` pixels.
+///
+/// Breaks at newlines and at the last space that fits. Lines contain at least one character,
+/// even if it is wider than `width`.
+fn wrap<'t, F: Font>(text: &'t str, font: &F, width: usize) -> (&'t str, &'t str) {
+    let mut line_width = 0;
+    let mut last_space = None;
+    for (i, c) in text.char_indices() {
+        if c == '\n' {
+            return (&text[..i], &text[i + 1..]);
+        }
+        if c == ' ' {
+            // spaces may hang over the edge, they are removed at the break
+            last_space = Some(i);
+            line_width += font.advance(c);
+            continue;
+        }
+        line_width += font.advance(c);
+        if line_width > width {
+            let (line, rest) = match last_space {
+                Some(space) => (text[..space].trim_end_matches(' '), &text[space..]),
+                None if i == 0 => text.split_at(c.len_utf8()),
+                None => text.split_at(i),
+            };
+            let rest = rest.trim_start_matches(' ');
+            return (line, rest);
+        }
+    }
+    (text, "")
+}
+
+impl<T: Framebuffer> Layer<T> {
+    /// Clears the text box and draws `text` into it.
+    ///
+    /// Returns `false` if the text did not fit and was cut off.
+    pub fn draw_text_box<F: Font>(&mut self, text_box: &TextBox<F>, text: &str) -> bool {
+        self.clear_text_box(text_box);
+
+        let font = &text_box.font;
+        let line_height = font.height() + text_box.line_spacing;
+        let max_lines = text_box.max_lines();
+        let line_count = text_box.lines(text).count();
+        let shown = line_count.min(max_lines);
+        if shown == 0 {
+            return line_count == 0;
+        }
+
+        let block_height = shown * line_height - text_box.line_spacing;
+        let free = text_box.bounds.height.saturating_sub(block_height);
+        let mut y = text_box.bounds.y
+            + match text_box.vertical {
+                VerticalAlignment::Top => 0,
+                VerticalAlignment::Middle => free / 2,
+                VerticalAlignment::Bottom => free,
+            };
+        for (i, line) in text_box.lines(text).take(shown).enumerate() {
+            let truncated = i + 1 == shown && line_count > shown;
+            let line = if truncated {
+                ellipsize(line, font, text_box.bounds.width)
+            } else {
+                line
+            };
+            let suffix = if truncated { ELLIPSIS } else { "" };
+            let width = font.text_width(line) + font.text_width(suffix);
+            let x = text_box.line_x(width);
+            let color = text_box.color;
+            let clip = text_box.bounds;
+            let x = x + self.draw_text_clipped(Point::new(x, y), line, font, color, clip);
+            self.draw_text_clipped(Point::new(x, y), suffix, font, color, clip);
+            y += line_height;
+        }
+        line_count <= max_lines
+    }
+
+    /// Fills exactly the rectangle of the text box with its background color.
+    pub fn clear_text_box<F: Font>(&mut self, text_box: &TextBox<F>) {
+        self.fill_rect(text_box.bounds, text_box.background);
+    }
+}
+
+/// Shortens `line` so that it fits into `width` pixels together with an ellipsis.
+fn ellipsize<'t, F: Font>(line: &'t str, font: &F, width: usize) -> &'t str {
+    let available = width.saturating_sub(font.text_width(ELLIPSIS));
+    let mut line_width = 0;
+    for (i, c) in line.char_indices() {
+        line_width += font.advance(c);
+        if line_width > available {
+            return line[..i].trim_end_matches(' ');
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HorizontalAlignment, TextBox, VerticalAlignment};
+    use crate::lcd::font::Font8x8;
+    use crate::lcd::{Color, Layer, MemoryFramebuffer, Rect};
+    use alloc::vec::Vec;
+
+    fn text_box(width: usize, height: usize) -> TextBox<Font8x8> {
+        TextBox::new(Rect::new(10, 10, width, height), Font8x8::new(1))
+    }
+
+    #[test]
+    fn wraps_at_spaces_and_newlines() {
+        let text_box = text_box(80, 80);
+        let lines: Vec<&str> = text_box
+            .lines("select a fire location\n\nyour fleet")
+            .collect();
+        assert_eq!(lines, ["select a", "fire", "location", "", "your fleet"]);
+    }
+
+    #[test]
+    fn breaks_long_words() {
+        let text_box = text_box(24, 80);
+        let lines: Vec<&str> = text_box.lines("abcdefg hi").collect();
+        assert_eq!(lines, ["abc", "def", "g", "hi"]);
+    }
+
+    #[test]
+    fn draws_aligned_and_truncated_text() {
+        let white = Color::rgb(255, 255, 255);
+        let text_box = text_box(80, 20)
+            .with_alignment(HorizontalAlignment::Right, VerticalAlignment::Bottom)
+            .with_line_spacing(4)
+            .with_colors(white, Color::rgb(0, 0, 0));
+        assert_eq!(text_box.max_lines(), 2);
+
+        let mut layer = Layer::new(MemoryFramebuffer::new());
+        layer.fill_rect(Rect::screen(), Color::rgb(0, 0, 255));
+        assert!(layer.draw_text_box(&text_box, "|"));
+        // the line ends at the right edge and the last line at the bottom edge, the bar of
+        // font8x8 covers the columns 3 and 4 of its glyph
+        let framebuffer = layer.framebuffer();
+        assert_eq!(framebuffer.pixel(10 + 80 - 8 + 3, 10 + 20 - 8), white);
+        assert_eq!(framebuffer.pixel(10, 10), Color::rgb(0, 0, 0));
+        // only the rectangle of the text box is cleared
+        assert_eq!(framebuffer.pixel(9, 10), Color::rgb(0, 0, 255));
+        assert_eq!(framebuffer.pixel(90, 29), Color::rgb(0, 0, 255));
+
+        assert!(!layer.draw_text_box(&text_box, "one two three four five"));
+    }
+}
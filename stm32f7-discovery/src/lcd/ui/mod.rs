@@ -0,0 +1,216 @@
+//! A small retained-mode widget toolkit.
+//!
+//! A [`Screen`] owns a list of widgets. Each widget knows its bounds and how to draw itself.
+//! The screen draws only widgets that were changed since the last [`Screen::draw`] call and
+//! finds the widget under a touch point with [`Screen::hit_test`].
+//!
+//! ```
+//! let mut screen = Screen::new();
+//! let status = screen.add(Label::new(Rect::new(290, 160, 180, 8), "select a field"));
+//! let confirm = screen.add(Button::new(Rect::new(299, 199, 158, 52), "CONFIRM"));
+//! screen.draw(&mut layer_1);
+//!
+//! if screen.hit_test(Point::new(touch.x as usize, touch.y as usize)) == Some(confirm) {
+//!     screen.update(status, |label: &mut Label| label.set_text("confirmed"));
+//! }
+//! screen.draw(&mut layer_1); // redraws only the status label
+//! ```
+//!
+//! Widgets are drawn in the order in which they were added, so containers like [`Panel`] should
+//! be added before the widgets placed on top of them.
+
+pub use self::widgets::{Button, ButtonState, Grid, Label, Panel};
+
+mod widgets;
+
+use super::{font, Color, Framebuffer, Layer, Point, Rect};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::Any;
+
+/// An element of a [`Screen`].
+pub trait Widget<T>: Any {
+    /// Returns the area that the widget covers.
+    fn bounds(&self) -> Rect;
+
+    /// Draws the widget. The widget must paint its whole bounds, because the screen does not
+    /// clear the area before redrawing a widget.
+    fn draw(&self, layer: &mut Layer<T>);
+
+    /// Returns whether the widget reacts to touches. Only interactive widgets are returned by
+    /// [`Screen::hit_test`].
+    fn is_interactive(&self) -> bool {
+        false
+    }
+
+    /// Converts the widget to `Any`, used for downcasting in [`Screen::update`].
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// Identifies a widget of a [`Screen`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WidgetId(usize);
+
+struct Entry<T> {
+    widget: Box<dyn Widget<T>>,
+    dirty: bool,
+    visible: bool,
+}
+
+/// A collection of widgets that are drawn to a layer.
+pub struct Screen<T> {
+    widgets: Vec<Entry<T>>,
+}
+
+impl<T: Framebuffer + 'static> Screen<T> {
+    /// Creates an empty screen.
+    pub fn new() -> Screen<T> {
+        Screen {
+            widgets: Vec::new(),
+        }
+    }
+
+    /// Adds a widget on top of the existing widgets.
+    pub fn add<W: Widget<T>>(&mut self, widget: W) -> WidgetId {
+        self.widgets.push(Entry {
+            widget: Box::new(widget),
+            dirty: true,
+            visible: true,
+        });
+        WidgetId(self.widgets.len() - 1)
+    }
+
+    /// Changes the widget with the passed id and marks it for redrawing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the widget is not of type `W`.
+    pub fn update<W, F, R>(&mut self, id: WidgetId, f: F) -> R
+    where
+        W: Widget<T>,
+        F: FnOnce(&mut W) -> R,
+    {
+        let entry = &mut self.widgets[id.0];
+        let widget = entry
+            .widget
+            .as_any_mut()
+            .downcast_mut::<W>()
+            .expect("widget has a different type");
+        entry.dirty = true;
+        f(widget)
+    }
+
+    /// Shows or hides a widget. Hidden widgets are neither drawn nor hit.
+    ///
+    /// Hiding a widget does not erase it; the widgets below it are redrawn instead.
+    pub fn set_visible(&mut self, id: WidgetId, visible: bool) {
+        if self.widgets[id.0].visible == visible {
+            return;
+        }
+        self.widgets[id.0].visible = visible;
+        if visible {
+            self.widgets[id.0].dirty = true;
+        } else {
+            let bounds = self.widgets[id.0].widget.bounds();
+            self.invalidate(bounds);
+        }
+    }
+
+    /// Returns the bounds of the widget.
+    pub fn bounds(&self, id: WidgetId) -> Rect {
+        self.widgets[id.0].widget.bounds()
+    }
+
+    /// Marks all widgets that overlap the passed area for redrawing.
+    pub fn invalidate(&mut self, area: Rect) {
+        for entry in self.widgets.iter_mut() {
+            if entry.widget.bounds().intersects(&area) {
+                entry.dirty = true;
+            }
+        }
+    }
+
+    /// Marks all widgets for redrawing, e.g. after the layer was cleared.
+    pub fn invalidate_all(&mut self) {
+        for entry in self.widgets.iter_mut() {
+            entry.dirty = true;
+        }
+    }
+
+    /// Returns the topmost visible interactive widget at the passed point.
+    pub fn hit_test(&self, point: Point) -> Option<WidgetId> {
+        self.widgets
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, entry)| {
+                entry.visible
+                    && entry.widget.is_interactive()
+                    && entry.widget.bounds().contains(point)
+            })
+            .map(|(i, _)| WidgetId(i))
+    }
+
+    /// Draws all widgets that changed since the last call.
+    ///
+    /// Widgets on top of a redrawn widget are redrawn as well, so that they stay visible.
+    pub fn draw(&mut self, layer: &mut Layer<T>) {
+        for i in 0..self.widgets.len() {
+            if !self.widgets[i].dirty {
+                continue;
+            }
+            let bounds = self.widgets[i].widget.bounds();
+            for above in self.widgets[i + 1..].iter_mut() {
+                if above.widget.bounds().intersects(&bounds) {
+                    above.dirty = true;
+                }
+            }
+        }
+        for entry in self.widgets.iter_mut().filter(|entry| entry.dirty) {
+            if entry.visible {
+                entry.widget.draw(layer);
+            }
+            entry.dirty = false;
+        }
+    }
+}
+
+/// Draws an outline of `thickness` pixels along the edges of the rectangle.
+fn outline<T: Framebuffer>(layer: &mut Layer<T>, rect: Rect, thickness: usize, color: Color) {
+    let (top, rest) = rect.split_top(thickness);
+    let (middle, _) = rest.split_top(rest.height.saturating_sub(thickness));
+    let bottom = Rect::new(rect.x, middle.bottom(), rect.width, rect.bottom() - middle.bottom());
+    let (left, rest) = middle.split_left(thickness);
+    let (_, right) = rest.split_left(rest.width.saturating_sub(thickness));
+    for edge in [top, bottom, left, right].iter() {
+        layer.fill_rect(*edge, color);
+    }
+}
+
+/// Draws a line of 8x8 text with explicit foreground and background colors, clipped to `clip`.
+fn text<T: Framebuffer>(
+    layer: &mut Layer<T>,
+    origin: Point,
+    clip: Rect,
+    s: &str,
+    foreground: Color,
+    background: Color,
+) {
+    for (i, c) in s.chars().enumerate() {
+        let glyph = font::glyph(c);
+        for (y, byte) in glyph.iter().enumerate() {
+            for x in 0..8 {
+                let point = Point::new(origin.x + i * 8 + x, origin.y + y);
+                if !clip.contains(point) {
+                    continue;
+                }
+                let color = if *byte & (1 << x) == 0 {
+                    background
+                } else {
+                    foreground
+                };
+                layer.print_point_color_at(point.x, point.y, color);
+            }
+        }
+    }
+}
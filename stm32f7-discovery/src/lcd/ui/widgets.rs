@@ -0,0 +1,307 @@
+use super::{outline, text, Widget};
+use crate::lcd::{Color, Framebuffer, Layer, Point, Rect, HEIGHT, WIDTH};
+use alloc::string::String;
+use core::any::Any;
+
+const GLYPH_SIZE: usize = 8;
+
+/// A filled rectangle with an optional border, used as background for other widgets.
+pub struct Panel {
+    bounds: Rect,
+    background: Color,
+    border: Option<Color>,
+}
+
+impl Panel {
+    /// Creates a panel filled with `background`.
+    pub fn new(bounds: Rect, background: Color) -> Panel {
+        Panel {
+            bounds,
+            background,
+            border: None,
+        }
+    }
+
+    /// Adds a one pixel border in the passed color.
+    pub fn with_border(mut self, color: Color) -> Panel {
+        self.border = Some(color);
+        self
+    }
+}
+
+impl<T: Framebuffer> Widget<T> for Panel {
+    fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    fn draw(&self, layer: &mut Layer<T>) {
+        layer.fill_rect(self.bounds, self.background);
+        if let Some(border) = self.border {
+            outline(layer, self.bounds, 1, border);
+        }
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// A single line of text on a solid background.
+///
+/// The text is vertically centered and clipped to the bounds of the label.
+pub struct Label {
+    bounds: Rect,
+    text: String,
+    foreground: Color,
+    background: Color,
+    centered: bool,
+}
+
+impl Label {
+    /// Creates a label with white text on a transparent background.
+    pub fn new(bounds: Rect, text: &str) -> Label {
+        Label {
+            bounds,
+            text: String::from(text),
+            foreground: Color::rgb(255, 255, 255),
+            background: Color::rgba(0, 0, 0, 0),
+            centered: false,
+        }
+    }
+
+    /// Sets the text and background colors.
+    pub fn with_colors(mut self, foreground: Color, background: Color) -> Label {
+        self.foreground = foreground;
+        self.background = background;
+        self
+    }
+
+    /// Centers the text horizontally.
+    pub fn centered(mut self) -> Label {
+        self.centered = true;
+        self
+    }
+
+    /// Returns the current text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replaces the text.
+    pub fn set_text(&mut self, text: &str) {
+        self.text.clear();
+        self.text.push_str(text);
+    }
+
+    /// Sets the text color.
+    pub fn set_foreground(&mut self, color: Color) {
+        self.foreground = color;
+    }
+}
+
+impl<T: Framebuffer> Widget<T> for Label {
+    fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    fn draw(&self, layer: &mut Layer<T>) {
+        layer.fill_rect(self.bounds, self.background);
+        let text_width = self.text.chars().count() * GLYPH_SIZE;
+        let x = if self.centered {
+            self.bounds.x + self.bounds.width.saturating_sub(text_width) / 2
+        } else {
+            self.bounds.x
+        };
+        let y = self.bounds.y + self.bounds.height.saturating_sub(GLYPH_SIZE) / 2;
+        text(
+            layer,
+            Point::new(x, y),
+            self.bounds,
+            &self.text,
+            self.foreground,
+            self.background,
+        );
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// The state of a [`Button`], which determines its colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ButtonState {
+    /// The button can be pressed.
+    Enabled,
+    /// The button is greyed out.
+    Disabled,
+    /// The button is currently touched.
+    Pressed,
+}
+
+/// A rectangular button with a centered caption.
+pub struct Button {
+    bounds: Rect,
+    label: Label,
+    state: ButtonState,
+}
+
+impl Button {
+    /// Creates an enabled button.
+    pub fn new(bounds: Rect, caption: &str) -> Button {
+        Button {
+            bounds,
+            label: Label::new(bounds.inset(2), caption).centered(),
+            state: ButtonState::Enabled,
+        }
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> ButtonState {
+        self.state
+    }
+
+    /// Changes the state of the button.
+    pub fn set_state(&mut self, state: ButtonState) {
+        self.state = state;
+    }
+
+    /// Replaces the caption.
+    pub fn set_caption(&mut self, caption: &str) {
+        self.label.set_text(caption);
+    }
+
+    fn colors(&self) -> (Color, Color, Color) {
+        let black = Color::rgb(0, 0, 0);
+        let white = Color::rgb(255, 255, 255);
+        let grey = Color::rgb(127, 127, 127);
+        // (border, background, text)
+        match self.state {
+            ButtonState::Enabled => (white, black, white),
+            ButtonState::Disabled => (grey, grey, Color::rgb(200, 200, 200)),
+            ButtonState::Pressed => (black, white, black),
+        }
+    }
+}
+
+impl<T: Framebuffer> Widget<T> for Button {
+    fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    fn draw(&self, layer: &mut Layer<T>) {
+        let (border, background, foreground) = self.colors();
+        outline(layer, self.bounds, 2, border);
+        let label = Label::new(self.label.bounds, &self.label.text)
+            .centered()
+            .with_colors(foreground, background);
+        Widget::<T>::draw(&label, layer);
+    }
+
+    fn is_interactive(&self) -> bool {
+        self.state != ButtonState::Disabled
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// A grid of square cells separated by lines.
+///
+/// The grid only draws its lines, so the cell contents stay untouched when the grid is redrawn.
+/// Lines outside of the display are clipped.
+pub struct Grid {
+    origin: Point,
+    cell_size: usize,
+    columns: usize,
+    rows: usize,
+    line_width: usize,
+    line_color: Color,
+}
+
+impl Grid {
+    /// Creates a grid with `columns` x `rows` cells, starting at `origin`.
+    ///
+    /// A line is drawn at the right and bottom edge of each cell.
+    pub fn new(origin: Point, cell_size: usize, columns: usize, rows: usize) -> Grid {
+        Grid {
+            origin,
+            cell_size,
+            columns,
+            rows,
+            line_width: 2,
+            line_color: Color::rgb(0, 0, 0),
+        }
+    }
+
+    /// Sets the color and width of the lines.
+    pub fn with_lines(mut self, color: Color, width: usize) -> Grid {
+        self.line_color = color;
+        self.line_width = width;
+        self
+    }
+
+    /// Returns the cell `(column, row)` at the passed point.
+    pub fn cell_at(&self, point: Point) -> Option<(usize, usize)> {
+        if point.x < self.origin.x || point.y < self.origin.y {
+            return None;
+        }
+        let column = (point.x - self.origin.x) / self.cell_size;
+        let row = (point.y - self.origin.y) / self.cell_size;
+        if column < self.columns && row < self.rows {
+            Some((column, row))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the area of the cell, excluding the grid lines.
+    pub fn cell_rect(&self, column: usize, row: usize) -> Rect {
+        let half_line = self.line_width / 2;
+        let inner = self.cell_size.saturating_sub(self.line_width);
+        Rect::new(
+            self.origin.x + column * self.cell_size + half_line,
+            self.origin.y + row * self.cell_size + half_line,
+            inner,
+            inner,
+        )
+    }
+}
+
+impl<T: Framebuffer> Widget<T> for Grid {
+    fn bounds(&self) -> Rect {
+        let unclipped = Rect::new(
+            self.origin.x,
+            self.origin.y,
+            self.columns * self.cell_size + self.line_width / 2,
+            self.rows * self.cell_size + self.line_width / 2,
+        );
+        unclipped
+            .intersection(&Rect::new(0, 0, WIDTH, HEIGHT))
+            .unwrap_or(Rect::new(0, 0, 0, 0))
+    }
+
+    fn draw(&self, layer: &mut Layer<T>) {
+        let bounds = Widget::<T>::bounds(self);
+        let half_line = self.line_width / 2;
+        for column in 1..=self.columns {
+            let x = self.origin.x + column * self.cell_size - half_line;
+            let line = Rect::new(x, bounds.y, self.line_width, bounds.height);
+            if let Some(line) = line.intersection(&bounds) {
+                layer.fill_rect(line, self.line_color);
+            }
+        }
+        for row in 1..=self.rows {
+            let y = self.origin.y + row * self.cell_size - half_line;
+            let line = Rect::new(bounds.x, y, bounds.width, self.line_width);
+            if let Some(line) = line.intersection(&bounds) {
+                layer.fill_rect(line, self.line_color);
+            }
+        }
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -45,7 +45,10 @@ extern crate cortex_m_semihosting as sh;
 
 #[macro_use]
 pub mod lcd;
+#[macro_use]
+pub mod logging;
 pub mod ethernet;
+pub mod flash;
 pub mod future_mutex;
 pub mod gpio;
 pub mod i2c;
@@ -0,0 +1,185 @@
+//! A small logging facade with severity levels and pluggable sinks.
+//!
+//! Log messages are created with the [`log_error!`], [`log_warning!`], [`log_info!`] and
+//! [`log_debug!`] macros. Each message that passes the global maximum level is forwarded to all
+//! registered [`Sink`]s. If no sink is registered, messages are discarded, so logging is safe to
+//! use on boards that run without a debugger attached.
+//!
+//! Available sinks:
+//!
+//! - [`SemihostingSink`] prints to the host console through semihosting. Only register it when
+//!   a debugger is attached, because semihosting calls halt the processor otherwise.
+//! - [`syslog::SyslogSink`] queues RFC 5424 messages that are sent as UDP datagrams through a
+//!   smoltcp socket.
+
+pub mod syslog;
+
+pub use self::syslog::SyslogSink;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+use cortex_m::interrupt;
+use spin::Mutex;
+
+static LOGGER: Mutex<Logger> = Mutex::new(Logger {
+    max_level: Level::Info,
+    sinks: None,
+});
+
+struct Logger {
+    max_level: Level,
+    sinks: Option<Vec<Box<dyn Sink + Send>>>,
+}
+
+/// The severity of a log message.
+///
+/// The numeric values are the severities defined by RFC 5424, so a lower value is more severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Error conditions.
+    Error = 3,
+    /// Warning conditions.
+    Warning = 4,
+    /// Normal but significant conditions.
+    Notice = 5,
+    /// Informational messages.
+    Info = 6,
+    /// Debug-level messages.
+    Debug = 7,
+}
+
+impl Level {
+    /// Returns the upper case name of the level.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warning => "WARNING",
+            Level::Notice => "NOTICE",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+        }
+    }
+}
+
+/// A single log message.
+pub struct Record<'a> {
+    /// The severity of the message.
+    pub level: Level,
+    /// The milliseconds since boot, see [`system_clock::ms`](crate::system_clock::ms).
+    pub timestamp_ms: usize,
+    /// The module path of the code that created the message.
+    pub target: &'a str,
+    /// The message itself.
+    pub args: fmt::Arguments<'a>,
+}
+
+/// A destination for log messages.
+///
+/// Sinks are called with interrupts disabled, so they should not block.
+pub trait Sink {
+    /// Handles a log message.
+    fn log(&mut self, record: &Record);
+}
+
+/// Registers an additional sink.
+pub fn add_sink<S: Sink + Send + 'static>(sink: S) {
+    interrupt::free(|_| {
+        LOGGER
+            .lock()
+            .sinks
+            .get_or_insert_with(Vec::new)
+            .push(Box::new(sink));
+    });
+}
+
+/// Sets the least severe level that is still forwarded to the sinks.
+///
+/// The default is [`Level::Info`].
+pub fn set_max_level(level: Level) {
+    interrupt::free(|_| LOGGER.lock().max_level = level);
+}
+
+/// Returns whether messages of the passed level are forwarded to the sinks.
+pub fn enabled(level: Level) -> bool {
+    interrupt::free(|_| level <= LOGGER.lock().max_level)
+}
+
+/// Forwards a message to all registered sinks.
+///
+/// Normally this function is not called directly, but through one of the logging macros.
+pub fn log(level: Level, target: &str, args: fmt::Arguments) {
+    interrupt::free(|_| {
+        let mut logger = LOGGER.lock();
+        if level > logger.max_level {
+            return;
+        }
+        let record = Record {
+            level,
+            timestamp_ms: crate::system_clock::ms(),
+            target,
+            args,
+        };
+        if let Some(ref mut sinks) = logger.sinks {
+            for sink in sinks.iter_mut() {
+                sink.log(&record);
+            }
+        }
+    });
+}
+
+/// A sink that prints messages to the host console through semihosting.
+///
+/// Semihosting halts the processor if no debugger is attached, so this sink should only be
+/// registered in debug sessions.
+pub struct SemihostingSink;
+
+impl Sink for SemihostingSink {
+    fn log(&mut self, record: &Record) {
+        use crate::sh::hio;
+        use core::fmt::Write;
+
+        if let Ok(mut hstdout) = hio::hstdout() {
+            let _ = writeln!(
+                hstdout,
+                "[{:>8}] {:<7} {}: {}",
+                record.timestamp_ms,
+                record.level.as_str(),
+                record.target,
+                record.args
+            );
+        }
+    }
+}
+
+/// Logs a message with the passed level.
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $($arg:tt)+) => {
+        $crate::logging::log($level, module_path!(), format_args!($($arg)+))
+    };
+}
+
+/// Logs a message with level [`Error`](crate::logging::Level::Error).
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)+) => { $crate::log!($crate::logging::Level::Error, $($arg)+) };
+}
+
+/// Logs a message with level [`Warning`](crate::logging::Level::Warning).
+#[macro_export]
+macro_rules! log_warning {
+    ($($arg:tt)+) => { $crate::log!($crate::logging::Level::Warning, $($arg)+) };
+}
+
+/// Logs a message with level [`Info`](crate::logging::Level::Info).
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)+) => { $crate::log!($crate::logging::Level::Info, $($arg)+) };
+}
+
+/// Logs a message with level [`Debug`](crate::logging::Level::Debug).
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)+) => { $crate::log!($crate::logging::Level::Debug, $($arg)+) };
+}
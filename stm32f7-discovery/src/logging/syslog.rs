@@ -0,0 +1,165 @@
+//! Remote logging through UDP syslog messages as specified in RFC 5424.
+//!
+//! The [`SyslogSink`] only formats and queues messages, because the network interface is
+//! usually owned by the application. The application drains the queue through the
+//! corresponding [`SyslogQueue`] whenever it polls its interface:
+//!
+//! ```
+//! let (sink, syslog_queue) = SyslogSink::new("board-1", "game", 32);
+//! logging::add_sink(sink);
+//!
+//! // in the network loop
+//! let mut socket = sockets.get::<UdpSocket>(syslog_handle);
+//! syslog_queue.flush(&mut socket, collector_endpoint)?;
+//! ```
+//!
+//! On a Linux machine, the messages can be collected by any syslog daemon listening on UDP
+//! port 514, e.g. `rsyslogd` with the `imudp` module, or simply with `nc -ulk 514`.
+
+use super::{Record, Sink};
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use cortex_m::interrupt;
+use smoltcp::socket::UdpSocket;
+use smoltcp::wire::IpEndpoint;
+use spin::Mutex;
+
+/// The default UDP port of syslog collectors.
+pub const SYSLOG_PORT: u16 = 514;
+
+/// The facility `local0`, reserved for local use.
+pub const FACILITY_LOCAL0: u8 = 16;
+
+// Private enterprise number reserved for documentation (RFC 5612), used for the uptime
+// structured data element.
+const ENTERPRISE_NUMBER: u32 = 32473;
+
+struct Queue {
+    messages: VecDeque<Vec<u8>>,
+    capacity: usize,
+    dropped: usize,
+}
+
+/// A logging sink that formats RFC 5424 messages and queues them for sending.
+pub struct SyslogSink {
+    hostname: String,
+    app_name: String,
+    facility: u8,
+    queue: Arc<Mutex<Queue>>,
+}
+
+/// The sending end of a [`SyslogSink`].
+#[derive(Clone)]
+pub struct SyslogQueue {
+    queue: Arc<Mutex<Queue>>,
+}
+
+impl SyslogSink {
+    /// Creates a new sink with facility `local0` and the queue that belongs to it.
+    ///
+    /// At most `capacity` messages are queued. If the queue is full, the oldest message is
+    /// dropped.
+    pub fn new(hostname: &str, app_name: &str, capacity: usize) -> (SyslogSink, SyslogQueue) {
+        let queue = Arc::new(Mutex::new(Queue {
+            messages: VecDeque::with_capacity(capacity),
+            capacity,
+            dropped: 0,
+        }));
+        let sink = SyslogSink {
+            hostname: String::from(hostname),
+            app_name: String::from(app_name),
+            facility: FACILITY_LOCAL0,
+            queue: queue.clone(),
+        };
+        (sink, SyslogQueue { queue })
+    }
+
+    /// Sets the syslog facility (0..=23) of all messages.
+    pub fn set_facility(&mut self, facility: u8) {
+        assert!(facility <= 23, "invalid syslog facility");
+        self.facility = facility;
+    }
+}
+
+impl Sink for SyslogSink {
+    fn log(&mut self, record: &Record) {
+        let message = format_message(self.facility, &self.hostname, &self.app_name, record);
+        let mut queue = self.queue.lock();
+        if queue.messages.len() >= queue.capacity {
+            queue.messages.pop_front();
+            queue.dropped += 1;
+        }
+        queue.messages.push_back(message);
+    }
+}
+
+impl SyslogQueue {
+    /// Sends all queued messages to `collector` through the passed socket.
+    ///
+    /// Returns the number of sent messages. Messages that do not fit into the transmit buffer
+    /// of the socket stay queued until the next call.
+    pub fn flush(
+        &self,
+        socket: &mut UdpSocket,
+        collector: IpEndpoint,
+    ) -> Result<usize, smoltcp::Error> {
+        let mut sent = 0;
+        while socket.can_send() {
+            let message = interrupt::free(|_| self.queue.lock().messages.pop_front());
+            let message = match message {
+                Some(message) => message,
+                None => break,
+            };
+            if let Err(e) = socket.send_slice(&message, collector) {
+                interrupt::free(|_| self.queue.lock().messages.push_front(message));
+                return Err(e);
+            }
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    /// Returns the number of messages that were dropped because the queue was full.
+    pub fn dropped(&self) -> usize {
+        interrupt::free(|_| self.queue.lock().dropped)
+    }
+}
+
+/// Formats a record as RFC 5424 message.
+///
+/// The board has no real time clock, so the timestamp is the nil value and the uptime is
+/// transmitted as structured data instead.
+pub fn format_message(facility: u8, hostname: &str, app_name: &str, record: &Record) -> Vec<u8> {
+    let priority = u32::from(facility) * 8 + record.level as u32;
+    let mut message = String::new();
+    // writing to a string never fails
+    let _ = write!(
+        message,
+        "<{}>1 - {} {} - - [uptime@{} ms=\"{}\"] {}: {}",
+        priority,
+        header_field(hostname, 255),
+        header_field(app_name, 48),
+        ENTERPRISE_NUMBER,
+        record.timestamp_ms,
+        record.target,
+        record.args
+    );
+    message.into_bytes()
+}
+
+/// Header fields must consist of printable US-ASCII characters and must not be empty.
+fn header_field(value: &str, max_len: usize) -> String {
+    let field: String = value
+        .chars()
+        .filter(|c| c.is_ascii_graphic())
+        .take(max_len)
+        .collect();
+    if field.is_empty() {
+        String::from("-")
+    } else {
+        field
+    }
+}
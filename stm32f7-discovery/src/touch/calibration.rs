@@ -0,0 +1,266 @@
+//! Affine correction of touch coordinates.
+
+use super::Touch;
+use crate::flash::{self, Flash};
+use crate::lcd::{Point, HEIGHT, WIDTH};
+use byteorder::{ByteOrder, LittleEndian};
+use stm32f7::stm32f7x6::FLASH;
+
+/// The display positions of the calibration targets.
+///
+/// They are spread over the display, so that the transform is accurate near the edges, too.
+pub const TARGETS: [Point; 3] = [
+    Point::new(WIDTH / 10, HEIGHT / 10),
+    Point::new(WIDTH * 9 / 10, HEIGHT / 2),
+    Point::new(WIDTH / 2, HEIGHT * 9 / 10),
+];
+
+const MAGIC: u32 = 0x4C41_4354; // "TCAL"
+const STORAGE_OFFSET: usize = 0;
+const STORAGE_LENGTH: usize = 4 + 7 * 8 + 4;
+
+/// An affine transform from touch controller coordinates to display coordinates.
+///
+/// The display coordinates are computed as `x = (a * tx + b * ty + c) / divisor` and
+/// `y = (d * tx + e * ty + f) / divisor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Calibration {
+    a: i64,
+    b: i64,
+    c: i64,
+    d: i64,
+    e: i64,
+    f: i64,
+    divisor: i64,
+}
+
+impl Calibration {
+    /// The transform that leaves the coordinates unchanged.
+    pub const IDENTITY: Calibration = Calibration {
+        a: 1,
+        b: 0,
+        c: 0,
+        d: 0,
+        e: 1,
+        f: 0,
+        divisor: 1,
+    };
+
+    /// Computes the transform that maps the three touched points to the three display points.
+    ///
+    /// Returns `None` if the touched points lie on a line.
+    pub fn from_points(display: [Point; 3], touched: [Touch; 3]) -> Option<Calibration> {
+        let (xd0, xd1, xd2) = (display[0].x as i64, display[1].x as i64, display[2].x as i64);
+        let (yd0, yd1, yd2) = (display[0].y as i64, display[1].y as i64, display[2].y as i64);
+        let (xt0, xt1, xt2) = (touched[0].x as i64, touched[1].x as i64, touched[2].x as i64);
+        let (yt0, yt1, yt2) = (touched[0].y as i64, touched[1].y as i64, touched[2].y as i64);
+
+        let divisor = (xt0 - xt2) * (yt1 - yt2) - (xt1 - xt2) * (yt0 - yt2);
+        if divisor == 0 {
+            return None;
+        }
+        let a = (xd0 - xd2) * (yt1 - yt2) - (xd1 - xd2) * (yt0 - yt2);
+        let b = (xt0 - xt2) * (xd1 - xd2) - (xd0 - xd2) * (xt1 - xt2);
+        let c = yt0 * (xt2 * xd1 - xt1 * xd2)
+            + yt1 * (xt0 * xd2 - xt2 * xd0)
+            + yt2 * (xt1 * xd0 - xt0 * xd1);
+        let d = (yd0 - yd2) * (yt1 - yt2) - (yd1 - yd2) * (yt0 - yt2);
+        let e = (xt0 - xt2) * (yd1 - yd2) - (yd0 - yd2) * (xt1 - xt2);
+        let f = yt0 * (xt2 * yd1 - xt1 * yd2)
+            + yt1 * (xt0 * yd2 - xt2 * yd0)
+            + yt2 * (xt1 * yd0 - xt0 * yd1);
+        Some(Calibration {
+            a,
+            b,
+            c,
+            d,
+            e,
+            f,
+            divisor,
+        })
+    }
+
+    /// Transforms a touch point to display coordinates, clamped to the display.
+    pub fn apply(&self, touch: Touch) -> Touch {
+        let (tx, ty) = (i64::from(touch.x), i64::from(touch.y));
+        let x = (self.a * tx + self.b * ty + self.c) / self.divisor;
+        let y = (self.d * tx + self.e * ty + self.f) / self.divisor;
+        Touch {
+            x: clamp(x, WIDTH) as u16,
+            y: clamp(y, HEIGHT) as u16,
+        }
+    }
+
+    /// Reads the calibration from the flash data sector.
+    ///
+    /// Returns `None` if no valid calibration was stored.
+    pub fn load() -> Option<Calibration> {
+        Calibration::from_bytes(flash::data_sector(STORAGE_OFFSET, STORAGE_LENGTH))
+    }
+
+    /// Stores the calibration in the flash data sector, which erases the whole sector.
+    pub fn store(&self, flash: &mut FLASH) -> Result<(), flash::Error> {
+        let mut flash = Flash::unlock(flash);
+        flash.erase_data_sector()?;
+        flash.program(flash::DATA_SECTOR_START + STORAGE_OFFSET, &self.to_bytes())
+    }
+
+    fn values(&self) -> [i64; 7] {
+        [
+            self.a,
+            self.b,
+            self.c,
+            self.d,
+            self.e,
+            self.f,
+            self.divisor,
+        ]
+    }
+
+    fn to_bytes(&self) -> [u8; STORAGE_LENGTH] {
+        let mut bytes = [0; STORAGE_LENGTH];
+        LittleEndian::write_u32(&mut bytes[0..4], MAGIC);
+        for (i, value) in self.values().iter().enumerate() {
+            LittleEndian::write_i64(&mut bytes[4 + i * 8..12 + i * 8], *value);
+        }
+        let checksum = checksum(&bytes[..STORAGE_LENGTH - 4]);
+        LittleEndian::write_u32(&mut bytes[STORAGE_LENGTH - 4..], checksum);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Calibration> {
+        if bytes.len() < STORAGE_LENGTH
+            || LittleEndian::read_u32(&bytes[0..4]) != MAGIC
+            || LittleEndian::read_u32(&bytes[STORAGE_LENGTH - 4..STORAGE_LENGTH])
+                != checksum(&bytes[..STORAGE_LENGTH - 4])
+        {
+            return None;
+        }
+        let value = |i: usize| LittleEndian::read_i64(&bytes[4 + i * 8..12 + i * 8]);
+        let calibration = Calibration {
+            a: value(0),
+            b: value(1),
+            c: value(2),
+            d: value(3),
+            e: value(4),
+            f: value(5),
+            divisor: value(6),
+        };
+        if calibration.divisor == 0 {
+            None
+        } else {
+            Some(calibration)
+        }
+    }
+}
+
+impl Default for Calibration {
+    fn default() -> Calibration {
+        Calibration::IDENTITY
+    }
+}
+
+fn clamp(value: i64, len: usize) -> i64 {
+    value.max(0).min(len as i64 - 1)
+}
+
+/// A simple rotating checksum, enough to detect an erased or foreign data sector.
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0x1234_5678, |sum: u32, &b| sum.rotate_left(5) ^ u32::from(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Calibration, Touch, STORAGE_LENGTH, TARGETS};
+    use crate::lcd::{Point, HEIGHT, WIDTH};
+
+    fn t(x: u16, y: u16) -> Touch {
+        Touch { x, y }
+    }
+
+    /// A touch controller whose axes are scaled, skewed and offset against the display.
+    fn skewed(point: Point) -> Touch {
+        t(
+            (2 * point.x + point.y + 10) as u16,
+            (3 * point.y + 20) as u16,
+        )
+    }
+
+    fn skewed_calibration() -> Calibration {
+        let touched = [skewed(TARGETS[0]), skewed(TARGETS[1]), skewed(TARGETS[2])];
+        Calibration::from_points(TARGETS, touched).unwrap()
+    }
+
+    #[test]
+    fn identity() {
+        let touched = [
+            t(TARGETS[0].x as u16, TARGETS[0].y as u16),
+            t(TARGETS[1].x as u16, TARGETS[1].y as u16),
+            t(TARGETS[2].x as u16, TARGETS[2].y as u16),
+        ];
+        let calibration = Calibration::from_points(TARGETS, touched).unwrap();
+        for &touch in &[t(0, 0), t(123, 45), t(479, 271)] {
+            assert_eq!(calibration.apply(touch), touch);
+            assert_eq!(Calibration::IDENTITY.apply(touch), touch);
+        }
+    }
+
+    #[test]
+    fn skew_and_offset() {
+        let calibration = skewed_calibration();
+        for &point in &[TARGETS[0], TARGETS[1], TARGETS[2], Point::new(100, 50)] {
+            assert_eq!(
+                calibration.apply(skewed(point)),
+                t(point.x as u16, point.y as u16)
+            );
+        }
+    }
+
+    #[test]
+    fn apply_clamps_to_the_display() {
+        let calibration = skewed_calibration();
+        assert_eq!(calibration.apply(t(0, 0)), t(0, 0));
+        assert_eq!(
+            calibration.apply(t(2000, 1000)),
+            t(WIDTH as u16 - 1, HEIGHT as u16 - 1)
+        );
+    }
+
+    #[test]
+    fn collinear_points() {
+        let touched = [t(10, 10), t(100, 100), t(200, 200)];
+        assert_eq!(Calibration::from_points(TARGETS, touched), None);
+        let touched = [t(50, 80), t(50, 80), t(300, 200)];
+        assert_eq!(Calibration::from_points(TARGETS, touched), None);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let calibration = skewed_calibration();
+        let bytes = calibration.to_bytes();
+        assert_eq!(Calibration::from_bytes(&bytes), Some(calibration));
+        assert_eq!(Calibration::from_bytes(&bytes[..STORAGE_LENGTH - 1]), None);
+    }
+
+    #[test]
+    fn corrupted_bytes_are_rejected() {
+        let bytes = skewed_calibration().to_bytes();
+
+        let mut bad_magic = bytes;
+        bad_magic[0] ^= 1;
+        assert_eq!(Calibration::from_bytes(&bad_magic), None);
+
+        let mut bad_checksum = bytes;
+        bad_checksum[STORAGE_LENGTH - 1] ^= 1;
+        assert_eq!(Calibration::from_bytes(&bad_checksum), None);
+
+        let mut bad_value = bytes;
+        bad_value[10] ^= 0x80;
+        assert_eq!(Calibration::from_bytes(&bad_value), None);
+
+        // an erased flash sector
+        assert_eq!(Calibration::from_bytes(&[0xff; STORAGE_LENGTH]), None);
+    }
+}
@@ -0,0 +1,390 @@
+//! Recognition of touch gestures from touch samples.
+
+use super::{touches, Touch};
+use crate::future_mutex::FutureMutex;
+use crate::i2c::{self, I2C};
+use crate::system_clock;
+use crate::task_runtime::mpsc;
+use alloc::sync::Arc;
+use arrayvec::ArrayVec;
+use futures::{Stream, StreamExt};
+use pin_utils::pin_mut;
+use stm32f7::stm32f7x6 as device;
+
+/// An event produced by the [`TouchTracker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TouchEvent {
+    /// A finger was put on the display.
+    Down(Touch),
+    /// The finger moved to a new position.
+    Move(Touch),
+    /// The finger was lifted at the passed position.
+    Up(Touch),
+    /// The finger was lifted shortly after it was put down, without moving.
+    Tap(Touch),
+    /// The finger rested at the same position for at least [`Config::long_press_ms`].
+    LongPress(Touch),
+    /// The finger moved more than [`Config::drag_threshold`] pixels away from `start`.
+    ///
+    /// A drag event is sent for each move until the finger is lifted.
+    Drag {
+        /// The position where the finger was put down.
+        start: Touch,
+        /// The current position of the finger.
+        current: Touch,
+    },
+}
+
+/// Timing and distance thresholds of the [`TouchTracker`].
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// How long no touch must be reported before the finger counts as lifted.
+    ///
+    /// The touch controller sometimes reports no touch for a single sample while a finger is on
+    /// the display, which would otherwise split a touch in two.
+    pub debounce_ms: usize,
+    /// The maximal duration of a tap.
+    pub tap_max_ms: usize,
+    /// The duration after which a resting finger triggers a long press.
+    pub long_press_ms: usize,
+    /// The distance in pixels (in x or y direction) after which a move becomes a drag.
+    pub drag_threshold: u16,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            debounce_ms: 40,
+            tap_max_ms: 400,
+            long_press_ms: 700,
+            drag_threshold: 10,
+        }
+    }
+}
+
+/// The events produced by a single sample.
+pub type Events = ArrayVec<[TouchEvent; 3]>;
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Released,
+    Pressed {
+        start: Touch,
+        start_ms: usize,
+        last: Touch,
+        lifted_ms: Option<usize>,
+        dragging: bool,
+        long_press_sent: bool,
+    },
+}
+
+/// Turns touch samples into [`TouchEvent`]s.
+///
+/// Only the first touch point is tracked; additional fingers are ignored.
+#[derive(Debug, Clone)]
+pub struct TouchTracker {
+    config: Config,
+    state: State,
+}
+
+impl TouchTracker {
+    /// Creates a tracker with the passed thresholds.
+    pub fn new(config: Config) -> TouchTracker {
+        TouchTracker {
+            config,
+            state: State::Released,
+        }
+    }
+
+    /// Returns whether a finger is currently on the display.
+    ///
+    /// While this is true, the touchscreen should be sampled regularly even without a touch
+    /// interrupt, because releases and long presses are only detected on a new sample.
+    pub fn is_pressed(&self) -> bool {
+        match self.state {
+            State::Released => false,
+            State::Pressed { .. } => true,
+        }
+    }
+
+    /// Reads the touch controller and returns the resulting events.
+    pub fn poll(&mut self, i2c_3: &mut I2C<device::I2C3>) -> Result<Events, i2c::Error> {
+        let sample = touches(i2c_3)?.first().cloned();
+        Ok(self.update(sample, system_clock::ms()))
+    }
+
+    /// Processes a touch sample that was taken at `now_ms` and returns the resulting events.
+    ///
+    /// `None` means that no finger is on the display.
+    pub fn update(&mut self, sample: Option<Touch>, now_ms: usize) -> Events {
+        let mut events = Events::new();
+        let config = self.config;
+        self.state = match (self.state, sample) {
+            (State::Released, None) => State::Released,
+            (State::Released, Some(touch)) => {
+                events.push(TouchEvent::Down(touch));
+                State::Pressed {
+                    start: touch,
+                    start_ms: now_ms,
+                    last: touch,
+                    lifted_ms: None,
+                    dragging: false,
+                    long_press_sent: false,
+                }
+            }
+            (
+                State::Pressed {
+                    start,
+                    start_ms,
+                    last,
+                    mut dragging,
+                    mut long_press_sent,
+                    ..
+                },
+                Some(touch),
+            ) => {
+                if touch != last {
+                    events.push(TouchEvent::Move(touch));
+                    dragging = dragging || distance(start, touch) > config.drag_threshold;
+                    if dragging {
+                        events.push(TouchEvent::Drag {
+                            start,
+                            current: touch,
+                        });
+                    }
+                }
+                if !dragging
+                    && !long_press_sent
+                    && now_ms.wrapping_sub(start_ms) >= config.long_press_ms
+                {
+                    events.push(TouchEvent::LongPress(touch));
+                    long_press_sent = true;
+                }
+                State::Pressed {
+                    start,
+                    start_ms,
+                    last: touch,
+                    lifted_ms: None,
+                    dragging,
+                    long_press_sent,
+                }
+            }
+            (
+                State::Pressed {
+                    start,
+                    start_ms,
+                    last,
+                    lifted_ms,
+                    dragging,
+                    long_press_sent,
+                },
+                None,
+            ) => {
+                let lifted = lifted_ms.unwrap_or(now_ms);
+                if now_ms.wrapping_sub(lifted) < config.debounce_ms {
+                    State::Pressed {
+                        start,
+                        start_ms,
+                        last,
+                        lifted_ms: Some(lifted),
+                        dragging,
+                        long_press_sent,
+                    }
+                } else {
+                    events.push(TouchEvent::Up(last));
+                    let duration = lifted.wrapping_sub(start_ms);
+                    if !dragging && !long_press_sent && duration <= config.tap_max_ms {
+                        events.push(TouchEvent::Tap(last));
+                    }
+                    State::Released
+                }
+            }
+        };
+        events
+    }
+}
+
+impl Default for TouchTracker {
+    fn default() -> TouchTracker {
+        TouchTracker::new(Config::default())
+    }
+}
+
+fn distance(a: Touch, b: Touch) -> u16 {
+    let dx = if a.x > b.x { a.x - b.x } else { b.x - a.x };
+    let dy = if a.y > b.y { a.y - b.y } else { b.y - a.y };
+    dx.max(dy)
+}
+
+/// Recognizes touch events and sends them to `sink`.
+///
+/// The touchscreen is sampled whenever `touch_int_stream` yields, i.e. when the touch controller
+/// signals a touch. While a finger is on the display, it is additionally sampled whenever
+/// `idle_stream` yields, so that releases and long presses are detected without an interrupt.
+/// The receiving end of `sink` is a `Stream` of events that other tasks can await.
+pub async fn event_task<S, I>(
+    touch_int_stream: S,
+    idle_stream: I,
+    i2c_3_mutex: Arc<FutureMutex<I2C<device::I2C3>>>,
+    sink: mpsc::UnboundedSender<TouchEvent>,
+) where
+    S: Stream<Item = ()>,
+    I: Stream<Item = ()>,
+{
+    pin_mut!(touch_int_stream);
+    pin_mut!(idle_stream);
+    let mut tracker = TouchTracker::default();
+    loop {
+        if tracker.is_pressed() {
+            await!(idle_stream.next()).expect("idle stream closed");
+        } else {
+            await!(touch_int_stream.next()).expect("touch channel closed");
+        }
+        let sample = await!(i2c_3_mutex.with(|i2c_3| touches(i2c_3)))
+            .map(|touches| touches.first().cloned());
+        let sample = match sample {
+            Ok(sample) => sample,
+            Err(err) => {
+                log_warning!("reading touches failed: {:?}", err);
+                continue;
+            }
+        };
+        for event in tracker.update(sample, system_clock::ms()) {
+            if sink.unbounded_send(event).is_err() {
+                // the receiver was dropped, so nobody is interested in the events anymore
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, Touch, TouchEvent, TouchTracker};
+    use alloc::vec::Vec;
+
+    fn t(x: u16, y: u16) -> Touch {
+        Touch { x, y }
+    }
+
+    /// Feeds a trace of `(time, sample)` samples and collects the events.
+    fn run(tracker: &mut TouchTracker, trace: &[(usize, Option<Touch>)]) -> Vec<TouchEvent> {
+        trace
+            .iter()
+            .flat_map(|&(ms, sample)| tracker.update(sample, ms))
+            .collect()
+    }
+
+    #[test]
+    fn tap_inside_slop_radius() {
+        let mut tracker = TouchTracker::default();
+        let trace = [
+            (0, Some(t(100, 100))),
+            (50, Some(t(105, 92))),
+            (100, None),
+            (150, None),
+        ];
+        assert_eq!(
+            run(&mut tracker, &trace),
+            [
+                TouchEvent::Down(t(100, 100)),
+                TouchEvent::Move(t(105, 92)),
+                TouchEvent::Up(t(105, 92)),
+                TouchEvent::Tap(t(105, 92)),
+            ]
+        );
+        assert!(!tracker.is_pressed());
+    }
+
+    #[test]
+    fn short_lift_is_debounced() {
+        let mut tracker = TouchTracker::default();
+        let trace = [
+            (0, Some(t(100, 100))),
+            (20, None),
+            (40, Some(t(100, 100))),
+            (100, None),
+            (120, None),
+        ];
+        assert_eq!(run(&mut tracker, &trace), [TouchEvent::Down(t(100, 100))]);
+        assert!(tracker.is_pressed());
+    }
+
+    #[test]
+    fn long_press_at_threshold() {
+        let config = Config::default();
+        let mut tracker = TouchTracker::new(config);
+        let trace = [
+            (0, Some(t(200, 150))),
+            (config.long_press_ms - 1, Some(t(200, 150))),
+        ];
+        assert_eq!(run(&mut tracker, &trace), [TouchEvent::Down(t(200, 150))]);
+        let trace = [
+            (config.long_press_ms, Some(t(200, 150))),
+            (config.long_press_ms + 100, Some(t(200, 150))),
+        ];
+        assert_eq!(
+            run(&mut tracker, &trace),
+            [TouchEvent::LongPress(t(200, 150))]
+        );
+        // lifting the finger after a long press is no tap
+        let trace = [(800, None), (900, None)];
+        assert_eq!(run(&mut tracker, &trace), [TouchEvent::Up(t(200, 150))]);
+    }
+
+    #[test]
+    fn drag_starts_once_slop_is_exceeded() {
+        let config = Config::default();
+        let mut tracker = TouchTracker::new(config);
+        let start = t(100, 100);
+        let at_slop = t(100 + config.drag_threshold, 100);
+        let beyond_slop = t(100 + config.drag_threshold + 1, 100);
+        let trace = [(0, Some(start)), (10, Some(at_slop))];
+        assert_eq!(
+            run(&mut tracker, &trace),
+            [TouchEvent::Down(start), TouchEvent::Move(at_slop)]
+        );
+        assert_eq!(
+            run(&mut tracker, &[(20, Some(beyond_slop))]),
+            [
+                TouchEvent::Move(beyond_slop),
+                TouchEvent::Drag {
+                    start,
+                    current: beyond_slop,
+                },
+            ]
+        );
+        // the drag continues when the finger moves back towards the start
+        assert_eq!(
+            run(&mut tracker, &[(30, Some(t(104, 100)))]),
+            [
+                TouchEvent::Move(t(104, 100)),
+                TouchEvent::Drag {
+                    start,
+                    current: t(104, 100),
+                },
+            ]
+        );
+        // a drag never becomes a long press
+        let trace = [(config.long_press_ms + 10, Some(t(104, 100)))];
+        assert_eq!(run(&mut tracker, &trace), []);
+    }
+
+    #[test]
+    fn release_after_drag_is_no_tap() {
+        let mut tracker = TouchTracker::default();
+        let trace = [
+            (0, Some(t(100, 100))),
+            (30, Some(t(130, 100))),
+            (60, None),
+            (110, None),
+        ];
+        let events = run(&mut tracker, &trace);
+        assert_eq!(events.last(), Some(&TouchEvent::Up(t(130, 100))));
+        assert!(!events.iter().any(|event| match event {
+            TouchEvent::Tap(_) => true,
+            _ => false,
+        }));
+        assert!(!tracker.is_pressed());
+    }
+}
@@ -0,0 +1,412 @@
+//! Recognition of swipes and two-finger gestures.
+//!
+//! The [`GestureRecognizer`] only depends on the passed touch samples and timestamps, so it can
+//! be tested with recorded touch traces.
+
+use super::Touch;
+
+/// A gesture recognized by the [`GestureRecognizer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+    /// A single finger moved quickly in one direction and was lifted.
+    Swipe(SwipeDirection),
+    /// Two fingers moved towards or away from each other.
+    ///
+    /// A pinch event is sent for every change of the scale until the fingers are lifted.
+    Pinch {
+        /// The point between the two fingers.
+        center: Touch,
+        /// The current distance between the fingers in percent of their initial distance.
+        scale_percent: u16,
+    },
+    /// Two fingers were put on the display and lifted shortly after, without moving.
+    TwoFingerTap {
+        /// The point between the two fingers.
+        center: Touch,
+    },
+}
+
+/// The direction of a [`Gesture::Swipe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwipeDirection {
+    /// Towards smaller x coordinates.
+    Left,
+    /// Towards larger x coordinates.
+    Right,
+    /// Towards smaller y coordinates.
+    Up,
+    /// Towards larger y coordinates.
+    Down,
+}
+
+/// Timing and distance thresholds of the [`GestureRecognizer`].
+#[derive(Debug, Clone, Copy)]
+pub struct GestureConfig {
+    /// The distance in pixels that a finger must travel for a swipe.
+    pub swipe_min_distance: u16,
+    /// The maximal duration of a swipe.
+    pub swipe_max_ms: usize,
+    /// The maximal duration of a two-finger tap.
+    pub two_finger_tap_max_ms: usize,
+    /// The distance in pixels that the fingers may move during a two-finger tap.
+    pub tap_max_movement: u16,
+    /// The change of the finger distance in percent after which two fingers start a pinch.
+    pub pinch_threshold_percent: u16,
+}
+
+impl Default for GestureConfig {
+    fn default() -> GestureConfig {
+        GestureConfig {
+            swipe_min_distance: 80,
+            swipe_max_ms: 600,
+            two_finger_tap_max_ms: 400,
+            tap_max_movement: 15,
+            pinch_threshold_percent: 15,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Contacts {
+    None,
+    One(Touch),
+    Two(Touch, Touch),
+}
+
+impl Contacts {
+    fn from_touches(touches: &[Touch]) -> Contacts {
+        match touches.len() {
+            0 => Contacts::None,
+            1 => Contacts::One(touches[0]),
+            // more than two fingers are treated like two fingers
+            _ => Contacts::Two(touches[0], touches[1]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Idle,
+    OneFinger {
+        start: Touch,
+        start_ms: usize,
+        last: Touch,
+    },
+    TwoFingers {
+        start_ms: usize,
+        start_center: Touch,
+        initial_distance: u32,
+        scale_percent: u16,
+        pinching: bool,
+        moved: bool,
+    },
+}
+
+/// Recognizes gestures from the touch points reported by [`touches`](super::touches).
+///
+/// Every sample with no touch point ends the current gesture.
+#[derive(Debug, Clone)]
+pub struct GestureRecognizer {
+    config: GestureConfig,
+    state: State,
+}
+
+impl GestureRecognizer {
+    /// Creates a recognizer with the passed thresholds.
+    pub fn new(config: GestureConfig) -> GestureRecognizer {
+        GestureRecognizer {
+            config,
+            state: State::Idle,
+        }
+    }
+
+    /// Returns whether more than one finger touched the display since the last finger was
+    /// put down.
+    ///
+    /// Single finger events like taps should be ignored in this case.
+    pub fn is_multi_touch(&self) -> bool {
+        match self.state {
+            State::TwoFingers { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Processes the touch points of a sample taken at `now_ms` and returns the recognized
+    /// gesture.
+    pub fn update(&mut self, touches: &[Touch], now_ms: usize) -> Option<Gesture> {
+        let config = self.config;
+        let (state, gesture) = match (self.state, Contacts::from_touches(touches)) {
+            (State::Idle, Contacts::None) => (State::Idle, None),
+            (State::Idle, Contacts::One(touch)) => (
+                State::OneFinger {
+                    start: touch,
+                    start_ms: now_ms,
+                    last: touch,
+                },
+                None,
+            ),
+            (State::OneFinger { start, start_ms, .. }, Contacts::One(touch)) => (
+                State::OneFinger {
+                    start,
+                    start_ms,
+                    last: touch,
+                },
+                None,
+            ),
+            (
+                State::OneFinger {
+                    start,
+                    start_ms,
+                    last,
+                },
+                Contacts::None,
+            ) => {
+                let gesture = if now_ms.wrapping_sub(start_ms) <= config.swipe_max_ms {
+                    swipe_direction(start, last, config.swipe_min_distance).map(Gesture::Swipe)
+                } else {
+                    None
+                };
+                (State::Idle, gesture)
+            }
+            (State::Idle, Contacts::Two(first, second)) => {
+                (two_fingers(first, second, now_ms), None)
+            }
+            (State::OneFinger { start_ms, .. }, Contacts::Two(first, second)) => {
+                // the fingers rarely touch the display at exactly the same time
+                (two_fingers(first, second, start_ms), None)
+            }
+            (
+                State::TwoFingers {
+                    start_ms,
+                    start_center,
+                    initial_distance,
+                    scale_percent,
+                    pinching,
+                    moved,
+                },
+                Contacts::Two(first, second),
+            ) => {
+                let center = midpoint(first, second);
+                let max_movement = u32::from(config.tap_max_movement);
+                let moved = moved || distance(start_center, center) > max_movement;
+                let new_scale = scale(initial_distance, distance(first, second));
+                let change = if new_scale > 100 { new_scale - 100 } else { 100 - new_scale };
+                let pinching = pinching || change >= config.pinch_threshold_percent;
+                let gesture = if pinching && new_scale != scale_percent {
+                    Some(Gesture::Pinch {
+                        center,
+                        scale_percent: new_scale,
+                    })
+                } else {
+                    None
+                };
+                let scale_percent = if pinching { new_scale } else { scale_percent };
+                (
+                    State::TwoFingers {
+                        start_ms,
+                        start_center,
+                        initial_distance,
+                        scale_percent,
+                        pinching,
+                        moved,
+                    },
+                    gesture,
+                )
+            }
+            (two_fingers @ State::TwoFingers { .. }, Contacts::One(_)) => {
+                // the fingers are rarely lifted at exactly the same time
+                (two_fingers, None)
+            }
+            (
+                State::TwoFingers {
+                    start_ms,
+                    start_center,
+                    pinching,
+                    moved,
+                    ..
+                },
+                Contacts::None,
+            ) => {
+                let is_tap = !pinching
+                    && !moved
+                    && now_ms.wrapping_sub(start_ms) <= config.two_finger_tap_max_ms;
+                let gesture = if is_tap {
+                    Some(Gesture::TwoFingerTap {
+                        center: start_center,
+                    })
+                } else {
+                    None
+                };
+                (State::Idle, gesture)
+            }
+        };
+        self.state = state;
+        gesture
+    }
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> GestureRecognizer {
+        GestureRecognizer::new(GestureConfig::default())
+    }
+}
+
+fn two_fingers(first: Touch, second: Touch, start_ms: usize) -> State {
+    State::TwoFingers {
+        start_ms,
+        start_center: midpoint(first, second),
+        initial_distance: distance(first, second).max(1),
+        scale_percent: 100,
+        pinching: false,
+        moved: false,
+    }
+}
+
+fn swipe_direction(start: Touch, end: Touch, min_distance: u16) -> Option<SwipeDirection> {
+    let dx = i32::from(end.x) - i32::from(start.x);
+    let dy = i32::from(end.y) - i32::from(start.y);
+    let min_distance = i32::from(min_distance);
+    if dx.abs() >= dy.abs() && dx.abs() >= min_distance {
+        Some(if dx < 0 {
+            SwipeDirection::Left
+        } else {
+            SwipeDirection::Right
+        })
+    } else if dy.abs() > dx.abs() && dy.abs() >= min_distance {
+        Some(if dy < 0 {
+            SwipeDirection::Up
+        } else {
+            SwipeDirection::Down
+        })
+    } else {
+        None
+    }
+}
+
+fn midpoint(a: Touch, b: Touch) -> Touch {
+    Touch {
+        x: ((u32::from(a.x) + u32::from(b.x)) / 2) as u16,
+        y: ((u32::from(a.y) + u32::from(b.y)) / 2) as u16,
+    }
+}
+
+fn distance(a: Touch, b: Touch) -> u32 {
+    let dx = i32::from(a.x) - i32::from(b.x);
+    let dy = i32::from(a.y) - i32::from(b.y);
+    isqrt((dx * dx + dy * dy) as u32)
+}
+
+fn scale(initial_distance: u32, distance: u32) -> u16 {
+    (distance * 100 / initial_distance).min(u32::from(u16::max_value())) as u16
+}
+
+/// Integer square root using Newton's method.
+fn isqrt(n: u32) -> u32 {
+    if n < 2 {
+        return n;
+    }
+    let mut x = n;
+    let mut y = (x + 1) / 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Gesture, GestureRecognizer, SwipeDirection, Touch};
+    use alloc::vec::Vec;
+
+    fn t(x: u16, y: u16) -> Touch {
+        Touch { x, y }
+    }
+
+    /// Feeds a trace of `(time, touches)` samples and collects the recognized gestures.
+    fn run(trace: &[(usize, &[Touch])]) -> Vec<Gesture> {
+        let mut recognizer = GestureRecognizer::default();
+        trace
+            .iter()
+            .filter_map(|(ms, touches)| recognizer.update(touches, *ms))
+            .collect()
+    }
+
+    #[test]
+    fn swipe_left() {
+        let trace: &[(usize, &[Touch])] = &[
+            (0, &[t(400, 100)]),
+            (50, &[t(350, 104)]),
+            (100, &[t(280, 110)]),
+            (150, &[t(200, 112)]),
+            (200, &[]),
+        ];
+        assert_eq!(run(trace), [Gesture::Swipe(SwipeDirection::Left)]);
+    }
+
+    #[test]
+    fn swipe_down() {
+        let trace: &[(usize, &[Touch])] = &[
+            (0, &[t(100, 20)]),
+            (100, &[t(110, 90)]),
+            (200, &[t(112, 150)]),
+            (250, &[]),
+        ];
+        assert_eq!(run(trace), [Gesture::Swipe(SwipeDirection::Down)]);
+    }
+
+    #[test]
+    fn slow_or_short_moves_are_no_swipes() {
+        let slow: &[(usize, &[Touch])] = &[
+            (0, &[t(400, 100)]),
+            (500, &[t(300, 100)]),
+            (1000, &[t(200, 100)]),
+            (1050, &[]),
+        ];
+        assert_eq!(run(slow), []);
+        let short: &[(usize, &[Touch])] = &[(0, &[t(400, 100)]), (50, &[t(370, 100)]), (100, &[])];
+        assert_eq!(run(short), []);
+    }
+
+    #[test]
+    fn two_finger_tap() {
+        let trace: &[(usize, &[Touch])] = &[
+            (0, &[t(100, 100)]),
+            (20, &[t(100, 100), t(160, 100)]),
+            (120, &[t(101, 101), t(161, 100)]),
+            (180, &[t(161, 100)]),
+            (200, &[]),
+        ];
+        assert_eq!(run(trace), [Gesture::TwoFingerTap { center: t(130, 100) }]);
+    }
+
+    #[test]
+    fn pinch_out() {
+        let trace: &[(usize, &[Touch])] = &[
+            (0, &[t(200, 100), t(240, 100)]),
+            (50, &[t(195, 100), t(245, 100)]),
+            (100, &[t(180, 100), t(260, 100)]),
+            (150, &[t(180, 100), t(260, 100)]),
+            (200, &[t(160, 100), t(280, 100)]),
+            (250, &[]),
+        ];
+        assert_eq!(
+            run(trace),
+            [
+                Gesture::Pinch { center: t(220, 100), scale_percent: 125 },
+                Gesture::Pinch { center: t(220, 100), scale_percent: 200 },
+                Gesture::Pinch { center: t(220, 100), scale_percent: 300 },
+            ]
+        );
+    }
+
+    #[test]
+    fn pinch_is_no_tap() {
+        let trace: &[(usize, &[Touch])] = &[
+            (0, &[t(200, 100), t(300, 100)]),
+            (50, &[t(230, 100), t(270, 100)]),
+            (100, &[]),
+        ];
+        assert_eq!(run(trace), [Gesture::Pinch { center: t(250, 100), scale_percent: 40 }]);
+    }
+}
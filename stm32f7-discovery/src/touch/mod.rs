@@ -1,9 +1,25 @@
 //! Touchscreen functions.
+//!
+//! [`touches`] returns the raw touch points reported by the FT5336 touch controller. The
+//! [`TouchTracker`] turns these samples into [`TouchEvent`]s like taps, long presses, and drags.
+//! Gestures with multiple fingers and swipes are recognized by the [`GestureRecognizer`].
+//!
+//! The raw coordinates of the touch controller are corrected with the current [`Calibration`],
+//! which is set through [`set_calibration`].
+
+pub use self::calibration::{Calibration, TARGETS as CALIBRATION_TARGETS};
+pub use self::events::{event_task, Config, Events, TouchEvent, TouchTracker};
+pub use self::gestures::{Gesture, GestureConfig, GestureRecognizer, SwipeDirection};
 
 use crate::i2c::{self, I2C};
 use arrayvec::ArrayVec;
+use spin::Mutex;
 use stm32f7::stm32f7x6 as device;
 
+mod calibration;
+mod events;
+mod gestures;
+
 const FT5336_ADDRESS: i2c::Address = i2c::Address::bits_7(0b0111000);
 const FT5336_FAMILY_ID_REGISTER: u8 = 0xA8;
 const FT5336_STATUS_REGISTER: u8 = 0x02;
@@ -11,6 +27,18 @@ const FT5336_STATUS_REGISTER: u8 = 0x02;
 // Start locations for reading pressed touches
 const FT5336_DATA_REGISTERS: [u8; 5] = [0x03, 0x09, 0x0F, 0x15, 0x1B];
 
+static CALIBRATION: Mutex<Calibration> = Mutex::new(Calibration::IDENTITY);
+
+/// Sets the calibration that is applied to all touch points returned by [`touches`].
+pub fn set_calibration(calibration: Calibration) {
+    *CALIBRATION.lock() = calibration;
+}
+
+/// Returns the current calibration.
+pub fn calibration() -> Calibration {
+    *CALIBRATION.lock()
+}
+
 /// Checks the whether the device familiy ID register contains the expected value.
 pub fn check_family_id(i2c_3: &mut I2C<device::I2C3>) -> Result<(), i2c::Error> {
     i2c_3.connect::<u8, _>(FT5336_ADDRESS, |mut conn| {
@@ -20,7 +48,7 @@ pub fn check_family_id(i2c_3: &mut I2C<device::I2C3>) -> Result<(), i2c::Error>
     })
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Represents a touch point on the display at coordinates (x,y).
 pub struct Touch {
     /// The x coordinate of the touch point (horizontal).
@@ -29,8 +57,19 @@ pub struct Touch {
     pub y: u16,
 }
 
-/// Returns a list of active touch points.
+/// Returns a list of active touch points in display coordinates.
 pub fn touches(i2c_3: &mut I2C<device::I2C3>) -> Result<ArrayVec<[Touch; 5]>, i2c::Error> {
+    let calibration = calibration();
+    let mut touches = raw_touches(i2c_3)?;
+    for touch in touches.iter_mut() {
+        *touch = calibration.apply(*touch);
+    }
+    Ok(touches)
+}
+
+/// Returns a list of active touch points as reported by the touch controller, without applying
+/// the calibration.
+pub fn raw_touches(i2c_3: &mut I2C<device::I2C3>) -> Result<ArrayVec<[Touch; 5]>, i2c::Error> {
     let mut touches = ArrayVec::new();
     i2c_3.connect::<u8, _>(FT5336_ADDRESS, |mut conn| {
         let status = conn.read(FT5336_STATUS_REGISTER)?;